@@ -1,26 +1,185 @@
-use std::{fs, sync::Arc};
+use std::{fs, path::PathBuf, sync::Arc, time::Duration, time::SystemTime};
 
 use axum::Extension;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
 use bitcoinsuite_chronik_client::ChronikClient;
 use bitcoinsuite_error::Result;
-use explorer_server::{config, server::Server};
+use clap::{Args, Parser, Subcommand};
+use explorer_server::{
+    config::{self, Config, TlsConfig},
+    server::Server,
+};
+use tracing::{error, info};
+
+/// How often the certificate files are checked for changes, e.g. after a certbot renewal.
+const TLS_RELOAD_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How long in-flight HTTPS requests get to finish after a shutdown signal; plain HTTP waits for
+/// all of them.
+const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(30);
+
+#[derive(Parser)]
+#[clap(version, about = "eCash block explorer")]
+struct Cli {
+    /// Config file to use
+    #[clap(long, short, global = true, default_value = "config.toml")]
+    config: PathBuf,
+    /// Config file, as passed before there were subcommands
+    #[clap(hide = true)]
+    legacy_config: Option<PathBuf>,
+    #[clap(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Serve the explorer over HTTP(S); what runs if no subcommand is given
+    Serve(ServeArgs),
+    /// Copy finalized blocks, txs and token txs into the Postgres database in `[export]`
+    #[cfg(feature = "export")]
+    Export(ExportArgs),
+}
+
+#[derive(Args, Default)]
+struct ServeArgs {
+    /// Don't scan blocks for the feeds, e.g. on web replicas
+    #[clap(long)]
+    no_indexer: bool,
+}
+
+#[cfg(feature = "export")]
+#[derive(Args)]
+struct ExportArgs {
+    /// First block to export; defaults to the one after the last exported block
+    #[clap(long)]
+    from_height: Option<i32>,
+    /// Keep running and export new blocks as they finalize
+    #[clap(long)]
+    follow: bool,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let config_path = std::env::args().nth(1);
-    let config_path = config_path.as_deref().unwrap_or("config.toml");
-    let config_string = fs::read_to_string(config_path)?;
+    let cli = Cli::parse();
+    let config_path = cli.legacy_config.unwrap_or(cli.config);
+    let config_string = fs::read_to_string(&config_path)?;
     let config = config::load_config(&config_string)?;
 
-    let chronik = ChronikClient::new(config.chronik_api_url)?;
-    let base_dir = config.base_dir.unwrap_or_else(|| "../explorer-server".into());
-    let server = Arc::new(Server::setup(chronik, base_dir).await?);
-    let app = server.router().layer(Extension(server));
+    match cli.command {
+        Some(Command::Serve(args)) => serve(config, args).await,
+        #[cfg(feature = "export")]
+        Some(Command::Export(args)) => export(config, args).await,
+        None => serve(config, ServeArgs::default()).await,
+    }
+}
+
+#[cfg(feature = "export")]
+async fn export(config: Config, args: ExportArgs) -> Result<()> {
+    use explorer_server::{export::Exporter, logging};
+    use eyre::eyre;
 
-    axum::Server::bind(&config.host)
-        .serve(app.into_make_service())
-        .await
-        .unwrap();
+    let export_config = config
+        .export
+        .as_ref()
+        .ok_or_else(|| eyre!("export needs an [export] section in the config"))?;
+    logging::init(config.log_format);
+    let chronik = ChronikClient::new(config.chronik_api_url.clone())?;
+    let mut exporter = Exporter::connect(export_config).await?;
+    exporter.run(&chronik, args.from_height, args.follow).await
+}
+
+async fn serve(mut config: Config, args: ServeArgs) -> Result<()> {
+    if args.no_indexer {
+        config.run_indexer = false;
+    }
+
+    let chronik = ChronikClient::new(config.chronik_api_url.clone())?;
+    let server = Arc::new(Server::setup(chronik, &config).await?);
+    server.spawn_background_tasks();
+    let app = server.router().layer(Extension(Arc::clone(&server)));
+
+    let shutdown_server = Arc::clone(&server);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        info!("Shutting down, draining in-flight requests");
+        shutdown_server.shutdown();
+    });
+
+    match config.tls {
+        Some(tls) => {
+            let rustls_config = RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path).await?;
+            tokio::spawn(reload_tls_on_change(rustls_config.clone(), tls));
+            let handle = Handle::new();
+            let shutdown_handle = handle.clone();
+            tokio::spawn(async move {
+                server.until_shutdown().await;
+                shutdown_handle.graceful_shutdown(Some(SHUTDOWN_GRACE_PERIOD));
+            });
+            axum_server::bind_rustls(config.host, rustls_config)
+                .handle(handle)
+                .serve(app.into_make_service())
+                .await?;
+        }
+        None => {
+            axum::Server::bind(&config.host)
+                .serve(app.into_make_service())
+                .with_graceful_shutdown(async move { server.until_shutdown().await })
+                .await
+                .unwrap();
+        }
+    }
 
     Ok(())
 }
+
+/// Resolves on Ctrl+C or, on Unix, SIGTERM (what service managers and `docker stop` send).
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to listen for Ctrl+C");
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to listen for SIGTERM")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+async fn reload_tls_on_change(rustls_config: RustlsConfig, tls: TlsConfig) {
+    let mut last_modified = tls_files_modified(&tls);
+    let mut interval = tokio::time::interval(TLS_RELOAD_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let modified = tls_files_modified(&tls);
+        if modified == last_modified {
+            continue;
+        }
+        match rustls_config
+            .reload_from_pem_file(&tls.cert_path, &tls.key_path)
+            .await
+        {
+            Ok(()) => {
+                info!("Reloaded TLS certificate from {}", tls.cert_path.display());
+                last_modified = modified;
+            }
+            // Keeps serving the old certificate, e.g. if only one of the files was replaced yet
+            Err(err) => error!("Failed to reload TLS certificate: {}", err),
+        }
+    }
+}
+
+fn tls_files_modified(tls: &TlsConfig) -> Option<(SystemTime, SystemTime)> {
+    let cert_modified = fs::metadata(&tls.cert_path).ok()?.modified().ok()?;
+    let key_modified = fs::metadata(&tls.key_path).ok()?.modified().ok()?;
+    Some((cert_modified, key_modified))
+}
@@ -1,9 +1,34 @@
 const getAddress = () => window.location.pathname.split('/')[2];
+// Either "address" or "script", both pages share this script
+const getHistoryKind = () => window.location.pathname.split('/')[1];
+
+// UTXOs aren't embedded in the page, each balance's are fetched page by page once it's expanded
+const fetchUtxoPages = url => $.getJSON(url).then(response => {
+  if (!response.pagination.next) {
+    return response.data;
+  }
+  return fetchUtxoPages(response.pagination.next).then(rest => response.data.concat(rest));
+});
+
+const fetchUtxos = tokenId => {
+  const params = tokenId ? `?token_id=${tokenId}` : '';
+  return fetchUtxoPages(`/api/v1/${getHistoryKind()}/${getAddress()}/utxos${params}`);
+};
+
+// UTXOs already spent by an unconfirmed tx are still listed, but don't count towards the balance
+const renderOutpoint = row => {
+  return '<a href="/tx/' + row.txHash + '">' +
+    row.txHash + ':' + row.outIdx +
+    (row.isCoinbase ? '<div class="ui green horizontal label">Coinbase</div>' : '') +
+    '</a>' +
+    (row.spentIn ? ' <a class="ui orange horizontal label" href="/tx/' + row.spentIn + '">Spent in mempool</a>' : '');
+};
 
 var isSatsTableLoaded = false;
 function loadSatsTable() {
   if (!isSatsTableLoaded) {
-    webix.ui({
+    isSatsTableLoaded = true;
+    fetchUtxos().then(utxos => webix.ui({
       container: "sats-coins-table",
       view: "datatable",
       columns:[
@@ -12,12 +37,7 @@ function loadSatsTable() {
           header: "Outpoint",
           css: "hash",
           adjust: true,
-          template: function (row) {
-            return '<a href="/tx/' + row.txHash + '">' + 
-              row.txHash + ':' + row.outIdx +
-              (row.isCoinbase ? '<div class="ui green horizontal label">Coinbase</div>' : '') +
-              '</a>';
-          },
+          template: renderOutpoint,
         },
         {
           id: "blockHeight",
@@ -29,25 +49,27 @@ function loadSatsTable() {
         },
         {
           id: "amount",
-          header: "XEC amount",
+          header: "Amount",
           adjust: true,
           template: function (row) {
-            return renderSats(row.satsAmount) + ' XEC';
+            return renderXecAmount(row.satsAmount);
           },
         },
       ],
       autoheight: true,
       autowidth: true,
-      data: addrBalances["main"].utxos,
-    });
-    isSatsTableLoaded = true;
+      data: utxos,
+    }));
   }
 }
 
 var isTokenTableLoaded = {};
-function loadTokenTable(tokenId) {
+function loadTokenTable(tokenId, toggle) {
   if (!isTokenTableLoaded[tokenId]) {
-    webix.ui({
+    isTokenTableLoaded[tokenId] = true;
+    const tokenTicker = toggle.dataset.tokenTicker;
+    const decimals = parseInt(toggle.dataset.decimals);
+    fetchUtxos(tokenId).then(utxos => webix.ui({
       container: "tokens-coins-table-" + tokenId,
       view: "datatable",
       columns:[
@@ -56,12 +78,7 @@ function loadTokenTable(tokenId) {
           header: "Outpoint",
           css: "hash",
           adjust: true,
-          template: function (row) {
-            return '<a href="/tx/' + row.txHash + '">' + 
-              row.txHash + ':' + row.outIdx +
-              (row.isCoinbase ? '<div class="ui green horizontal label">Coinbase</div>' : '') +
-              '</a>';
-          },
+          template: renderOutpoint,
         },
         {
           id: "blockHeight",
@@ -73,26 +90,25 @@ function loadTokenTable(tokenId) {
         },
         {
           id: "tokenAmount",
-          header: addrBalances[tokenId].token?.tokenTicker + " amount",
+          header: tokenTicker + " amount",
           adjust: true,
           template: function (row) {
-            return renderAmount(row.tokenAmount, addrBalances[tokenId].token?.decimals) + ' ' + addrBalances[tokenId].token?.tokenTicker;
+            return renderAmount(row.tokenAmount, decimals) + ' ' + tokenTicker;
           },
         },
         {
           id: "satsAmount",
-          header: "XEC amount",
+          header: "Amount",
           adjust: true,
           template: function (row) {
-            return renderSats(row.satsAmount) + ' XEC';
+            return renderXecAmount(row.satsAmount);
           },
         },
       ],
       autoheight: true,
       autowidth: true,
-      data: addrBalances[tokenId].utxos,
-    });
-    isTokenTableLoaded[tokenId] = true;
+      data: utxos,
+    }));
   }
 }
 
@@ -100,7 +116,8 @@ const renderAge = timestamp => {
   if (timestamp == 0) {
     return '<div class="ui gray horizontal label">Mempool</div>';
   }
-  return moment(timestamp * 1000).fromNow();
+  const utc = moment.utc(timestamp * 1000).format('YYYY-MM-DD HH:mm:ss');
+  return '<span title="' + utc + ' UTC">' + moment(timestamp * 1000).fromNow() + '</span>';
 };
 
 const renderTimestamp = timestamp => {
@@ -146,7 +163,7 @@ const renderFeePerByte = (_value, _type, row) => {
   return renderInteger(Math.round(feePerByte * 1000)) + '/kB';
 };
 
-const renderAmountXEC = (_value, _type, row) => renderSats(row.stats.deltaSats) + ' XEC';
+const renderAmountXEC = (_value, _type, row) => renderXecAmount(row.stats.deltaSats);
 
 const renderToken = (_value, _type, row) => {
   if (row.token !== null) {
@@ -180,7 +197,7 @@ const datatable = () => {
       zeroRecords: '',
       emptyTable: '',
     },
-    ajax: `/api/address/${address}/transactions`,
+    ajax: `/api/v1/${getHistoryKind()}/${address}/transactions`,
     order: [],
     responsive: {
         details: {
@@ -202,7 +219,7 @@ const datatable = () => {
       { name: "fee", title: "Fee [sats]", className: "fee", render: renderFee },
       { name: "numInputs", data: 'numInputs', title: "Inputs" },
       { name: "numOutputs", data: 'numOutputs', title: "Outputs" },
-      { name: "deltaSats", data: 'deltaSats', title: "Amount XEC", render: renderAmountXEC },
+      { name: "deltaSats", data: 'deltaSats', title: "Amount", render: renderAmountXEC },
       { name: "token", title: "Amount Token", render: renderToken },
       { name: 'responsive', render: () => '' },
     ],
@@ -214,11 +231,16 @@ $('#address-txs-table').on('xhr.dt', () => {
 });
 
 const updateTable = (paginationRequest) => {
+  // Set by the history tabs of the address page
+  const tokenId = new URLSearchParams(window.location.search).get('token_id');
+  if (tokenId) {
+    paginationRequest = { ...paginationRequest, token_id: tokenId };
+  }
   const params = new URLSearchParams(paginationRequest).toString();
   const address = getAddress();
 
   updateTableLoading(true, 'address-txs-table');
-  $('#address-txs-table').dataTable().api().ajax.url(`/api/address/${address}/transactions?${params}`).load()
+  $('#address-txs-table').dataTable().api().ajax.url(`/api/v1/${getHistoryKind()}/${address}/transactions?${params}`).load()
 }
 
 const goToPage = (event, page) => {
@@ -65,7 +65,7 @@ const updateLoading = (status) => {
 // data fetching
 const updateTable = (startPosition, endPosition) => {
   updateLoading(true);
-  $('#blocks-table').dataTable().api().ajax.url(`/api/blocks/${endPosition}/${startPosition}`).load()
+  $('#blocks-table').dataTable().api().ajax.url(`/api/v1/blocks/${endPosition}/${startPosition}`).load()
 }
 
 
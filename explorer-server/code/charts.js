@@ -0,0 +1,80 @@
+const HASHRATE_UNITS = [
+  [1e18, 'EH/s'],
+  [1e15, 'PH/s'],
+  [1e12, 'TH/s'],
+  [1e9, 'GH/s'],
+];
+
+const formatHashrate = hashrate => {
+  for (const [factor, unit] of HASHRATE_UNITS) {
+    if (hashrate >= factor) {
+      return (hashrate / factor).toFixed(2) + ' ' + unit;
+    }
+  }
+  return (hashrate / 1e9).toFixed(2) + ' GH/s';
+};
+
+const renderInt = number => Intl.NumberFormat('en-EN').format(number);
+
+const renderLineChart = (container, points, valueKey, formatValue) => {
+  $$(container)?.destructor();
+  webix.ui({
+    id: container,
+    container,
+    view: 'chart',
+    type: 'line',
+    height: 300,
+    value: `#${valueKey}#`,
+    item: { radius: 0 },
+    line: { color: '#0074c2', width: 2 },
+    tooltip: { template: point => `#${renderInt(point.height)}: ${formatValue(point[valueKey])}` },
+    xAxis: { template: point => moment(point.timestamp * 1000).format('MMM D') },
+    yAxis: { template: value => formatValue(value) },
+    data: points,
+  });
+};
+
+const renderDailyChart = (container, points, valueKey, height) => {
+  $$(container)?.destructor();
+  webix.ui({
+    id: container,
+    container,
+    view: 'chart',
+    type: 'area',
+    height,
+    value: `#${valueKey}#`,
+    color: '#0074c2',
+    alpha: 0.4,
+    tooltip: { template: point => `${moment(point.timestamp * 1000).format('MMM D')}: ${renderInt(point[valueKey])}` },
+    xAxis: { template: point => moment(point.timestamp * 1000).format('MMM D') },
+    yAxis: { template: value => renderInt(value) },
+    data: points,
+  });
+};
+
+const loadTxCountChart = () => {
+  $.getJSON('/api/v1/charts/tx-count', response => {
+    renderDailyChart('tx-count-chart', response.points, 'numTxs', 120);
+  });
+};
+
+const loadHashrateCharts = () => {
+  const blockWindow = $('#hashrate-window').val();
+
+  $.getJSON(`/api/v1/stats/hashrate?window=${blockWindow}`, response => {
+    $('#hashrate-current').text(formatHashrate(response.hashrate));
+    renderLineChart('hashrate-chart', response.points, 'hashrate', formatHashrate);
+    renderLineChart('difficulty-chart', response.points, 'difficulty', value => value.toExponential(2));
+  });
+};
+
+$('#hashrate-window').on('change', loadHashrateCharts);
+
+webix.ready(() => {
+  if ($('#hashrate-chart').length) {
+    loadHashrateCharts();
+  }
+  if ($('#tx-count-chart').length) {
+    loadTxCountChart();
+  }
+});
@@ -77,6 +77,22 @@ function renderSats(sats) {
   }
 }
 
+// Mirrors the server: `?unit=` overrides the `unit` cookie
+var displayUnit = (
+  new URLSearchParams(window.location.search).get('unit') || getPreference('unit') || 'xec'
+).toLowerCase();
+
+function renderXecAmount(sats) {
+  switch (displayUnit) {
+    case 'sats':
+      return renderInteger(sats) + ' sats';
+    case 'bcha':
+      return renderAmount(sats, 8) + ' BCHA';
+    default:
+      return renderSats(sats) + ' XEC';
+  }
+}
+
 function renderTxHash(txHash) {
   return txHash.substr(0, 10) + '&hellip;' + txHash.substr(60, 4)
 }
@@ -94,7 +110,8 @@ function searchBarChange() {
 
 function searchButton() {
   var search = $('#search-bar').val();
-  location.href = '/search/' + search;
+  // Payment URIs have a query string of their own
+  location.href = '/search/' + encodeURIComponent(search);
 }
 
 function toggleTransactionScriptData() {
@@ -103,6 +120,14 @@ function toggleTransactionScriptData() {
   });
 }
 
+function selectOpReturnView(button) {
+  const opReturn = $(button).closest('.op-return');
+  opReturn.find('.button').removeClass('active');
+  $(button).addClass('active');
+  opReturn.find('.op-return__view').addClass('display-none');
+  opReturn.find(`.op-return__view--${$(button).data('view')}`).removeClass('display-none');
+}
+
 function minifyHash(hash) {
   return `${hash.slice(0, 25)}...${hash.slice(39, 64)}`;
 }
@@ -355,3 +380,38 @@ const scrollToBottom = () => {
   };
 
 }(window.pagination = window.pagination || {}, jQuery));
+
+function getPreference(name) {
+  const cookie = document.cookie
+    .split(';')
+    .map(pair => pair.trim().split('='))
+    .find(([key]) => key === name);
+  return cookie ? decodeURIComponent(cookie[1]) : '';
+}
+
+function setPreference(name, value) {
+  if (value) {
+    document.cookie = `${name}=${encodeURIComponent(value)}; path=/; max-age=31536000; samesite=lax`;
+  } else {
+    document.cookie = `${name}=; path=/; max-age=0`;
+  }
+  window.location.reload();
+}
+
+$(document).ready(() => {
+  const browserTimezone = Intl.DateTimeFormat().resolvedOptions().timeZone;
+  $('.preference-select[data-preference="tz"]').each((index, element) => {
+    if (browserTimezone && browserTimezone !== 'UTC') {
+      $(element).append(new Option(browserTimezone, browserTimezone));
+    }
+    const timezone = getPreference('tz');
+    if (timezone && timezone !== browserTimezone) {
+      $(element).append(new Option(timezone, timezone));
+    }
+  });
+
+  $('.preference-select').each((index, element) => {
+    element.value = getPreference(element.dataset.preference);
+    element.onchange = () => setPreference(element.dataset.preference, element.value);
+  });
+});
@@ -9,4 +9,26 @@ $(document).ready(() => {
 
     element.innerHTML = `${human_timestamp} <small>(UTC ${tzOffset})</small>`;
   });
+
+  $('.moment__eta').each((index, element) => {
+    if (!element.dataset.timestamp) {
+      return;
+    }
+
+    const timestamp = element.dataset.timestamp * 1000;
+    element.innerText = `~${moment(timestamp).fromNow()}`;
+  });
+
+  const updateMinutesSince = () => {
+    $('.moment__minutes-since').each((index, element) => {
+      if (!element.dataset.timestamp) {
+        return;
+      }
+
+      const secondsSince = Date.now() / 1000 - element.dataset.timestamp;
+      element.innerText = Math.max(0, Math.floor(secondsSince / 60));
+    });
+  };
+  updateMinutesSince();
+  setInterval(updateMinutesSince, 30 * 1000);
 });
@@ -0,0 +1,57 @@
+const STATUS_POLL_INTERVAL_MS = 30000;
+
+$(document).ready(() => {
+  const status = document.getElementById('tx-confirmation-status');
+  if (!status) {
+    return;
+  }
+  const txid = status.dataset.txid;
+  let isConfirmed = false;
+  let pollTimer = null;
+
+  const showConfirmed = (blockHash, blockHeight, confirmations) => {
+    if (isConfirmed) {
+      return;
+    }
+    isConfirmed = true;
+    clearInterval(pollTimer);
+    const plural = confirmations === 1 ? 'confirmation' : 'confirmations';
+    status.innerHTML =
+      `<a href="/block/${blockHash}">${renderInteger(blockHeight)}</a> ` +
+      `(${confirmations} ${plural})`;
+  };
+
+  const checkStatus = () => {
+    $.getJSON(`/api/v1/tx/${txid}/status`, response => {
+      if (response.isConfirmed) {
+        showConfirmed(response.blockHash, response.blockHeight, response.confirmations);
+      }
+    });
+  };
+
+  // Fallback for browsers or proxies that don't let the WebSocket through
+  const startPolling = () => {
+    if (isConfirmed || pollTimer !== null) {
+      return;
+    }
+    pollTimer = setInterval(checkStatus, STATUS_POLL_INTERVAL_MS);
+  };
+
+  if (!window.WebSocket) {
+    startPolling();
+    return;
+  }
+  const protocol = location.protocol === 'https:' ? 'wss:' : 'ws:';
+  const socket = new WebSocket(`${protocol}//${location.host}/ws?tx=${txid}`);
+  // The tx may have been mined between rendering the page and subscribing
+  socket.onopen = checkStatus;
+  socket.onmessage = message => {
+    const event = JSON.parse(message.data);
+    if (event.type === 'txConfirmed') {
+      showConfirmed(event.blockHash, event.blockHeight, event.confirmations);
+      socket.close();
+    }
+  };
+  socket.onerror = startPolling;
+  socket.onclose = startPolling;
+});
@@ -27,7 +27,7 @@ const renderOutput = (satsOutput, _type, row) => {
     var ticker = ' <a href="/tx/' + row.token.tokenId + '">' + row.token.tokenTicker + '</a>';
     return renderAmount(row.stats.tokenOutput, row.token.decimals) + ticker;
   }
-  return renderSats(row.stats.satsOutput) + ' XEC';
+  return renderXecAmount(row.stats.satsOutput);
 };
 
 
@@ -53,6 +53,20 @@ const goToPage = (event, page) => {
 };
 
 
+// Blocks can have 100k+ txs, so only the current page is fetched
+const blockTxsParams = () => {
+  const paginationRequest = window.pagination.generatePaginationRequest();
+  return new URLSearchParams(paginationRequest).toString();
+};
+
+const updateTable = () => {
+  const blockHash = $('#block-hash').text();
+
+  updateLoading(true);
+  $('#txs-table').dataTable().api().ajax.url(`/api/v1/block/${blockHash}/transactions?${blockTxsParams()}`).load();
+};
+
+
 // UI presentation elements
 const datatable = () => {
   const blockHash = $('#block-hash').text();
@@ -66,7 +80,7 @@ const datatable = () => {
       zeroRecords: '',
       emptyTable: '',
     },
-    ajax: `/api/block/${blockHash}/transactions`,
+    ajax: `/api/v1/block/${blockHash}/transactions?${blockTxsParams()}`,
     order: [],
     responsive: {
         details: {
@@ -126,10 +140,7 @@ $('#txs-table').on('xhr.dt', () => {
 const reRenderPage = params => {
   if (params) {
     window.state.updateParameters(params)
-
-    if (params.page) {
-      $('#txs-table').DataTable().page(params.page).draw(false);
-    }
+    updateTable();
   }
 
   const { currentPage, pageArray } = window.pagination.generatePaginationUIParams();
@@ -1,14 +1,185 @@
 use std::collections::HashMap;
 
-use bitcoinsuite_chronik_client::proto::{Block, SlpGenesisInfo, Token, Tx, TxHistoryPage};
-use bitcoinsuite_core::CashAddress;
+use bitcoinsuite_chronik_client::proto::{
+    Block, BlockInfo, OutPoint, SlpGenesisInfo, Token, Tx, TxHistoryPage, Utxo,
+};
 use bitcoinsuite_error::Result;
+use eyre::bail;
+use tracing::warn;
 
 use crate::{
-    blockchain::to_be_hex,
-    server_primitives::{JsonToken, JsonTx, JsonTxStats},
+    blockchain::{
+        block_fullness_percent, calculate_block_difficulty, destination_from_script,
+        miner_fund_script, op_return_protocol, op_return_pushes, to_be_hex, Destination,
+        FINALIZATION_DEPTH, STAKING_REWARD_PERCENT,
+    },
+    config::Network,
+    server_primitives::{
+        JsonBlock, JsonCoinbaseOutput, JsonCoinbaseSplit, JsonDoubleSpentInput, JsonOpReturnData,
+        JsonSlpError, JsonToken, JsonTx, JsonTxStats, JsonUtxo,
+    },
 };
 
+/// Opaque position in an address history, pointing just past the tx it was created from.
+///
+/// Chronik's offset pages shift whenever new txs arrive, so the cursor remembers the tx
+/// itself (and the page it was last seen on) rather than a bare offset.
+pub struct HistoryCursor {
+    pub block_height: i32,
+    pub txid: Vec<u8>,
+    pub page: usize,
+}
+
+impl HistoryCursor {
+    pub fn from_tx(tx: &Tx, page: usize) -> Self {
+        HistoryCursor {
+            block_height: tx.block.as_ref().map(|block| block.height).unwrap_or(-1),
+            txid: tx.txid.clone(),
+            page,
+        }
+    }
+
+    pub fn encode(&self) -> String {
+        let raw = format!(
+            "{}:{}:{}",
+            self.block_height,
+            hex::encode(&self.txid),
+            self.page
+        );
+        base64::encode_config(raw, base64::URL_SAFE_NO_PAD)
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self> {
+        let raw = base64::decode_config(cursor, base64::URL_SAFE_NO_PAD)?;
+        let raw = String::from_utf8(raw)?;
+        let mut parts = raw.split(':');
+        let (block_height, txid, page) = match (parts.next(), parts.next(), parts.next()) {
+            (Some(block_height), Some(txid), Some(page)) => (block_height, txid, page),
+            _ => bail!("Malformed cursor"),
+        };
+        let txid = hex::decode(txid)?;
+        if txid.len() != 32 {
+            bail!("Malformed cursor");
+        }
+
+        Ok(HistoryCursor {
+            block_height: block_height.parse()?,
+            txid,
+            page: page.parse()?,
+        })
+    }
+
+    /// Whether `tx` is mined strictly below the cursor's block, i.e. is certainly past it
+    /// in chronik's newest-first ordering even if the cursor tx itself got reorged out.
+    pub fn is_past(&self, tx: &Tx) -> bool {
+        let height = tx.block.as_ref().map(|block| block.height).unwrap_or(-1);
+        self.block_height != -1 && height != -1 && height < self.block_height
+    }
+}
+
+pub fn block_info_to_json(block: &BlockInfo, tip_height: i32) -> JsonBlock {
+    let confirmations = (tip_height - block.height + 1).max(0);
+    JsonBlock {
+        hash: to_be_hex(&block.hash),
+        height: block.height,
+        timestamp: block.timestamp,
+        difficulty: calculate_block_difficulty(block.n_bits),
+        size: block.block_size,
+        fullness_percent: block_fullness_percent(block.block_size),
+        num_txs: block.num_txs,
+        confirmations,
+        is_finalized: confirmations >= FINALIZATION_DEPTH,
+        coinbase_split: None,
+    }
+}
+
+/// Labels the paid outputs of a coinbase tx: the miner fund by its script, and the staking
+/// reward as the smallest other output worth at least [`STAKING_REWARD_PERCENT`] of the
+/// claimed reward. Nodes only enforce that lower bound, so the reward may be rounded up.
+/// The miner keeps everything else; a coinbase with a single such output has no staking
+/// reward, as before its activation.
+pub fn coinbase_split_to_json(coinbase: &Tx, network: Network) -> JsonCoinbaseSplit {
+    let miner_fund_script = miner_fund_script(network);
+    let is_miner_fund = |script: &[u8]| miner_fund_script == Some(script);
+    let total_sats: i64 = coinbase.outputs.iter().map(|output| output.value).sum();
+    let min_staking_reward_sats = total_sats * STAKING_REWARD_PERCENT / 100;
+    let other_outputs = coinbase
+        .outputs
+        .iter()
+        .enumerate()
+        .filter(|(_, output)| output.value > 0 && !is_miner_fund(&output.output_script))
+        .collect::<Vec<_>>();
+    let staking_reward_idx = if other_outputs.len() > 1 {
+        other_outputs
+            .iter()
+            .filter(|(_, output)| output.value >= min_staking_reward_sats)
+            .min_by_key(|(_, output)| output.value)
+            .map(|(out_idx, _)| *out_idx)
+    } else {
+        None
+    };
+
+    let mut split = JsonCoinbaseSplit {
+        miner_reward_sats: 0,
+        miner_fund_sats: 0,
+        staking_reward_sats: 0,
+        outputs: Vec::new(),
+    };
+    for (out_idx, output) in coinbase.outputs.iter().enumerate() {
+        if output.value == 0 {
+            continue;
+        }
+        let kind = if is_miner_fund(&output.output_script) {
+            split.miner_fund_sats += output.value;
+            "minerFund"
+        } else if staking_reward_idx == Some(out_idx) {
+            split.staking_reward_sats += output.value;
+            "stakingReward"
+        } else {
+            split.miner_reward_sats += output.value;
+            "minerReward"
+        };
+        let address =
+            match destination_from_script(network.satoshi_addr_prefix(), &output.output_script) {
+                Destination::Address(address) => Some(address.as_str().to_string()),
+                _ => None,
+            };
+        split.outputs.push(JsonCoinbaseOutput {
+            out_idx: out_idx as u32,
+            kind,
+            address,
+            output_script: hex::encode(&output.output_script),
+            sats_amount: output.value,
+        });
+    }
+    split
+}
+
+pub fn utxo_to_json(utxo: &Utxo) -> JsonUtxo {
+    let OutPoint { txid, out_idx } = utxo.outpoint.as_ref().expect("Impossible");
+    JsonUtxo {
+        tx_hash: to_be_hex(txid),
+        out_idx: *out_idx,
+        sats_amount: utxo.value,
+        token_amount: utxo
+            .slp_token
+            .as_ref()
+            .map(|token| token.amount)
+            .unwrap_or(0),
+        is_coinbase: utxo.is_coinbase,
+        block_height: utxo.block_height,
+        spent_in: None,
+    }
+}
+
+/// Key a UTXO's balance is grouped under: the token ID hex, or "main" for plain XEC.
+pub fn utxo_balance_key(utxo: &Utxo) -> String {
+    match (&utxo.slp_meta, &utxo.slp_token) {
+        (Some(slp_meta), Some(_)) => hex::encode(&slp_meta.token_id),
+        _ => "main".to_string(),
+    }
+}
+
 pub fn tokens_to_json(tokens: &HashMap<String, Token>) -> Result<HashMap<String, JsonToken>> {
     let mut json_tokens = HashMap::new();
 
@@ -26,7 +197,8 @@ pub fn tokens_to_json(tokens: &HashMap<String, Token>) -> Result<HashMap<String,
                     token_ticker,
                     token_name,
                     decimals: genesis_info.decimals,
-                    group_id: Some(hex::encode(&slp_meta.group_token_id)),
+                    group_id: (!slp_meta.group_token_id.is_empty())
+                        .then(|| hex::encode(&slp_meta.group_token_id)),
                 };
                 json_tokens.insert(token_id.clone(), json_token.clone());
             }
@@ -37,59 +209,137 @@ pub fn tokens_to_json(tokens: &HashMap<String, Token>) -> Result<HashMap<String,
 }
 
 pub fn tx_history_to_json(
-    address: &CashAddress,
+    output_script: &[u8],
     address_tx_history: TxHistoryPage,
     json_tokens: &HashMap<String, JsonToken>,
 ) -> Result<Vec<JsonTx>> {
     let mut json_txs = Vec::new();
-    let address_bytes = address.to_script().bytecode().to_vec();
 
     for tx in address_tx_history.txs.iter() {
-        let (block_height, timestamp) = match &tx.block {
-            Some(block) => (Some(block.height), block.timestamp),
-            None => (None, tx.time_first_seen),
-        };
+        json_txs.push(tx_to_json(tx, json_tokens, Some(output_script)));
+    }
 
-        let (token_id, token) = match &tx.slp_tx_data {
-            Some(slp_tx_data) => {
-                let slp_meta = slp_tx_data.slp_meta.as_ref().expect("Impossible");
-                let token_id = hex::encode(&slp_meta.token_id);
-                let json_token = json_tokens.get(&token_id);
+    Ok(json_txs)
+}
+
+pub fn tx_to_json(
+    tx: &Tx,
+    json_tokens: &HashMap<String, JsonToken>,
+    address_bytes: Option<&[u8]>,
+) -> JsonTx {
+    let (block_height, timestamp) = match &tx.block {
+        Some(block) => (Some(block.height), block.timestamp),
+        None => (None, tx.time_first_seen),
+    };
 
-                match json_token {
-                    Some(json_token) => (Some(token_id.clone()), Some(json_token.clone())),
-                    None => (Some(token_id.clone()), None),
-                }
+    let (token_id, token) = match &tx.slp_tx_data {
+        Some(slp_tx_data) => {
+            let slp_meta = slp_tx_data.slp_meta.as_ref().expect("Impossible");
+            let token_id = hex::encode(&slp_meta.token_id);
+            let json_token = json_tokens.get(&token_id);
+
+            match json_token {
+                Some(json_token) => (Some(token_id.clone()), Some(json_token.clone())),
+                None => (Some(token_id.clone()), None),
             }
-            None => (None, None),
-        };
+        }
+        None => (None, None),
+    };
 
-        let stats = calc_tx_stats(tx, Some(&address_bytes));
+    let stats = calc_tx_stats(tx, address_bytes);
 
-        json_txs.push(JsonTx {
-            tx_hash: to_be_hex(&tx.txid),
-            block_height,
-            timestamp,
-            is_coinbase: tx.is_coinbase,
-            size: tx.size as i32,
-            num_inputs: tx.inputs.len() as u32,
-            num_outputs: tx.outputs.len() as u32,
-            stats,
-            token_id,
-            token,
-        });
+    JsonTx {
+        tx_hash: to_be_hex(&tx.txid),
+        block_height,
+        position_in_block: None,
+        timestamp,
+        is_coinbase: tx.is_coinbase,
+        size: tx.size as i32,
+        num_inputs: tx.inputs.len() as u32,
+        num_outputs: tx.outputs.len() as u32,
+        stats,
+        token_id,
+        token,
+        slp_errors: slp_errors(tx, &HashMap::new()),
+        op_returns: tx_op_returns_to_json(tx),
+        double_spent_inputs: Vec::new(),
     }
+}
 
-    Ok(json_txs)
+/// Decoded data of the output at `out_idx`, if it's an OP_RETURN made of data pushes.
+pub fn op_return_to_json(out_idx: u32, script: &[u8]) -> Option<JsonOpReturnData> {
+    let pushes = op_return_pushes(script)?;
+    let (protocol, num_prefix_pushes) = op_return_protocol(&pushes);
+    let text = pushes[num_prefix_pushes..]
+        .iter()
+        .map(|push| String::from_utf8_lossy(push))
+        .collect::<Vec<_>>()
+        .join(" ");
+    Some(JsonOpReturnData {
+        out_idx,
+        protocol,
+        pushes: pushes.iter().map(hex::encode).collect(),
+        text,
+    })
+}
+
+/// Token section of a tx as written in its SLP OP_RETURN, for token types chronik can't
+/// parse like Mint Vault (type 2), so they can still be told apart.
+pub struct RawTokenSection {
+    pub token_type: u16,
+    /// "Fungible", "Mint Vault", "NFT1 Group", "NFT1 Child" or "Unknown"
+    pub token_type_name: &'static str,
+    /// e.g. "GENESIS" or "SEND"
+    pub tx_type: String,
+    /// Every push after the tx type, in hex
+    pub pushes: Vec<String>,
 }
 
+/// Token section of `tx` if its first output is an SLP OP_RETURN. Only needed where chronik
+/// didn't recognize the token type, since its raw type byte isn't kept otherwise.
+pub fn raw_token_section(tx: &Tx) -> Option<RawTokenSection> {
+    let pushes = op_return_pushes(&tx.outputs.first()?.output_script)?;
+    if op_return_protocol(&pushes).0 != "slp" {
+        return None;
+    }
+    let token_type = match *pushes.get(1)? {
+        [token_type] => u16::from(*token_type),
+        [high, low] => u16::from_be_bytes([*high, *low]),
+        _ => return None,
+    };
+    let token_type_name = match token_type {
+        0x01 => "Fungible",
+        0x02 => "Mint Vault",
+        0x41 => "NFT1 Child",
+        0x81 => "NFT1 Group",
+        _ => "Unknown",
+    };
+    Some(RawTokenSection {
+        token_type,
+        token_type_name,
+        tx_type: String::from_utf8_lossy(pushes.get(2)?).into_owned(),
+        pushes: pushes[3..].iter().map(hex::encode).collect(),
+    })
+}
+
+fn tx_op_returns_to_json(tx: &Tx) -> Vec<JsonOpReturnData> {
+    tx.outputs
+        .iter()
+        .enumerate()
+        .filter_map(|(out_idx, output)| op_return_to_json(out_idx as u32, &output.output_script))
+        .collect()
+}
+
+/// `first_position` is the position in the block of the first tx in `block.txs`, which may
+/// only hold a page of the block's txs.
 pub fn block_txs_to_json(
     block: Block,
     tokens_by_hex: &HashMap<String, Token>,
+    first_position: usize,
 ) -> Result<Vec<JsonTx>> {
     let mut json_txs = Vec::new();
 
-    for tx in block.txs.iter() {
+    for (idx, tx) in block.txs.iter().enumerate() {
         let (block_height, timestamp) = match &block.block_info {
             Some(block_info) => (Some(block_info.height), block_info.timestamp),
             None => (None, 0),
@@ -112,7 +362,7 @@ pub fn block_txs_to_json(
                 let genesis_info = match genesis_info {
                     Some(genesis_info) => genesis_info,
                     None => {
-                        eprintln!("No genesis info for token ID {}", token_id_hex);
+                        warn!("No genesis info for token ID {}", token_id_hex);
                         &default_genesis_info
                     }
                 };
@@ -127,7 +377,8 @@ pub fn block_txs_to_json(
                         token_ticker,
                         token_name,
                         decimals: genesis_info.decimals,
-                        group_id: Some(to_be_hex(&slp_meta.group_token_id)),
+                        group_id: (!slp_meta.group_token_id.is_empty())
+                            .then(|| to_be_hex(&slp_meta.group_token_id)),
                     }),
                 )
             }
@@ -139,6 +390,7 @@ pub fn block_txs_to_json(
         json_txs.push(JsonTx {
             tx_hash: to_be_hex(&tx.txid),
             block_height,
+            position_in_block: Some((first_position + idx) as u32),
             timestamp,
             is_coinbase: tx.is_coinbase,
             size: tx.size as i32,
@@ -147,12 +399,54 @@ pub fn block_txs_to_json(
             stats,
             token_id,
             token,
+            slp_errors: slp_errors(tx, &HashMap::new()),
+            op_returns: tx_op_returns_to_json(tx),
+            double_spent_inputs: Vec::new(),
         });
     }
 
     Ok(json_txs)
 }
 
+/// Why the tx is not a valid eToken tx (as reported by chronik), followed by every input
+/// whose tokens get burned by it.
+///
+/// Burned tokens are named by ticker where `token_tickers` (keyed by token ID hex) has one.
+pub fn slp_errors(tx: &Tx, token_tickers: &HashMap<String, String>) -> Vec<JsonSlpError> {
+    let mut errors = Vec::new();
+    if !tx.slp_error_msg.is_empty() {
+        errors.push(JsonSlpError {
+            section: "Transaction".to_string(),
+            message: tx.slp_error_msg.clone(),
+        });
+    }
+
+    for (input_idx, input) in tx.inputs.iter().enumerate() {
+        let slp_burn = match &input.slp_burn {
+            Some(slp_burn) => slp_burn,
+            None => continue,
+        };
+        let token_id = hex::encode(&slp_burn.token_id);
+        let token_id = match token_tickers.get(&token_id) {
+            Some(token_ticker) if !token_ticker.is_empty() => {
+                format!("{} ({})", token_ticker, token_id)
+            }
+            _ => token_id,
+        };
+        let message = match &slp_burn.token {
+            Some(token) if token.is_mint_baton => format!("Burns the mint baton of {}", token_id),
+            Some(token) => format!("Burns {} base units of {}", token.amount, token_id),
+            None => format!("Burns tokens of {}", token_id),
+        };
+        errors.push(JsonSlpError {
+            section: format!("Input #{}", input_idx),
+            message,
+        });
+    }
+
+    errors
+}
+
 pub fn calc_tx_stats(tx: &Tx, address_bytes: Option<&[u8]>) -> JsonTxStats {
     let sats_input = tx.inputs.iter().map(|input| input.value).sum();
     let sats_output = tx.outputs.iter().map(|output| output.value).sum();
@@ -207,3 +501,297 @@ pub fn calc_tx_stats(tx: &Tx, address_bytes: Option<&[u8]>) -> JsonTxStats {
         does_burn_slp,
     }
 }
+
+/// Inputs of `tx` whose previous output `prev_txs`, by txid, record as spent by another tx.
+/// Inputs whose previous tx isn't in `prev_txs` are skipped.
+pub fn double_spent_inputs(tx: &Tx, prev_txs: &HashMap<Vec<u8>, Tx>) -> Vec<JsonDoubleSpentInput> {
+    tx.inputs
+        .iter()
+        .enumerate()
+        .filter_map(|(input_idx, input)| {
+            let prev_out = input.prev_out.as_ref()?;
+            let prev_tx = prev_txs.get(&prev_out.txid)?;
+            let spent_by = prev_tx
+                .outputs
+                .get(prev_out.out_idx as usize)?
+                .spent_by
+                .as_ref()?;
+            if spent_by.txid == tx.txid {
+                return None;
+            }
+            Some(JsonDoubleSpentInput {
+                input_idx: input_idx as u32,
+                spent_by: to_be_hex(&spent_by.txid),
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use bitcoinsuite_chronik_client::proto::{OutPoint, Tx, TxInput, TxOutput};
+
+    use super::{coinbase_split_to_json, double_spent_inputs, raw_token_section, HistoryCursor};
+    use crate::{blockchain::miner_fund_script, config::Network};
+
+    fn p2pkh_script(hash_byte: u8) -> Vec<u8> {
+        let mut script = vec![0x76, 0xa9, 0x14];
+        script.extend([hash_byte; 20]);
+        script.extend([0x88, 0xac]);
+        script
+    }
+
+    fn coinbase(outputs: &[(Vec<u8>, i64)]) -> Tx {
+        Tx {
+            outputs: outputs
+                .iter()
+                .map(|(output_script, value)| TxOutput {
+                    output_script: output_script.clone(),
+                    value: *value,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn history_cursor_round_trip() {
+        let cursor = HistoryCursor {
+            block_height: 761_234,
+            txid: (0..32).collect(),
+            page: 3,
+        };
+        let decoded = HistoryCursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(decoded.block_height, cursor.block_height);
+        assert_eq!(decoded.txid, cursor.txid);
+        assert_eq!(decoded.page, cursor.page);
+    }
+
+    #[test]
+    fn history_cursor_round_trip_mempool() {
+        let cursor = HistoryCursor {
+            block_height: -1,
+            txid: vec![0xff; 32],
+            page: 0,
+        };
+        let decoded = HistoryCursor::decode(&cursor.encode()).unwrap();
+        assert_eq!(decoded.block_height, -1);
+        assert_eq!(decoded.txid, vec![0xff; 32]);
+        assert_eq!(decoded.page, 0);
+    }
+
+    #[test]
+    fn history_cursor_rejects_malformed() {
+        let encode = |raw: &str| base64::encode_config(raw, base64::URL_SAFE_NO_PAD);
+        assert!(HistoryCursor::decode("not base64!").is_err());
+        assert!(HistoryCursor::decode(&encode("12:abcd")).is_err());
+        assert!(HistoryCursor::decode(&encode(&format!("12:{}:0", "ab".repeat(31)))).is_err());
+        assert!(HistoryCursor::decode(&encode(&format!("x:{}:0", "ab".repeat(32)))).is_err());
+    }
+
+    #[test]
+    fn coinbase_split_labels_rounded_up_staking_reward() {
+        let miner_fund = miner_fund_script(Network::Mainnet).unwrap().to_vec();
+        let coinbase = coinbase(&[
+            (vec![0x6a], 0),
+            (p2pkh_script(1), 58_000_001),
+            (miner_fund, 32_000_001),
+            // 10% of the total is 10_000_000.3; nodes accept anything from 10_000_000 up
+            (p2pkh_script(2), 10_000_001),
+        ]);
+        let split = coinbase_split_to_json(&coinbase, Network::Mainnet);
+        assert_eq!(split.miner_reward_sats, 58_000_001);
+        assert_eq!(split.miner_fund_sats, 32_000_001);
+        assert_eq!(split.staking_reward_sats, 10_000_001);
+        let kinds = split
+            .outputs
+            .iter()
+            .map(|output| (output.out_idx, output.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            kinds,
+            [(1, "minerReward"), (2, "minerFund"), (3, "stakingReward")]
+        );
+        assert!(split.outputs[0]
+            .address
+            .as_deref()
+            .unwrap()
+            .starts_with("ecash:"));
+    }
+
+    #[test]
+    fn coinbase_split_without_staking_reward() {
+        let miner_fund = miner_fund_script(Network::Mainnet).unwrap().to_vec();
+        let coinbase = coinbase(&[(p2pkh_script(1), 92_000_000), (miner_fund, 8_000_000)]);
+        let split = coinbase_split_to_json(&coinbase, Network::Mainnet);
+        assert_eq!(split.miner_reward_sats, 92_000_000);
+        assert_eq!(split.miner_fund_sats, 8_000_000);
+        assert_eq!(split.staking_reward_sats, 0);
+    }
+
+    #[test]
+    fn coinbase_split_regtest_has_no_miner_fund() {
+        let mainnet_miner_fund = miner_fund_script(Network::Mainnet).unwrap().to_vec();
+        let coinbase = coinbase(&[(mainnet_miner_fund, 50_000_000)]);
+        let split = coinbase_split_to_json(&coinbase, Network::Regtest);
+        assert_eq!(split.miner_reward_sats, 50_000_000);
+        assert_eq!(split.miner_fund_sats, 0);
+        assert_eq!(split.outputs[0].kind, "minerReward");
+        assert!(split.outputs[0]
+            .address
+            .as_deref()
+            .unwrap()
+            .starts_with("ecregtest:"));
+    }
+
+    fn spending_tx(txid: u8, prev_outs: &[(u8, u32)]) -> Tx {
+        Tx {
+            txid: vec![txid; 32],
+            inputs: prev_outs
+                .iter()
+                .map(|&(prev_txid, out_idx)| TxInput {
+                    prev_out: Some(OutPoint {
+                        txid: vec![prev_txid; 32],
+                        out_idx,
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Prev tx with `spent_by[i]` spending its output `i`, if given.
+    fn prev_tx(txid: u8, spent_by: &[Option<u8>]) -> (Vec<u8>, Tx) {
+        let tx = Tx {
+            txid: vec![txid; 32],
+            outputs: spent_by
+                .iter()
+                .map(|spent_by| TxOutput {
+                    spent_by: spent_by.map(|spent_by| OutPoint {
+                        txid: vec![spent_by; 32],
+                        out_idx: 0,
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        };
+        (tx.txid.clone(), tx)
+    }
+
+    #[test]
+    fn double_spent_inputs_by_other_txs() {
+        let tx = spending_tx(0x01, &[(0xaa, 0), (0xaa, 1), (0xbb, 0), (0xcc, 0)]);
+        let prev_txs = HashMap::from([
+            prev_tx(0xaa, &[Some(0x01), Some(0x02)]),
+            prev_tx(0xbb, &[None]),
+        ]);
+        let double_spent = double_spent_inputs(&tx, &prev_txs);
+        // Spent by the tx itself, unspent, and unknown prev tx aren't flagged
+        assert_eq!(double_spent.len(), 1);
+        assert_eq!(double_spent[0].input_idx, 1);
+        assert_eq!(double_spent[0].spent_by, hex::encode([0x02; 32]));
+    }
+
+    #[test]
+    fn double_spent_inputs_out_of_range_output() {
+        let tx = spending_tx(0x01, &[(0xaa, 3)]);
+        let prev_txs = HashMap::from([prev_tx(0xaa, &[Some(0x02)])]);
+        assert!(double_spent_inputs(&tx, &prev_txs).is_empty());
+    }
+
+    /// Tx whose first output has the script `op_return`.
+    fn op_return_tx(op_return: &[u8]) -> Tx {
+        Tx {
+            outputs: vec![TxOutput {
+                output_script: op_return.to_vec(),
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    /// OP_RETURN with the SLP lokad ID followed by `pushes`.
+    fn slp_script(pushes: &[u8]) -> Vec<u8> {
+        let mut script = vec![0x6a, 0x04];
+        script.extend(b"SLP\0");
+        script.extend(pushes);
+        script
+    }
+
+    #[test]
+    fn raw_token_section_of_unknown_type() {
+        // Type 0x41 as 2 bytes, "SEND", a token ID and an amount
+        let mut pushes = vec![0x02, 0x00, 0x41, 0x04];
+        pushes.extend(b"SEND");
+        pushes.push(0x20);
+        pushes.extend([0xab; 32]);
+        pushes.extend([0x08, 0, 0, 0, 0, 0, 0, 0, 0x64]);
+        let section = raw_token_section(&op_return_tx(&slp_script(&pushes))).unwrap();
+        assert_eq!(section.token_type, 0x41);
+        assert_eq!(section.token_type_name, "NFT1 Child");
+        assert_eq!(section.tx_type, "SEND");
+        assert_eq!(
+            section.pushes,
+            vec![hex::encode([0xab; 32]), "0000000000000064".to_string()]
+        );
+
+        // One byte type, PUSHDATA1 tx type, no further pushes
+        let mut pushes = vec![0x01, 0x07, 0x4c, 0x07];
+        pushes.extend(b"GENESIS");
+        let section = raw_token_section(&op_return_tx(&slp_script(&pushes))).unwrap();
+        assert_eq!(section.token_type, 7);
+        assert_eq!(section.token_type_name, "Unknown");
+        assert_eq!(section.tx_type, "GENESIS");
+        assert!(section.pushes.is_empty());
+    }
+
+    #[test]
+    fn raw_token_section_rejects_malformed_scripts() {
+        let malformed: &[&[u8]] = &[
+            // No token type
+            &[],
+            // No tx type
+            &[0x01, 0x01],
+            // Token type of 3 bytes
+            &[0x03, 0x00, 0x00, 0x01, 0x04, b'S', b'E', b'N', b'D'],
+            // Push longer than the rest of the script
+            &[0x01, 0x01, 0x07, b'S', b'E', b'N'],
+            // PUSHDATA1 and PUSHDATA2 without their length
+            &[0x01, 0x01, 0x4c],
+            &[0x01, 0x01, 0x4d, 0x01],
+            // PUSHDATA4 with a length beyond the script
+            &[0x01, 0x01, 0x4e, 0xff, 0xff, 0xff, 0xff, 0x00],
+            // Non-push opcode
+            &[0x01, 0x01, 0x76],
+        ];
+        for pushes in malformed {
+            let tx = op_return_tx(&slp_script(pushes));
+            assert!(raw_token_section(&tx).is_none(), "{}", hex::encode(pushes));
+        }
+    }
+
+    #[test]
+    fn raw_token_section_needs_slp_first_output() {
+        let section_pushes = [0x01, 0x01, 0x04, b'S', b'E', b'N', b'D'];
+        // Other lokad ID
+        let mut script = vec![0x6a, 0x04];
+        script.extend(b"\0tab");
+        script.extend(section_pushes);
+        assert!(raw_token_section(&op_return_tx(&script)).is_none());
+        // Not an OP_RETURN
+        assert!(raw_token_section(&op_return_tx(&p2pkh_script(1))).is_none());
+        // SLP OP_RETURN not in the first output
+        let mut tx = op_return_tx(&p2pkh_script(1));
+        tx.outputs.push(TxOutput {
+            output_script: slp_script(&section_pushes),
+            ..Default::default()
+        });
+        assert!(raw_token_section(&tx).is_none());
+        assert!(raw_token_section(&Tx::default()).is_none());
+    }
+}
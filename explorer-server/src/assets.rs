@@ -0,0 +1,61 @@
+use std::{collections::HashMap, path::Path};
+
+use bitcoin::hashes::{sha256, Hash};
+use bitcoinsuite_error::Result;
+use once_cell::sync::OnceCell;
+
+/// Content hash of every static file, keyed by the URL path it's served under.
+static ASSET_HASHES: OnceCell<HashMap<String, String>> = OnceCell::new();
+
+/// Far-future caching is only safe for URLs that change whenever the file does.
+pub const IMMUTABLE_CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+pub const DEFAULT_CACHE_CONTROL: &str = "public, max-age=300";
+
+/// Hashes the files under `code/` and `assets/` once at startup; these are served straight from
+/// disk, so hashing what's actually deployed keeps URLs in step with the served contents.
+pub fn load_asset_hashes(base_dir: &Path) -> Result<()> {
+    let mut hashes = HashMap::new();
+    for dir in ["code", "assets"] {
+        hash_dir(&base_dir.join(dir), &format!("/{}", dir), &mut hashes)?;
+    }
+    // Only the first server set up in a process gets to define them
+    let _ = ASSET_HASHES.set(hashes);
+    Ok(())
+}
+
+fn hash_dir(dir: &Path, url_path: &str, hashes: &mut HashMap<String, String>) -> Result<()> {
+    if !dir.exists() {
+        return Ok(());
+    }
+    for entry in std::fs::read_dir(dir)? {
+        let entry = entry?;
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let entry_url_path = format!("{}/{}", url_path, file_name);
+        if entry.file_type()?.is_dir() {
+            hash_dir(&entry.path(), &entry_url_path, hashes)?;
+        } else {
+            let contents = std::fs::read(entry.path())?;
+            let hash = sha256::Hash::hash(&contents).to_string();
+            hashes.insert(entry_url_path, hash[..8].to_string());
+        }
+    }
+    Ok(())
+}
+
+/// Whether `hash` is the content hash of the file served under `path`, i.e. whether a
+/// fingerprinted URL refers to the file currently deployed.
+pub fn is_current_hash(path: &str, hash: &str) -> bool {
+    ASSET_HASHES
+        .get()
+        .and_then(|hashes| hashes.get(path))
+        .map(|current_hash| current_hash == hash)
+        .unwrap_or(false)
+}
+
+/// URL of a static file with its content hash appended, e.g. `/code/common.js?hash=1a2b3c4d`.
+pub fn asset_url(path: &str) -> String {
+    match ASSET_HASHES.get().and_then(|hashes| hashes.get(path)) {
+        Some(hash) => format!("{}?hash={}", path, hash),
+        None => path.to_string(),
+    }
+}
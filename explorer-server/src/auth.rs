@@ -0,0 +1,64 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use crate::{config::ApiKeyConfig, server_middleware::secrets_match};
+
+const RATE_LIMIT_WINDOW: Duration = Duration::from_secs(60);
+
+pub enum ApiKeyCheck {
+    Allowed,
+    Unknown,
+    RateLimited,
+}
+
+/// API keys from the config, each with its own fixed-window rate limit.
+pub struct ApiKeys {
+    keys: Vec<ApiKeyConfig>,
+    windows: Mutex<HashMap<String, RateLimitWindow>>,
+}
+
+struct RateLimitWindow {
+    started: Instant,
+    num_requests: u32,
+}
+
+impl ApiKeys {
+    pub fn new(keys: Vec<ApiKeyConfig>) -> Self {
+        ApiKeys {
+            keys,
+            windows: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn check(&self, given_key: &str) -> ApiKeyCheck {
+        let api_key = match self
+            .keys
+            .iter()
+            .find(|api_key| secrets_match(given_key, &api_key.key))
+        {
+            Some(api_key) => api_key,
+            None => return ApiKeyCheck::Unknown,
+        };
+
+        let mut windows = self.windows.lock().unwrap();
+        let window = windows
+            .entry(api_key.name.clone())
+            .or_insert_with(|| RateLimitWindow {
+                started: Instant::now(),
+                num_requests: 0,
+            });
+        if window.started.elapsed() >= RATE_LIMIT_WINDOW {
+            window.started = Instant::now();
+            window.num_requests = 0;
+        }
+        if window.num_requests >= api_key.requests_per_minute {
+            return ApiKeyCheck::RateLimited;
+        }
+        window.num_requests += 1;
+
+        ApiKeyCheck::Allowed
+    }
+}
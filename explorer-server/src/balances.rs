@@ -0,0 +1,157 @@
+use bitcoinsuite_chronik_client::proto::Utxo;
+
+use crate::{blockchain::COINBASE_MATURITY, server_primitives::JsonBalanceBreakdown};
+
+/// What the sats of a UTXO can be used for.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum UtxoKind {
+    /// Plain XEC that can be spent right away
+    Spendable,
+    /// Coinbase output that needs more confirmations before it can be spent
+    ImmatureCoinbase,
+    /// Sats carrying a token amount or mint baton; spending them moves or burns the token
+    TokenDust,
+}
+
+/// Classifies `utxo` as of a chain tip at `tip_height`.
+///
+/// Any UTXO chronik attached a token to is dust, mint batons (amount 0) included, even if
+/// the token itself is unknown or hidden as spam.
+pub fn utxo_kind(utxo: &Utxo, tip_height: i32) -> UtxoKind {
+    if utxo.slp_token.is_some() {
+        return UtxoKind::TokenDust;
+    }
+    // A tx spending it could at best be mined in the block after the tip
+    if utxo.is_coinbase && tip_height + 1 - utxo.block_height < COINBASE_MATURITY {
+        return UtxoKind::ImmatureCoinbase;
+    }
+    UtxoKind::Spendable
+}
+
+/// Sats of a script's UTXOs, split by what they can be used for.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct BalanceBreakdown {
+    pub spendable_sats: i64,
+    pub immature_coinbase_sats: i64,
+    pub token_dust_sats: i64,
+}
+
+impl BalanceBreakdown {
+    pub fn from_utxos<'a>(utxos: impl IntoIterator<Item = &'a Utxo>, tip_height: i32) -> Self {
+        let mut breakdown = BalanceBreakdown::default();
+        for utxo in utxos {
+            breakdown.add(utxo, tip_height);
+        }
+        breakdown
+    }
+
+    pub fn add(&mut self, utxo: &Utxo, tip_height: i32) {
+        match utxo_kind(utxo, tip_height) {
+            UtxoKind::Spendable => self.spendable_sats += utxo.value,
+            UtxoKind::ImmatureCoinbase => self.immature_coinbase_sats += utxo.value,
+            UtxoKind::TokenDust => self.token_dust_sats += utxo.value,
+        }
+    }
+
+    /// XEC balance as shown to users: everything not locked up in token UTXOs.
+    pub fn total_xec_sats(&self) -> i64 {
+        self.spendable_sats + self.immature_coinbase_sats
+    }
+
+    pub fn total_sats(&self) -> i64 {
+        self.total_xec_sats() + self.token_dust_sats
+    }
+
+    pub fn to_json(&self) -> JsonBalanceBreakdown {
+        JsonBalanceBreakdown {
+            spendable_sats: self.spendable_sats,
+            immature_coinbase_sats: self.immature_coinbase_sats,
+            token_dust_sats: self.token_dust_sats,
+            total_sats: self.total_sats(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_chronik_client::proto::{SlpMeta, SlpToken, Utxo};
+
+    use super::{utxo_kind, BalanceBreakdown, UtxoKind};
+
+    fn utxo(value: i64, block_height: i32, is_coinbase: bool) -> Utxo {
+        Utxo {
+            value,
+            block_height,
+            is_coinbase,
+            ..Default::default()
+        }
+    }
+
+    fn token_utxo(value: i64, amount: u64, is_mint_baton: bool) -> Utxo {
+        Utxo {
+            slp_meta: Some(SlpMeta {
+                token_id: vec![0xab; 32],
+                ..Default::default()
+            }),
+            slp_token: Some(SlpToken {
+                amount,
+                is_mint_baton,
+            }),
+            ..utxo(value, 1000, false)
+        }
+    }
+
+    #[test]
+    fn coinbase_matures_after_100_confirmations() {
+        let coinbase = utxo(625_000_000, 1000, true);
+        // Mined at 1000, so spendable in block 1100, i.e. once the tip is at 1099
+        assert_eq!(utxo_kind(&coinbase, 1000), UtxoKind::ImmatureCoinbase);
+        assert_eq!(utxo_kind(&coinbase, 1098), UtxoKind::ImmatureCoinbase);
+        assert_eq!(utxo_kind(&coinbase, 1099), UtxoKind::Spendable);
+        // Non-coinbase outputs are spendable right away
+        assert_eq!(
+            utxo_kind(&utxo(546, 1000, false), 1000),
+            UtxoKind::Spendable
+        );
+    }
+
+    #[test]
+    fn mint_baton_is_token_dust() {
+        let mint_baton = token_utxo(546, 0, true);
+        assert_eq!(utxo_kind(&mint_baton, 2000), UtxoKind::TokenDust);
+    }
+
+    #[test]
+    fn unknown_token_is_token_dust() {
+        // No genesis info is needed, chronik attaching a token is enough
+        let unknown_token = token_utxo(546, 1_000_000, false);
+        assert_eq!(utxo_kind(&unknown_token, 2000), UtxoKind::TokenDust);
+    }
+
+    #[test]
+    fn breakdown_totals() {
+        let utxos = [
+            utxo(10_000, 500, false),
+            utxo(20_000, 600, false),
+            utxo(625_000_000, 1950, true),
+            utxo(625_000_000, 1000, true),
+            token_utxo(546, 0, true),
+            token_utxo(1_000, 42, false),
+        ];
+        let breakdown = BalanceBreakdown::from_utxos(&utxos, 2000);
+        assert_eq!(breakdown.spendable_sats, 625_030_000);
+        assert_eq!(breakdown.immature_coinbase_sats, 625_000_000);
+        assert_eq!(breakdown.token_dust_sats, 1_546);
+        assert_eq!(breakdown.total_xec_sats(), 1_250_030_000);
+        assert_eq!(breakdown.total_sats(), 1_250_031_546);
+
+        let json = breakdown.to_json();
+        assert_eq!(json.total_sats, breakdown.total_sats());
+    }
+
+    #[test]
+    fn empty_breakdown() {
+        let breakdown = BalanceBreakdown::from_utxos(&Vec::<Utxo>::new(), 2000);
+        assert_eq!(breakdown.total_sats(), 0);
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use bitcoinsuite_chronik_client::proto::Block;
+use bitcoinsuite_core::Sha256d;
+
+use crate::blockchain::FINALIZATION_DEPTH;
+
+/// Total size of the cached blocks, as serialized on the chain.
+const MAX_CACHED_BYTES: u64 = 128_000_000;
+/// Larger blocks aren't cached, so one of them can't evict everything else.
+const MAX_BLOCK_BYTES: u64 = MAX_CACHED_BYTES / 8;
+
+/// Finalized blocks as returned by chronik, keyed by hash, plus the newest blocks, which the
+/// warmer puts here as they arrive.
+///
+/// Crawlers walk the whole chain, and every block page and block API request used to fetch
+/// the full block from chronik. Finalized blocks never change, so they're kept here until
+/// evicted, oldest first. Rendered pages aren't cached since they show the confirmations and
+/// depend on the visitor's preferences. A block is only dropped early if chronik reports it
+/// disconnected, which shouldn't happen this deep.
+pub struct BlockCache {
+    state: Mutex<CacheState>,
+}
+
+struct CacheState {
+    blocks: HashMap<Sha256d, Arc<Block>>,
+    /// Hashes in the order they were cached, oldest first
+    order: VecDeque<Sha256d>,
+    num_bytes: u64,
+}
+
+impl BlockCache {
+    pub fn new() -> Self {
+        BlockCache {
+            state: Mutex::new(CacheState {
+                blocks: HashMap::new(),
+                order: VecDeque::new(),
+                num_bytes: 0,
+            }),
+        }
+    }
+
+    pub fn get(&self, block_hash: &Sha256d) -> Option<Arc<Block>> {
+        self.state.lock().unwrap().blocks.get(block_hash).cloned()
+    }
+
+    /// Caches `block` if it's finalized at `tip_height` and not too large.
+    pub fn insert_if_final(&self, block_hash: &Sha256d, block: &Arc<Block>, tip_height: i32) {
+        let block_info = match &block.block_info {
+            Some(block_info) => block_info,
+            None => return,
+        };
+        let confirmations = tip_height - block_info.height + 1;
+        if confirmations >= FINALIZATION_DEPTH {
+            self.insert(block_hash, block);
+        }
+    }
+
+    /// Caches `block` regardless of its depth, unless it's too large. Only for blocks that
+    /// are dropped via [`BlockCache::invalidate`] if they get disconnected.
+    pub fn insert(&self, block_hash: &Sha256d, block: &Arc<Block>) {
+        let block_size = match &block.block_info {
+            Some(block_info) => block_info.block_size,
+            None => return,
+        };
+        if block_size > MAX_BLOCK_BYTES {
+            return;
+        }
+
+        let mut state = self.state.lock().unwrap();
+        if state.blocks.contains_key(block_hash) {
+            return;
+        }
+        state.num_bytes += block_size;
+        state.blocks.insert(block_hash.clone(), Arc::clone(block));
+        state.order.push_back(block_hash.clone());
+        while state.num_bytes > MAX_CACHED_BYTES {
+            let oldest = match state.order.pop_front() {
+                Some(oldest) => oldest,
+                None => break,
+            };
+            if let Some(evicted) = state.blocks.remove(&oldest) {
+                state.num_bytes -= evicted
+                    .block_info
+                    .as_ref()
+                    .map_or(0, |info| info.block_size);
+            }
+        }
+    }
+
+    pub fn invalidate(&self, block_hash: &Sha256d) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(removed) = state.blocks.remove(block_hash) {
+            state.num_bytes -= removed
+                .block_info
+                .as_ref()
+                .map_or(0, |info| info.block_size);
+            state.order.retain(|hash| hash != block_hash);
+        }
+    }
+}
+
+impl Default for BlockCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
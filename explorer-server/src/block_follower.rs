@@ -0,0 +1,172 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+use bitcoinsuite_chronik_client::{
+    proto::{BlockDetails, BlockInfo, Tx},
+    ChronikClient,
+};
+use bitcoinsuite_error::Result;
+use eyre::eyre;
+use tokio::sync::Notify;
+use tracing::warn;
+
+/// How many recent block hashes are remembered to detect reorgs.
+const REORG_DEPTH: usize = 10;
+const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// State derived from the blocks near the tip, fed by a [`BlockFollower`].
+pub trait BlockConsumer: Sync {
+    /// How far back from the tip blocks are needed when backfilling.
+    fn initial_blocks(&self) -> i32;
+
+    /// Adds the next block; blocks arrive in height order without gaps.
+    fn add_block(&self, block_info: &BlockInfo, block_details: &BlockDetails, txs: &[Tx]);
+
+    /// Drops everything added from blocks at `height` or above.
+    fn remove_blocks_from(&self, height: i32);
+}
+
+/// Fetches each new block from chronik once and hands it to every [`BlockConsumer`].
+///
+/// On startup (or when too far behind to stay contiguous) the consumers are cleared and
+/// backfilled, each from its own [`BlockConsumer::initial_blocks`]. When blocks get reorged,
+/// the consumers drop what they took from them and the replacement blocks are fed again.
+/// A reorg deeper than the remembered blocks is handled like a fresh start.
+pub struct BlockFollower {
+    /// Height and hash of the most recently fed blocks, oldest first
+    recent_blocks: Mutex<VecDeque<(i32, Vec<u8>)>>,
+    chain_changed: Notify,
+}
+
+impl BlockFollower {
+    pub fn new() -> Self {
+        BlockFollower {
+            recent_blocks: Mutex::new(VecDeque::new()),
+            chain_changed: Notify::new(),
+        }
+    }
+
+    /// Syncs right away instead of waiting for the next poll.
+    pub fn notify_chain_changed(&self) {
+        self.chain_changed.notify_one();
+    }
+
+    /// Height of the last block fed to the consumers, if any.
+    pub fn scanned_height(&self) -> Option<i32> {
+        let recent_blocks = self.recent_blocks.lock().unwrap();
+        recent_blocks.back().map(|(height, _)| *height)
+    }
+
+    pub async fn run(&self, chronik: &ChronikClient, consumers: &[&dyn BlockConsumer]) {
+        loop {
+            if let Err(err) = self.catch_up(chronik, consumers).await {
+                warn!(
+                    height = self.scanned_height(),
+                    "Block follower failed to sync: {}", err
+                );
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(POLL_INTERVAL) => {}
+                _ = self.chain_changed.notified() => {}
+            }
+        }
+    }
+
+    async fn catch_up(
+        &self,
+        chronik: &ChronikClient,
+        consumers: &[&dyn BlockConsumer],
+    ) -> Result<()> {
+        let max_initial_blocks = consumers
+            .iter()
+            .map(|consumer| consumer.initial_blocks())
+            .max()
+            .unwrap_or(0);
+        let blockchain_info = chronik.blockchain_info().await?;
+        let tip_height = blockchain_info.tip_height;
+        let (start_height, is_backfill) = match self.unwind_reorgs(chronik, consumers).await? {
+            Some(next_height) if next_height > tip_height - max_initial_blocks => {
+                (next_height, false)
+            }
+            _ => {
+                // Too far behind, reorged too deep or nothing fed yet to keep the consumers
+                // contiguous
+                self.recent_blocks.lock().unwrap().clear();
+                for consumer in consumers {
+                    consumer.remove_blocks_from(0);
+                }
+                ((tip_height - max_initial_blocks + 1).max(0), true)
+            }
+        };
+
+        for height in start_height..=tip_height {
+            let block = chronik.block_by_height(height).await?;
+            let block_info = block.block_info.ok_or_else(|| eyre!("Block has no info"))?;
+            let block_details = block
+                .block_details
+                .ok_or_else(|| eyre!("Block has no details"))?;
+
+            for consumer in consumers {
+                // Consumers with a shorter backfill only start at their own window
+                if is_backfill && height <= tip_height - consumer.initial_blocks() {
+                    continue;
+                }
+                consumer.add_block(&block_info, &block_details, &block.txs);
+            }
+
+            let mut recent_blocks = self.recent_blocks.lock().unwrap();
+            recent_blocks.push_back((block_info.height, block_info.hash));
+            if recent_blocks.len() > REORG_DEPTH {
+                recent_blocks.pop_front();
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Has the consumers drop everything from blocks that are no longer part of the chain and
+    /// returns the next height to feed, or `None` if nothing has been fed yet or the fork
+    /// point is older than the remembered blocks.
+    async fn unwind_reorgs(
+        &self,
+        chronik: &ChronikClient,
+        consumers: &[&dyn BlockConsumer],
+    ) -> Result<Option<i32>> {
+        let recent_blocks = self.recent_blocks.lock().unwrap().clone();
+        let (first_height, last_height) = match (recent_blocks.front(), recent_blocks.back()) {
+            (Some((first_height, _)), Some((last_height, _))) => (*first_height, *last_height),
+            _ => return Ok(None),
+        };
+
+        let block_infos = chronik.blocks(first_height, last_height).await?;
+        let fork_height = recent_blocks
+            .iter()
+            .find(|(height, hash)| {
+                !block_infos
+                    .iter()
+                    .any(|block_info| block_info.height == *height && &block_info.hash == hash)
+            })
+            .map(|(height, _)| *height);
+
+        match fork_height {
+            // Even the oldest remembered block got reorged, so the fork may be deeper still
+            Some(fork_height) if fork_height == first_height => Ok(None),
+            Some(fork_height) => {
+                self.recent_blocks
+                    .lock()
+                    .unwrap()
+                    .retain(|(height, _)| *height < fork_height);
+                for consumer in consumers {
+                    consumer.remove_blocks_from(fork_height);
+                }
+                Ok(Some(fork_height))
+            }
+            None => Ok(Some(last_height + 1)),
+        }
+    }
+}
+
+impl Default for BlockFollower {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,6 +1,10 @@
+use bitcoin::hashes::{sha256d, Hash};
 use bitcoinsuite_chronik_client::ScriptType;
 use bitcoinsuite_core::{AddressType, CashAddress, Hashed, Op, Script, ShaRmd160};
 use bitcoinsuite_error::Result;
+use eyre::{bail, eyre};
+
+use crate::config::Network;
 
 pub fn to_be_hex(slice: &[u8]) -> String {
     let mut vec = slice.to_vec();
@@ -54,7 +58,162 @@ pub fn destination_from_script<'a>(prefix: &'a str, script: &[u8]) -> Destinatio
     }
 }
 
-pub fn to_legacy_address(cash_address: &CashAddress) -> String {
+/// Parses a cashaddr with a prefix of `network` (e.g. `bitcoincash:` on mainnet), a cashaddr
+/// without prefix or a legacy base58 address, and returns it as a cashaddr with the network's
+/// prefix. Addresses of other networks are rejected rather than converted.
+pub fn parse_address(address: &str, network: Network) -> Result<CashAddress<'static>> {
+    use bitcoin::util::address::Payload;
+
+    let (addr_type, hash, address_network) = match address.parse::<bitcoin::Address>() {
+        Ok(legacy_address) => {
+            let (addr_type, hash) = match legacy_address.payload {
+                Payload::PubkeyHash(hash) => (AddressType::P2PKH, hash.into_inner().to_vec()),
+                Payload::ScriptHash(hash) => (AddressType::P2SH, hash.into_inner().to_vec()),
+                Payload::WitnessProgram { .. } => bail!("Segwit addresses are not supported"),
+            };
+            // Legacy regtest addresses share the version bytes of testnet
+            let address_network = match (legacy_address.network, network) {
+                (bitcoin::Network::Bitcoin, _) => Network::Mainnet,
+                (_, Network::Regtest) => Network::Regtest,
+                _ => Network::Testnet,
+            };
+            (addr_type, hash, address_network)
+        }
+        Err(_) => {
+            let (cash_address, address_network) = if address.contains(':') {
+                let address_network =
+                    address_network(address).ok_or_else(|| eyre!("Unknown address prefix"))?;
+                (CashAddress::parse_cow(address.into())?, address_network)
+            } else {
+                let address = format!("{}:{}", network.satoshi_addr_prefix(), address);
+                (CashAddress::parse_cow(address.into())?, network)
+            };
+            (
+                cash_address.addr_type(),
+                cash_address.hash().as_slice().to_vec(),
+                address_network,
+            )
+        }
+    };
+    if address_network != network {
+        bail!(
+            "Address is for {}, this explorer is for {}",
+            address_network.name(),
+            network.name()
+        );
+    }
+    Ok(CashAddress::from_hash(
+        network.satoshi_addr_prefix(),
+        addr_type,
+        ShaRmd160::from_slice(&hash)?,
+    ))
+}
+
+pub fn address_type_name(addr_type: AddressType) -> &'static str {
+    match addr_type {
+        AddressType::P2PKH => "P2PKH",
+        AddressType::P2SH => "P2SH",
+    }
+}
+
+/// Network an address is meant for, judging by its cashaddr prefix.
+pub fn address_network(address: &str) -> Option<Network> {
+    let (prefix, _) = address.split_once(':')?;
+    let network = match prefix.to_ascii_lowercase().as_str() {
+        "ecash" | "etoken" | "bitcoincash" | "simpleledger" => Network::Mainnet,
+        "ectest" | "bchtest" | "slptest" => Network::Testnet,
+        "ecregtest" | "bchreg" | "slpreg" => Network::Regtest,
+        _ => return None,
+    };
+    Some(network)
+}
+
+/// Address and `amount` parameter of a BIP21 payment URI like
+/// `ecash:qq...?amount=12.5&label=Shop`; other parameters are dropped.
+pub fn parse_payment_uri(uri: &str) -> Option<(&str, Option<&str>)> {
+    let (address, params) = uri.split_once('?')?;
+    let amount = params
+        .split('&')
+        .filter_map(|param| param.split_once('='))
+        .find(|(key, _)| *key == "amount")
+        .map(|(_, amount)| amount);
+    Some((address, amount))
+}
+
+/// Sats of an amount in XEC as written in payment URIs, e.g. "12.5"; `None` if it has more
+/// than the 2 decimals XEC has or isn't a plain positive number.
+pub fn parse_xec_amount(amount: &str) -> Option<i64> {
+    let (coins, cents) = match amount.split_once('.') {
+        Some((_, "")) => return None,
+        Some((coins, cents)) => (coins, cents),
+        None => (amount, ""),
+    };
+    let all_digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit());
+    if coins.is_empty() || cents.len() > 2 || !all_digits(coins) || !all_digits(cents) {
+        return None;
+    }
+    let cents = format!("{:0<2}", cents).parse::<i64>().ok()?;
+    let sats = coins
+        .parse::<i64>()
+        .ok()?
+        .checked_mul(100)?
+        .checked_add(cents)?;
+    if sats > 0 {
+        Some(sats)
+    } else {
+        None
+    }
+}
+
+/// Data pushes of an OP_RETURN script, `None` if it isn't one or has non-push ops.
+pub fn op_return_pushes(script: &[u8]) -> Option<Vec<&[u8]>> {
+    const OP_RETURN: u8 = 0x6a;
+    const OP_PUSHDATA1: u8 = 0x4c;
+    const OP_PUSHDATA2: u8 = 0x4d;
+    const OP_PUSHDATA4: u8 = 0x4e;
+
+    let mut rest = match script {
+        [OP_RETURN, rest @ ..] => rest,
+        _ => return None,
+    };
+    let mut pushes = Vec::new();
+    while let [opcode, tail @ ..] = rest {
+        let (len, tail) = match *opcode {
+            len @ 0x01..=0x4b => (len as usize, tail),
+            OP_PUSHDATA1 => (*tail.first()? as usize, tail.get(1..)?),
+            OP_PUSHDATA2 => (
+                u16::from_le_bytes(tail.get(..2)?.try_into().ok()?) as usize,
+                tail.get(2..)?,
+            ),
+            OP_PUSHDATA4 => (
+                u32::from_le_bytes(tail.get(..4)?.try_into().ok()?) as usize,
+                tail.get(4..)?,
+            ),
+            _ => return None,
+        };
+        pushes.push(tail.get(..len)?);
+        rest = tail.get(len..)?;
+    }
+    Some(pushes)
+}
+
+/// Protocol of OP_RETURN pushes ("slp", "cashtab", "memo" or "other") and how many of the
+/// leading pushes only identify the protocol.
+pub fn op_return_protocol(pushes: &[&[u8]]) -> (&'static str, usize) {
+    const SLP_LOKAD_ID: &[u8] = b"SLP\0";
+    const CASHTAB_LOKAD_ID: &[u8] = b"\0tab";
+    const MEMO_PREFIX: u8 = 0x6d;
+
+    match pushes {
+        [lokad_id, ..] if *lokad_id == SLP_LOKAD_ID => ("slp", 1),
+        [lokad_id, ..] if *lokad_id == CASHTAB_LOKAD_ID => ("cashtab", 1),
+        [[MEMO_PREFIX, _], ..] => ("memo", 1),
+        _ => ("other", 0),
+    }
+}
+
+/// Base58 form of `cash_address`, with the version bytes of `network`.
+pub fn to_legacy_address(cash_address: &CashAddress, network: Network) -> String {
     use bitcoin::{
         hashes::{hash160, Hash},
         PubkeyHash, ScriptHash,
@@ -64,8 +223,12 @@ pub fn to_legacy_address(cash_address: &CashAddress) -> String {
         AddressType::P2PKH => bitcoin::Script::new_p2pkh(&PubkeyHash::from_hash(hash)),
         AddressType::P2SH => bitcoin::Script::new_p2sh(&ScriptHash::from_hash(hash)),
     };
-    let address =
-        bitcoin::Address::from_script(&script, bitcoin::Network::Bitcoin).expect("Invalid address");
+    let network = match network {
+        Network::Mainnet => bitcoin::Network::Bitcoin,
+        Network::Testnet => bitcoin::Network::Testnet,
+        Network::Regtest => bitcoin::Network::Regtest,
+    };
+    let address = bitcoin::Address::from_script(&script, network).expect("Invalid address");
     address.to_string()
 }
 
@@ -77,6 +240,87 @@ pub fn calculate_block_difficulty(n_bits: u32) -> f64 {
     max_target / (n_word * 2f64.powi(8 * (n_size as i32 - 3)))
 }
 
+/// Merkle branch of the tx at `index` among the block's `txids`, lowest level first, along
+/// with the merkle root. Hashes are in internal byte order, like chronik's txids.
+pub fn merkle_branch(txids: &[Vec<u8>], mut index: usize) -> (Vec<Vec<u8>>, Vec<u8>) {
+    let mut level = txids.to_vec();
+    let mut branch = Vec::new();
+    while level.len() > 1 {
+        if level.len() % 2 == 1 {
+            level.push(level[level.len() - 1].clone());
+        }
+        branch.push(level[index ^ 1].clone());
+        level = level
+            .chunks(2)
+            .map(|pair| {
+                sha256d::Hash::hash(&[&pair[0][..], &pair[1][..]].concat())
+                    .into_inner()
+                    .to_vec()
+            })
+            .collect();
+        index /= 2;
+    }
+    (branch, level.into_iter().next().unwrap_or_default())
+}
+
+pub const COIN: i64 = 100_000_000;
+/// eCash nodes refuse reorgs deeper than this, so blocks buried this deep are final.
+pub const FINALIZATION_DEPTH: i32 = 10;
+pub const TARGET_BLOCK_SPACING_SECS: i64 = 600;
+/// Coinbase outputs can only be spent once their block has this many confirmations.
+pub const COINBASE_MATURITY: i32 = 100;
+/// Consensus limit on the size of a block, in bytes.
+pub const MAX_BLOCK_SIZE: u64 = 32_000_000;
+pub const HALVING_INTERVAL: i32 = 210_000;
+const INITIAL_SUBSIDY: i64 = 50 * COIN;
+/// P2SH script of the miner fund, ecash:pqnqv9lt7e5vjyp0w88zf2af0l92l8rxdgnlxww9j4.
+const MINER_FUND_SCRIPT: [u8; 23] = [
+    0xa9, 0x14, 0x26, 0x06, 0x17, 0xeb, 0xf6, 0x68, 0xc9, 0x10, 0x2f, 0x71, 0xce, 0x24, 0xab, 0xa9,
+    0x7f, 0xca, 0xaf, 0x9c, 0x66, 0x6a, 0x87,
+];
+/// Share of the block reward paid to the avalanche staking reward winner.
+pub const STAKING_REWARD_PERCENT: i64 = 10;
+
+/// Script coinbase txs on `network` have to pay the miner fund to, if any.
+///
+/// Nodes decode the miner fund address with mainnet parameters, so testnet pays the same
+/// script; regtest doesn't enforce the miner fund.
+pub fn miner_fund_script(network: Network) -> Option<&'static [u8]> {
+    match network {
+        Network::Mainnet | Network::Testnet => Some(&MINER_FUND_SCRIPT),
+        Network::Regtest => None,
+    }
+}
+
+pub fn block_fullness_percent(block_size: u64) -> f64 {
+    block_size as f64 * 100.0 / MAX_BLOCK_SIZE as f64
+}
+
+/// Coinbase subsidy of the block at `height`, in sats.
+pub fn block_subsidy(height: i32) -> i64 {
+    let halvings = height / HALVING_INTERVAL;
+    if halvings >= 64 {
+        return 0;
+    }
+    INITIAL_SUBSIDY >> halvings
+}
+
+/// Sum of all subsidies from genesis up to and including the block at `height`, in sats.
+pub fn total_issued_sats(height: i32) -> i64 {
+    let mut issued = 0;
+    let mut era_start = 0;
+    while era_start <= height {
+        let subsidy = block_subsidy(era_start);
+        if subsidy == 0 {
+            break;
+        }
+        let era_end = (era_start + HALVING_INTERVAL - 1).min(height);
+        issued += subsidy * i64::from(era_end - era_start + 1);
+        era_start += HALVING_INTERVAL;
+    }
+    issued
+}
+
 pub fn cash_addr_to_script_type_payload(addr: &CashAddress) -> (ScriptType, [u8; 20]) {
     let script_type = match addr.addr_type() {
         AddressType::P2PKH => ScriptType::P2pkh,
@@ -86,3 +330,221 @@ pub fn cash_addr_to_script_type_payload(addr: &CashAddress) -> (ScriptType, [u8;
 
     (script_type, *script_payload)
 }
+
+/// Chronik indexes P2PK, P2PKH and P2SH scripts by their payload and everything else by the
+/// full script.
+pub fn script_to_script_type_payload(script: &[u8]) -> (ScriptType, Vec<u8>) {
+    const OP_DUP: u8 = 118;
+    const OP_EQUAL: u8 = 135;
+    const OP_EQUALVERIFY: u8 = 136;
+    const OP_HASH160: u8 = 169;
+    const OP_CHECKSIG: u8 = 172;
+
+    match script {
+        [OP_DUP, OP_HASH160, 20, hash @ .., OP_EQUALVERIFY, OP_CHECKSIG] if hash.len() == 20 => {
+            (ScriptType::P2pkh, hash.to_vec())
+        }
+        [OP_HASH160, 20, hash @ .., OP_EQUAL] if hash.len() == 20 => {
+            (ScriptType::P2sh, hash.to_vec())
+        }
+        [33, pk @ .., OP_CHECKSIG] if pk.len() == 33 => (ScriptType::P2pk, pk.to_vec()),
+        [65, pk @ .., OP_CHECKSIG] if pk.len() == 65 => (ScriptType::P2pk, pk.to_vec()),
+        _ => (ScriptType::Other, script.to_vec()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_core::{AddressType, CashAddress, Hashed, ShaRmd160};
+
+    use super::{
+        from_be_hex, merkle_branch, parse_address, parse_payment_uri, parse_xec_amount, to_be_hex,
+        to_legacy_address,
+    };
+    use crate::config::Network;
+
+    /// Txids of block 100000, shared by BTC and eCash.
+    const BLOCK_100000_TXIDS: [&str; 4] = [
+        "8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87",
+        "fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4",
+        "6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4",
+        "e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d",
+    ];
+    const BLOCK_100000_MERKLE_ROOT: &str =
+        "f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766";
+
+    fn block_100000_txids() -> Vec<Vec<u8>> {
+        BLOCK_100000_TXIDS
+            .iter()
+            .map(|txid| from_be_hex(txid).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn merkle_branch_block_100000() {
+        let txids = block_100000_txids();
+        let (branch, merkle_root) = merkle_branch(&txids, 2);
+        assert_eq!(to_be_hex(&merkle_root), BLOCK_100000_MERKLE_ROOT);
+        let branch = branch
+            .iter()
+            .map(|hash| to_be_hex(hash))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            branch,
+            [
+                BLOCK_100000_TXIDS[3],
+                "ccdafb73d8dcd0173d5d5c3c9a0770d0b3953db889dab99ef05b1907518cb815",
+            ],
+        );
+    }
+
+    #[test]
+    fn merkle_branch_same_root_for_every_index() {
+        let txids = block_100000_txids();
+        for index in 0..txids.len() {
+            let (branch, merkle_root) = merkle_branch(&txids, index);
+            assert_eq!(branch.len(), 2);
+            assert_eq!(to_be_hex(&merkle_root), BLOCK_100000_MERKLE_ROOT);
+        }
+    }
+
+    #[test]
+    fn merkle_branch_coinbase_only() {
+        let txids = block_100000_txids()[..1].to_vec();
+        let (branch, merkle_root) = merkle_branch(&txids, 0);
+        assert!(branch.is_empty());
+        assert_eq!(merkle_root, txids[0]);
+    }
+
+    #[test]
+    fn merkle_branch_odd_level_pairs_last_with_itself() {
+        let txids = block_100000_txids()[..3].to_vec();
+        let (branch, _) = merkle_branch(&txids, 2);
+        assert_eq!(branch[0], txids[2]);
+    }
+
+    #[test]
+    fn payment_uri_amount() {
+        assert_eq!(
+            parse_payment_uri("ecash:qq123?amount=12.5"),
+            Some(("ecash:qq123", Some("12.5")))
+        );
+        assert_eq!(
+            parse_payment_uri("ecash:qq123?label=Shop&amount=3&message=Hi"),
+            Some(("ecash:qq123", Some("3")))
+        );
+        assert_eq!(
+            parse_payment_uri("ecash:qq123?label=Shop"),
+            Some(("ecash:qq123", None))
+        );
+        // Plain addresses aren't payment URIs
+        assert_eq!(parse_payment_uri("ecash:qq123"), None);
+    }
+
+    #[test]
+    fn xec_amounts() {
+        assert_eq!(parse_xec_amount("12.5"), Some(1250));
+        assert_eq!(parse_xec_amount("12.05"), Some(1205));
+        assert_eq!(parse_xec_amount("7"), Some(700));
+        assert_eq!(parse_xec_amount("0.01"), Some(1));
+    }
+
+    #[test]
+    fn bad_xec_amounts() {
+        for amount in [
+            "", "0", "0.00", "-5", "+5", "1.234", "1.", ".5", "1e3", "abc", "1,5", " 1",
+        ] {
+            assert_eq!(parse_xec_amount(amount), None, "{:?}", amount);
+        }
+        // Overflows i64 once in sats
+        assert_eq!(parse_xec_amount("92233720368547758.07"), Some(i64::MAX));
+        assert_eq!(parse_xec_amount("92233720368547758.08"), None);
+        assert_eq!(parse_xec_amount("92233720368547759"), None);
+        assert_eq!(parse_xec_amount("99999999999999999999"), None);
+    }
+
+    fn cash_address(prefix: &str, is_p2sh: bool) -> String {
+        let addr_type = if is_p2sh {
+            AddressType::P2SH
+        } else {
+            AddressType::P2PKH
+        };
+        let hash = ShaRmd160::from_slice(&[0x42; 20]).unwrap();
+        CashAddress::from_hash(prefix, addr_type, hash)
+            .as_str()
+            .to_string()
+    }
+
+    #[test]
+    fn parse_cash_addresses() {
+        for is_p2sh in [false, true] {
+            let expected = cash_address("ecash", is_p2sh);
+            let parsed = parse_address(&expected, Network::Mainnet).unwrap();
+            assert_eq!(parsed.as_str(), expected);
+            // Other mainnet prefixes are normalized to ecash:
+            let bitcoincash = cash_address("bitcoincash", is_p2sh);
+            let parsed = parse_address(&bitcoincash, Network::Mainnet).unwrap();
+            assert_eq!(parsed.as_str(), expected);
+        }
+    }
+
+    #[test]
+    fn parse_prefixless_address() {
+        let expected = cash_address("ectest", false);
+        let (_, payload) = expected.split_once(':').unwrap();
+        let parsed = parse_address(payload, Network::Testnet).unwrap();
+        assert_eq!(parsed.as_str(), expected);
+    }
+
+    #[test]
+    fn parse_legacy_addresses() {
+        for network in [Network::Mainnet, Network::Testnet] {
+            for is_p2sh in [false, true] {
+                let expected = cash_address(network.satoshi_addr_prefix(), is_p2sh);
+                let legacy = to_legacy_address(
+                    &CashAddress::parse_cow(expected.clone().into()).unwrap(),
+                    network,
+                );
+                let parsed = parse_address(&legacy, network).unwrap();
+                assert_eq!(parsed.as_str(), expected);
+            }
+        }
+        // Legacy P2PKH address of hash160 0x00..00
+        let parsed = parse_address("1111111111111111111114oLvT2", Network::Mainnet).unwrap();
+        assert_eq!(parsed.hash().as_slice(), [0; 20]);
+    }
+
+    #[test]
+    fn reject_foreign_network_addresses() {
+        let testnet = cash_address("ectest", false);
+        assert!(parse_address(&testnet, Network::Mainnet).is_err());
+        let mainnet = cash_address("ecash", false);
+        assert!(parse_address(&mainnet, Network::Testnet).is_err());
+        let testnet_legacy = to_legacy_address(
+            &CashAddress::parse_cow(testnet.into()).unwrap(),
+            Network::Testnet,
+        );
+        assert!(parse_address(&testnet_legacy, Network::Mainnet).is_err());
+        // Valid cashaddr checksum, but for a prefix of no known network
+        let unknown = cash_address("foo", false);
+        assert!(parse_address(&unknown, Network::Mainnet).is_err());
+    }
+
+    #[test]
+    fn reject_bad_checksum() {
+        let mut address = cash_address("ecash", false);
+        let last = address.pop().unwrap();
+        address.push(if last == 'q' { 'p' } else { 'q' });
+        assert!(parse_address(&address, Network::Mainnet).is_err());
+    }
+
+    #[test]
+    fn reject_segwit_addresses() {
+        let err = parse_address(
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4",
+            Network::Mainnet,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Segwit addresses are not supported");
+    }
+}
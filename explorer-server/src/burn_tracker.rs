@@ -0,0 +1,88 @@
+use std::{sync::Mutex, time::Duration};
+
+use bitcoinsuite_chronik_client::ChronikClient;
+use bitcoinsuite_error::Result;
+use tracing::warn;
+
+use crate::blockchain::FINALIZATION_DEPTH;
+
+/// Only finalized blocks are summed, so the running total never needs to be unwound on reorg.
+const SAFE_DEPTH: i32 = FINALIZATION_DEPTH;
+const BLOCKS_PER_REQUEST: i32 = 500;
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Running total of sats sent to unspendable (OP_RETURN) outputs, summed from chronik's
+/// per-block `sum_burned_sats`.
+///
+/// Kept in memory only, so after a restart totals are incomplete until the scan from genesis
+/// has caught up again; [`BurnTotals::is_complete`] tells consumers whether that happened.
+pub struct BurnTracker {
+    totals: Mutex<BurnTotals>,
+}
+
+#[derive(Clone, Copy)]
+pub struct BurnTotals {
+    /// Highest block included in the sum, -1 before the first batch
+    pub scanned_height: i32,
+    pub op_return_burned_sats: i64,
+    pub is_complete: bool,
+}
+
+impl BurnTracker {
+    pub fn new() -> Self {
+        BurnTracker {
+            totals: Mutex::new(BurnTotals {
+                scanned_height: -1,
+                op_return_burned_sats: 0,
+                is_complete: false,
+            }),
+        }
+    }
+
+    pub fn totals(&self) -> BurnTotals {
+        *self.totals.lock().unwrap()
+    }
+
+    pub async fn run(&self, chronik: &ChronikClient) {
+        loop {
+            if let Err(err) = self.catch_up(chronik).await {
+                warn!(
+                    height = self.totals().scanned_height,
+                    "Burn tracker failed to sync: {}", err
+                );
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn catch_up(&self, chronik: &ChronikClient) -> Result<()> {
+        let blockchain_info = chronik.blockchain_info().await?;
+        let target_height = blockchain_info.tip_height - SAFE_DEPTH;
+
+        loop {
+            let start_height = self.totals().scanned_height + 1;
+            if start_height > target_height {
+                break;
+            }
+            let end_height = (start_height + BLOCKS_PER_REQUEST - 1).min(target_height);
+            let blocks = chronik.blocks(start_height, end_height).await?;
+            let burned_sats = blocks
+                .iter()
+                .map(|block| block.sum_burned_sats)
+                .sum::<i64>();
+
+            let mut totals = self.totals.lock().unwrap();
+            totals.scanned_height = end_height;
+            totals.op_return_burned_sats += burned_sats;
+        }
+
+        self.totals.lock().unwrap().is_complete = true;
+        Ok(())
+    }
+}
+
+impl Default for BurnTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,189 @@
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use bitcoinsuite_chronik_client::{
+    proto::{subscribe_msg::MsgType, SubscribeMsg},
+    ChronikClient, ScriptType,
+};
+use bitcoinsuite_error::Result;
+use eyre::bail;
+use futures::{
+    stream::{self, BoxStream},
+    Stream, StreamExt,
+};
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, warn};
+
+use crate::{blockchain::to_be_hex, config::ChronikWsConfig};
+
+/// How many events a slow subscriber may fall behind before it starts missing some.
+const CHANNEL_CAPACITY: usize = 256;
+
+/// Chain update pushed by chronik, in the shape sent to `/events` and `/ws` clients.
+/// Hashes are big-endian hex.
+#[derive(Serialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ChainEvent {
+    #[serde(rename_all = "camelCase")]
+    BlockConnected {
+        block_hash: String,
+    },
+    #[serde(rename_all = "camelCase")]
+    BlockDisconnected {
+        block_hash: String,
+    },
+    AddedToMempool {
+        txid: String,
+    },
+    RemovedFromMempool {
+        txid: String,
+    },
+    Confirmed {
+        txid: String,
+    },
+    /// Tx went back to the mempool after its block got disconnected
+    Reorg {
+        txid: String,
+    },
+    /// Sent to `?tx=` subscribers once their tx got mined; the explorer checks for this after
+    /// every block, chronik doesn't send it
+    #[serde(rename_all = "camelCase")]
+    TxConfirmed {
+        txid: String,
+        block_hash: String,
+        block_height: i32,
+        confirmations: i32,
+    },
+}
+
+impl ChainEvent {
+    fn from_msg(msg: SubscribeMsg) -> Option<Self> {
+        let event = match msg.msg_type? {
+            MsgType::BlockConnected(block) => ChainEvent::BlockConnected {
+                block_hash: to_be_hex(&block.block_hash),
+            },
+            MsgType::BlockDisconnected(block) => ChainEvent::BlockDisconnected {
+                block_hash: to_be_hex(&block.block_hash),
+            },
+            MsgType::AddedToMempool(tx) => ChainEvent::AddedToMempool {
+                txid: to_be_hex(&tx.txid),
+            },
+            MsgType::RemovedFromMempool(tx) => ChainEvent::RemovedFromMempool {
+                txid: to_be_hex(&tx.txid),
+            },
+            MsgType::Confirmed(tx) => ChainEvent::Confirmed {
+                txid: to_be_hex(&tx.txid),
+            },
+            MsgType::Reorg(tx) => ChainEvent::Reorg {
+                txid: to_be_hex(&tx.txid),
+            },
+            MsgType::Error(err) => {
+                error!("Chronik WebSocket error: {}", err.msg);
+                return None;
+            }
+        };
+        Some(event)
+    }
+}
+
+/// Block events from a single chronik WebSocket connection, shared by every subscriber.
+///
+/// Chronik only pushes mempool events for scripts a connection subscribed to, so those
+/// come from [`script_events`] instead.
+///
+/// The connection is dropped and reopened if it goes quiet for too long, since some networks
+/// cut idle connections without closing them. Reconnects back off exponentially with jitter,
+/// so a restarting chronik isn't hammered by every explorer at once.
+pub struct ChainEvents {
+    sender: broadcast::Sender<ChainEvent>,
+    config: ChronikWsConfig,
+}
+
+impl ChainEvents {
+    pub fn new(config: &ChronikWsConfig) -> Self {
+        let (sender, _) = broadcast::channel(CHANNEL_CAPACITY);
+        ChainEvents {
+            sender,
+            config: config.clone(),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<ChainEvent> {
+        self.sender.subscribe()
+    }
+
+    pub async fn run(&self, chronik: &ChronikClient) {
+        let min_delay = Duration::from_secs(self.config.min_reconnect_delay_secs);
+        let max_delay = Duration::from_secs(self.config.max_reconnect_delay_secs);
+        let mut delay = min_delay;
+        loop {
+            let connected_at = Instant::now();
+            if let Err(err) = self.forward(chronik).await {
+                warn!("Chronik WebSocket disconnected: {}", err);
+            }
+            // A connection that stayed up for a while was fine, so start backing off anew
+            if connected_at.elapsed() > max_delay {
+                delay = min_delay;
+            }
+            tokio::time::sleep(jittered(delay)).await;
+            delay = (delay * 2).min(max_delay);
+        }
+    }
+
+    async fn forward(&self, chronik: &ChronikClient) -> Result<()> {
+        let idle_timeout = Duration::from_secs(self.config.idle_timeout_secs);
+        let mut ws = chronik.ws().await?;
+        loop {
+            let msg = match tokio::time::timeout(idle_timeout, ws.recv()).await {
+                Ok(msg) => msg?,
+                Err(_) => bail!("Nothing received for {} s", idle_timeout.as_secs()),
+            };
+            if let Some(event) = ChainEvent::from_msg(msg) {
+                // Nobody listening right now is fine
+                let _ = self.sender.send(event);
+            }
+        }
+    }
+}
+
+/// Every event of a single script, including its mempool txs, over a dedicated connection.
+pub async fn script_events(
+    chronik: &ChronikClient,
+    script_type: ScriptType,
+    script_payload: &[u8],
+) -> Result<BoxStream<'static, Result<ChainEvent>>> {
+    let mut ws = chronik.ws().await?;
+    ws.subscribe(script_type, script_payload).await?;
+    Ok(stream::try_unfold(ws, |mut ws| async move {
+        loop {
+            if let Some(event) = ChainEvent::from_msg(ws.recv().await?) {
+                return Ok(Some((event, ws)));
+            }
+        }
+    })
+    .boxed())
+}
+
+/// Somewhere between half and all of `delay`. The clock's nanoseconds are random enough to
+/// spread out reconnects.
+fn jittered(delay: Duration) -> Duration {
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.subsec_nanos());
+    delay.mul_f64(0.5 + (nanos % 1000) as f64 / 2000.0)
+}
+
+/// Events of a subscription as a stream, skipping over any missed by lagging behind.
+pub fn receiver_stream(
+    receiver: broadcast::Receiver<ChainEvent>,
+) -> impl Stream<Item = ChainEvent> {
+    stream::unfold(receiver, |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return Some((event, receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
@@ -0,0 +1,345 @@
+use std::time::Duration;
+
+use bitcoinsuite_chronik_client::{
+    proto::{Block, SlpTokenType, SlpTxType, Tx},
+    ChronikClient,
+};
+use bitcoinsuite_error::Result;
+use eyre::eyre;
+use futures::{stream, StreamExt, TryStreamExt};
+use tokio_postgres::{types::ToSql, Client, NoTls, Transaction};
+use tracing::{error, info};
+
+use crate::{
+    api::calc_tx_stats,
+    blockchain::{to_be_hex, FINALIZATION_DEPTH},
+    config::ExportConfig,
+};
+
+/// Only finalized blocks are exported, so exported rows never need to be unwound on reorg.
+const SAFE_DEPTH: i32 = FINALIZATION_DEPTH;
+const POLL_INTERVAL: Duration = Duration::from_secs(60);
+/// Blocks written per database transaction; a backfill would crawl with one per block.
+const BLOCKS_PER_BATCH: i32 = 100;
+/// Blocks fetched from chronik at the same time.
+const CONCURRENT_FETCHES: usize = 8;
+/// Rows per `INSERT`, well below Postgres' limit of 65535 parameters per statement.
+const ROWS_PER_INSERT: usize = 1000;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS blocks (
+    height INT4 PRIMARY KEY,
+    hash TEXT NOT NULL,
+    prev_hash TEXT NOT NULL,
+    timestamp INT8 NOT NULL,
+    size INT8 NOT NULL,
+    num_txs INT8 NOT NULL,
+    sum_burned_sats INT8 NOT NULL
+);
+CREATE TABLE IF NOT EXISTS txs (
+    txid TEXT PRIMARY KEY,
+    height INT4 NOT NULL,
+    is_coinbase BOOL NOT NULL,
+    size INT4 NOT NULL,
+    num_inputs INT4 NOT NULL,
+    num_outputs INT4 NOT NULL,
+    sats_input INT8 NOT NULL,
+    sats_output INT8 NOT NULL
+);
+CREATE INDEX IF NOT EXISTS txs_height ON txs (height);
+CREATE TABLE IF NOT EXISTS token_events (
+    txid TEXT NOT NULL,
+    height INT4 NOT NULL,
+    token_id TEXT NOT NULL,
+    token_type INT4 NOT NULL,
+    tx_type TEXT NOT NULL,
+    token_input NUMERIC NOT NULL,
+    token_output NUMERIC NOT NULL,
+    PRIMARY KEY (txid, token_id)
+);
+CREATE INDEX IF NOT EXISTS token_events_token_id ON token_events (token_id, height);
+";
+
+/// Copies blocks, txs and token txs from chronik into Postgres tables for offline analytics.
+///
+/// Blocks are written in batches of [`BLOCKS_PER_BATCH`], each in one transaction with
+/// multi-row inserts, so the highest exported height is always complete and an interrupted
+/// export resumes right after it.
+///
+/// Postgres is the only sink; Parquet files can be written from its tables, e.g. with
+/// DuckDB's `COPY ... TO 'txs.parquet'`.
+pub struct Exporter {
+    client: Client,
+}
+
+impl Exporter {
+    /// Connects to the database in `config` and creates the tables if they don't exist yet.
+    pub async fn connect(config: &ExportConfig) -> Result<Self> {
+        let (client, connection) = tokio_postgres::connect(&config.postgres_url, NoTls).await?;
+        tokio::spawn(async move {
+            if let Err(err) = connection.await {
+                error!("Postgres connection failed: {}", err);
+            }
+        });
+        client.batch_execute(SCHEMA).await?;
+        Ok(Exporter { client })
+    }
+
+    /// Exports finalized blocks from `from_height`, or from after the last exported block if
+    /// not given. With `follow`, keeps exporting new blocks as they finalize.
+    pub async fn run(
+        &mut self,
+        chronik: &ChronikClient,
+        from_height: Option<i32>,
+        follow: bool,
+    ) -> Result<()> {
+        let mut next_height = match from_height {
+            Some(from_height) => from_height,
+            None => self.exported_height().await?.map_or(0, |height| height + 1),
+        };
+        loop {
+            let blockchain_info = chronik.blockchain_info().await?;
+            let target_height = blockchain_info.tip_height - SAFE_DEPTH;
+            while next_height <= target_height {
+                let end_height = (next_height + BLOCKS_PER_BATCH - 1).min(target_height);
+                let blocks = stream::iter(next_height..=end_height)
+                    .map(|height| chronik.block_by_height(height))
+                    .buffered(CONCURRENT_FETCHES)
+                    .try_collect::<Vec<_>>()
+                    .await?;
+                self.export_blocks(&blocks).await?;
+                if next_height / 1000 != (end_height + 1) / 1000 {
+                    info!(height = end_height, "Exported up to block {}", end_height);
+                }
+                next_height = end_height + 1;
+            }
+            if !follow {
+                info!("Exported up to block {}", next_height - 1);
+                return Ok(());
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+    }
+
+    async fn exported_height(&self) -> Result<Option<i32>> {
+        let row = self
+            .client
+            .query_one("SELECT MAX(height) FROM blocks", &[])
+            .await?;
+        Ok(row.get(0))
+    }
+
+    /// Writes `blocks` in one transaction, their rows in `blocks` last.
+    async fn export_blocks(&mut self, blocks: &[Block]) -> Result<()> {
+        let mut tx_rows = Vec::new();
+        let mut token_event_rows = Vec::new();
+        let mut block_rows = Vec::new();
+        for block in blocks {
+            let block_info = block
+                .block_info
+                .as_ref()
+                .ok_or_else(|| eyre!("Block has no info"))?;
+            let height = block_info.height;
+            for tx in &block.txs {
+                let stats = calc_tx_stats(tx, None);
+                let txid = to_be_hex(&tx.txid);
+                if let Some((token_id, token_type, tx_type)) = token_event(tx) {
+                    token_event_rows.push((
+                        txid.clone(),
+                        height,
+                        token_id,
+                        token_type,
+                        tx_type,
+                        stats.token_input.to_string(),
+                        stats.token_output.to_string(),
+                    ));
+                }
+                tx_rows.push((
+                    txid,
+                    height,
+                    tx.is_coinbase,
+                    tx.size as i32,
+                    tx.inputs.len() as i32,
+                    tx.outputs.len() as i32,
+                    stats.sats_input,
+                    stats.sats_output,
+                ));
+            }
+            block_rows.push((
+                height,
+                to_be_hex(&block_info.hash),
+                to_be_hex(&block_info.prev_hash),
+                block_info.timestamp,
+                block_info.block_size as i64,
+                block_info.num_txs as i64,
+                block_info.sum_burned_sats,
+            ));
+        }
+
+        let transaction = self.client.transaction().await?;
+        insert_rows(&transaction, "txs", &[""; 8], &tx_rows, |row| {
+            vec![
+                &row.0 as Param,
+                &row.1,
+                &row.2,
+                &row.3,
+                &row.4,
+                &row.5,
+                &row.6,
+                &row.7,
+            ]
+        })
+        .await?;
+        // Token amounts don't fit into INT8, so they're passed as text
+        let token_event_casts = ["", "", "", "", "", "::TEXT::NUMERIC", "::TEXT::NUMERIC"];
+        insert_rows(
+            &transaction,
+            "token_events",
+            &token_event_casts,
+            &token_event_rows,
+            |row| {
+                vec![
+                    &row.0 as Param,
+                    &row.1,
+                    &row.2,
+                    &row.3,
+                    &row.4,
+                    &row.5,
+                    &row.6,
+                ]
+            },
+        )
+        .await?;
+        insert_rows(&transaction, "blocks", &[""; 7], &block_rows, |row| {
+            vec![
+                &row.0 as Param,
+                &row.1,
+                &row.2,
+                &row.3,
+                &row.4,
+                &row.5,
+                &row.6,
+            ]
+        })
+        .await?;
+        transaction.commit().await?;
+        Ok(())
+    }
+}
+
+type Param<'a> = &'a (dyn ToSql + Sync);
+
+/// Inserts `rows` into `table` with as few statements as possible, skipping rows that are
+/// already there. `column_casts` has a cast like `::TEXT::NUMERIC` (or nothing) per column.
+async fn insert_rows<T>(
+    transaction: &Transaction<'_>,
+    table: &str,
+    column_casts: &[&str],
+    rows: &[T],
+    row_params: impl Fn(&T) -> Vec<Param>,
+) -> Result<()> {
+    for chunk in rows.chunks(ROWS_PER_INSERT) {
+        let statement = format!(
+            "INSERT INTO {} VALUES {} ON CONFLICT DO NOTHING",
+            table,
+            values_list(chunk.len(), column_casts),
+        );
+        let params = chunk.iter().flat_map(&row_params).collect::<Vec<_>>();
+        transaction.execute(statement.as_str(), &params).await?;
+    }
+    Ok(())
+}
+
+/// Placeholders of a multi-row `VALUES` list, e.g. `($1, $2::TEXT), ($3, $4::TEXT)`.
+fn values_list(num_rows: usize, column_casts: &[&str]) -> String {
+    let num_columns = column_casts.len();
+    (0..num_rows)
+        .map(|row| {
+            let columns = column_casts
+                .iter()
+                .enumerate()
+                .map(|(column, cast)| format!("${}{}", row * num_columns + column + 1, cast))
+                .collect::<Vec<_>>();
+            format!("({})", columns.join(", "))
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Token ID hex, token type and tx type of `tx`, if it's a valid token tx.
+fn token_event(tx: &Tx) -> Option<(String, i32, &'static str)> {
+    let slp_meta = tx.slp_tx_data.as_ref()?.slp_meta.as_ref()?;
+    if slp_meta.token_type() == SlpTokenType::UnknownTokenType {
+        return None;
+    }
+    let tx_type = match slp_meta.tx_type() {
+        SlpTxType::Genesis => "GENESIS",
+        SlpTxType::Send => "SEND",
+        SlpTxType::Mint => "MINT",
+        SlpTxType::Burn => "BURN",
+        SlpTxType::UnknownTxType => return None,
+    };
+    Some((
+        hex::encode(&slp_meta.token_id),
+        slp_meta.token_type as i32,
+        tx_type,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_chronik_client::proto::{SlpMeta, SlpTokenType, SlpTxData, SlpTxType, Tx};
+
+    use super::{token_event, values_list};
+
+    fn token_tx(token_type: SlpTokenType, tx_type: SlpTxType) -> Tx {
+        Tx {
+            slp_tx_data: Some(SlpTxData {
+                slp_meta: Some(SlpMeta {
+                    token_type: token_type as i32,
+                    tx_type: tx_type as i32,
+                    token_id: vec![0xab; 32],
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn token_event_of_valid_token_txs() {
+        let (token_id, token_type, tx_type) =
+            token_event(&token_tx(SlpTokenType::Fungible, SlpTxType::Genesis)).unwrap();
+        assert_eq!(token_id, "ab".repeat(32));
+        assert_eq!(token_type, SlpTokenType::Fungible as i32);
+        assert_eq!(tx_type, "GENESIS");
+
+        let event = token_event(&token_tx(SlpTokenType::Nft1Child, SlpTxType::Send)).unwrap();
+        assert_eq!(event.1, SlpTokenType::Nft1Child as i32);
+        assert_eq!(event.2, "SEND");
+        assert_eq!(
+            token_event(&token_tx(SlpTokenType::Fungible, SlpTxType::Burn))
+                .unwrap()
+                .2,
+            "BURN"
+        );
+    }
+
+    #[test]
+    fn token_event_skips_other_txs() {
+        assert!(token_event(&Tx::default()).is_none());
+        assert!(token_event(&token_tx(SlpTokenType::UnknownTokenType, SlpTxType::Send)).is_none());
+        assert!(token_event(&token_tx(SlpTokenType::Fungible, SlpTxType::UnknownTxType)).is_none());
+    }
+
+    #[test]
+    fn values_list_numbers_every_column() {
+        assert_eq!(values_list(1, &["", ""]), "($1, $2)");
+        assert_eq!(
+            values_list(2, &["", "::TEXT::NUMERIC"]),
+            "($1, $2::TEXT::NUMERIC), ($3, $4::TEXT::NUMERIC)"
+        );
+        assert_eq!(values_list(0, &[""]), "");
+    }
+}
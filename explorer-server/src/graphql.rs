@@ -0,0 +1,284 @@
+use std::{collections::HashMap, sync::Arc};
+
+use async_graphql::{
+    Context, EmptyMutation, EmptySubscription, Error, Object, Result, Schema, SimpleObject,
+};
+
+use crate::{
+    server::Server,
+    server_primitives::{JsonBlock, JsonToken, JsonTx, JsonTxStats},
+};
+
+pub type ExplorerSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+/// Deep enough for tx -> block -> transactions -> token, but not for unbounded nesting.
+const MAX_QUERY_DEPTH: usize = 8;
+/// List fields count as `take` times their children, so this bounds the number of objects
+/// a single query can resolve.
+const MAX_QUERY_COMPLEXITY: usize = 5000;
+/// Chronik's own maximum page size for script history.
+const MAX_ADDRESS_TXS_PAGE_SIZE: usize = 200;
+
+/// The schema carries no data itself; `Arc<Server>` is attached to every request.
+pub fn build_schema() -> ExplorerSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_QUERY_DEPTH)
+        .limit_complexity(MAX_QUERY_COMPLEXITY)
+        .finish()
+}
+
+fn num_blocks(start_height: i32, end_height: i32) -> usize {
+    (end_height as i64 - start_height as i64 + 1).max(1) as usize
+}
+
+fn server<'a>(ctx: &Context<'a>) -> Result<&'a Arc<Server>> {
+    ctx.data::<Arc<Server>>()
+}
+
+fn to_gql_error<T: ToString>(err: T) -> Error {
+    Error::new(err.to_string())
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn block(&self, ctx: &Context<'_>, hash: String) -> Result<Block> {
+        let server = server(ctx)?;
+        let block = server.data_block(&hash).await.map_err(to_gql_error)?;
+        Ok(Block(block))
+    }
+
+    async fn block_by_height(&self, ctx: &Context<'_>, height: i32) -> Result<Option<Block>> {
+        let server = server(ctx)?;
+        server
+            .validate_block_range(height, height)
+            .map_err(to_gql_error)?;
+        let blocks = server
+            .data_blocks(height, height)
+            .await
+            .map_err(to_gql_error)?;
+        Ok(blocks.data.into_iter().next().map(Block))
+    }
+
+    #[graphql(complexity = "num_blocks(start_height, end_height) * child_complexity")]
+    async fn blocks(
+        &self,
+        ctx: &Context<'_>,
+        start_height: i32,
+        end_height: i32,
+    ) -> Result<Vec<Block>> {
+        let server = server(ctx)?;
+        server
+            .validate_block_range(start_height, end_height)
+            .map_err(to_gql_error)?;
+        let blocks = server
+            .data_blocks(start_height, end_height)
+            .await
+            .map_err(to_gql_error)?;
+        Ok(blocks.data.into_iter().map(Block).collect())
+    }
+
+    async fn transaction(&self, ctx: &Context<'_>, hash: String) -> Result<Transaction> {
+        let server = server(ctx)?;
+        let tx = server.data_tx(&hash).await.map_err(to_gql_error)?;
+        Ok(Transaction(tx))
+    }
+
+    async fn address(&self, address: String) -> Address {
+        Address(address)
+    }
+
+    async fn token(&self, ctx: &Context<'_>, token_id: String) -> Result<Option<TokenInfo>> {
+        let server = server(ctx)?;
+        let token = server.data_token(&token_id).await.map_err(to_gql_error)?;
+        Ok(token.map(TokenInfo::from))
+    }
+}
+
+pub struct Block(JsonBlock);
+
+#[Object]
+impl Block {
+    async fn hash(&self) -> &str {
+        &self.0.hash
+    }
+
+    async fn height(&self) -> i32 {
+        self.0.height
+    }
+
+    async fn timestamp(&self) -> i64 {
+        self.0.timestamp
+    }
+
+    async fn difficulty(&self) -> f64 {
+        self.0.difficulty
+    }
+
+    async fn size(&self) -> u64 {
+        self.0.size
+    }
+
+    async fn num_txs(&self) -> u64 {
+        self.0.num_txs
+    }
+
+    async fn confirmations(&self) -> i32 {
+        self.0.confirmations
+    }
+
+    async fn is_finalized(&self) -> bool {
+        self.0.is_finalized
+    }
+
+    #[graphql(complexity = "take * child_complexity")]
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 0)] page: usize,
+        #[graphql(default = 200)] take: usize,
+    ) -> Result<Vec<Transaction>> {
+        let server = server(ctx)?;
+        let query = HashMap::from([
+            ("page".to_string(), page.to_string()),
+            ("take".to_string(), take.to_string()),
+        ]);
+        let txs = server
+            .data_block_txs(&self.0.hash, query)
+            .await
+            .map_err(to_gql_error)?;
+        Ok(txs.data.into_iter().map(Transaction).collect())
+    }
+}
+
+pub struct Transaction(JsonTx);
+
+#[Object]
+impl Transaction {
+    async fn hash(&self) -> &str {
+        &self.0.tx_hash
+    }
+
+    async fn block_height(&self) -> Option<i32> {
+        self.0.block_height
+    }
+
+    async fn timestamp(&self) -> i64 {
+        self.0.timestamp
+    }
+
+    async fn is_coinbase(&self) -> bool {
+        self.0.is_coinbase
+    }
+
+    async fn size(&self) -> i32 {
+        self.0.size
+    }
+
+    async fn num_inputs(&self) -> u32 {
+        self.0.num_inputs
+    }
+
+    async fn num_outputs(&self) -> u32 {
+        self.0.num_outputs
+    }
+
+    async fn stats(&self) -> TxStats {
+        TxStats::from(&self.0.stats)
+    }
+
+    async fn token(&self) -> Option<TokenInfo> {
+        self.0.token.clone().map(TokenInfo::from)
+    }
+
+    async fn block(&self, ctx: &Context<'_>) -> Result<Option<Block>> {
+        match self.0.block_height {
+            Some(height) => QueryRoot.block_by_height(ctx, height).await,
+            None => Ok(None),
+        }
+    }
+}
+
+pub struct Address(String);
+
+#[Object]
+impl Address {
+    async fn address(&self) -> &str {
+        &self.0
+    }
+
+    #[graphql(complexity = "take * child_complexity")]
+    async fn transactions(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 0)] page: usize,
+        #[graphql(default = 200)] take: usize,
+    ) -> Result<Vec<Transaction>> {
+        if take > MAX_ADDRESS_TXS_PAGE_SIZE {
+            return Err(Error::new(format!(
+                "take must be at most {}",
+                MAX_ADDRESS_TXS_PAGE_SIZE
+            )));
+        }
+        let server = server(ctx)?;
+        let query = HashMap::from([
+            ("page".to_string(), page.to_string()),
+            ("take".to_string(), take.to_string()),
+        ]);
+        let txs = server
+            .data_address_txs(&self.0, query)
+            .await
+            .map_err(to_gql_error)?;
+        Ok(txs.data.into_iter().map(Transaction).collect())
+    }
+}
+
+/// Token amounts are `i128`, which GraphQL has no scalar for, so they're exposed as strings.
+#[derive(SimpleObject)]
+pub struct TxStats {
+    sats_input: i64,
+    sats_output: i64,
+    delta_sats: i64,
+    delta_tokens: i64,
+    token_input: String,
+    token_output: String,
+    does_burn_slp: bool,
+}
+
+impl From<&JsonTxStats> for TxStats {
+    fn from(stats: &JsonTxStats) -> Self {
+        TxStats {
+            sats_input: stats.sats_input,
+            sats_output: stats.sats_output,
+            delta_sats: stats.delta_sats,
+            delta_tokens: stats.delta_tokens,
+            token_input: stats.token_input.to_string(),
+            token_output: stats.token_output.to_string(),
+            does_burn_slp: stats.does_burn_slp,
+        }
+    }
+}
+
+#[derive(SimpleObject)]
+pub struct TokenInfo {
+    token_id: String,
+    token_type: u32,
+    token_ticker: String,
+    token_name: String,
+    decimals: u32,
+    group_id: Option<String>,
+}
+
+impl From<JsonToken> for TokenInfo {
+    fn from(token: JsonToken) -> Self {
+        TokenInfo {
+            token_id: token.token_id,
+            token_type: token.token_type,
+            token_ticker: token.token_ticker,
+            token_name: token.token_name,
+            decimals: token.decimals,
+            group_id: token.group_id,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use maud::html;
+
+const GRID_SIZE: usize = 5;
+/// Columns that are drawn; the rest mirror them, which makes the pattern easier to recognize.
+const DRAWN_COLUMNS: usize = (GRID_SIZE + 1) / 2;
+const BACKGROUND_COLOR: &str = "#f0f0f0";
+
+/// Identicon of a token: a horizontally symmetric 5x5 pattern in a color derived from its
+/// 32-byte ID, used in place of a token icon so tokens can be told apart at a glance.
+pub fn token_identicon_svg(token_id: &[u8; 32]) -> String {
+    let hue = u32::from(u16::from_be_bytes([token_id[0], token_id[1]])) * 360 / 0x10000;
+    let saturation = 45 + token_id[2] % 30;
+    let color = format!("hsl({}, {}%, 45%)", hue, saturation);
+
+    let mut cells = Vec::new();
+    for (idx, byte) in token_id[3..]
+        .iter()
+        .take(GRID_SIZE * DRAWN_COLUMNS)
+        .enumerate()
+    {
+        if byte % 2 == 0 {
+            continue;
+        }
+        let (row, column) = (idx / DRAWN_COLUMNS, idx % DRAWN_COLUMNS);
+        cells.push((column, row));
+        if column != GRID_SIZE - 1 - column {
+            cells.push((GRID_SIZE - 1 - column, row));
+        }
+    }
+
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" width="64" height="64" viewBox="-1 -1 7 7"
+            shape-rendering="crispEdges" {
+            rect x="-1" y="-1" width="7" height="7" fill=(BACKGROUND_COLOR) {}
+            @for (x, y) in cells {
+                rect x=(x) y=(y) width="1" height="1" fill=(color) {}
+            }
+        }
+    }
+    .into_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{token_identicon_svg, BACKGROUND_COLOR};
+
+    /// Grid positions of the drawn cells, leaving out the background.
+    fn cells(svg: &str) -> Vec<(usize, usize)> {
+        svg.split("<rect ")
+            .skip(1)
+            .filter(|rect| !rect.contains(BACKGROUND_COLOR))
+            .map(|rect| {
+                let attr = |name: &str| -> usize {
+                    let value = rect.split(&format!("{}=\"", name)).nth(1).unwrap();
+                    value.split('"').next().unwrap().parse().unwrap()
+                };
+                (attr("x"), attr("y"))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn identicon_is_deterministic() {
+        let token_id = [0x5a; 32];
+        assert_eq!(
+            token_identicon_svg(&token_id),
+            token_identicon_svg(&token_id)
+        );
+        let mut other_token_id = token_id;
+        other_token_id[0] = 0x00;
+        assert_ne!(
+            token_identicon_svg(&token_id),
+            token_identicon_svg(&other_token_id)
+        );
+    }
+
+    #[test]
+    fn identicon_shape() {
+        let mut token_id = [0u8; 32];
+        // Odd bytes fill the cells of the drawn columns, row by row
+        for (idx, byte) in token_id[3..].iter_mut().enumerate() {
+            *byte = if idx % 3 == 0 { 1 } else { 2 };
+        }
+        let svg = token_identicon_svg(&token_id);
+        assert!(svg.starts_with("<svg "));
+        assert!(svg.ends_with("</svg>"));
+        assert!(svg.contains(r#"viewBox="-1 -1 7 7""#));
+
+        let mut cells = cells(&svg);
+        cells.sort_unstable();
+        // Column 0 of every row, mirrored to column 4
+        let mut expected = (0..5)
+            .flat_map(|row| [(0, row), (4, row)])
+            .collect::<Vec<_>>();
+        expected.sort_unstable();
+        assert_eq!(cells, expected);
+    }
+
+    #[test]
+    fn identicon_is_symmetric() {
+        let token_id = [0xff; 32];
+        let cells = cells(&token_identicon_svg(&token_id));
+        // All odd: the full 5x5 grid, the middle column drawn once
+        assert_eq!(cells.len(), 25);
+        for (x, y) in &cells {
+            assert!(cells.contains(&(4 - x, *y)));
+        }
+    }
+}
@@ -0,0 +1,79 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use bitcoinsuite_chronik_client::proto::{BlockDetails, BlockInfo, Tx};
+
+use crate::{
+    api::calc_tx_stats, block_follower::BlockConsumer, blockchain::to_be_hex,
+    server_primitives::JsonLargeTx,
+};
+
+/// Txs moving less than this (100k XEC) aren't kept, so it's also the lowest `min_value`.
+pub const MIN_TRACKED_SATS: i64 = 10_000_000;
+/// How far back the feed is backfilled on startup.
+const INITIAL_BLOCKS: i32 = 144;
+const MAX_TXS: usize = 1000;
+
+/// Most recent confirmed non-coinbase txs moving at least [`MIN_TRACKED_SATS`], newest first.
+///
+/// Kept in memory only and backfilled from the last [`INITIAL_BLOCKS`] blocks on startup.
+/// Txs of reorged blocks are dropped and the replacement blocks are scanned again.
+pub struct LargeTxFeed {
+    txs: Mutex<VecDeque<JsonLargeTx>>,
+}
+
+impl LargeTxFeed {
+    pub fn new() -> Self {
+        LargeTxFeed {
+            txs: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    pub fn txs(&self, min_sats: i64, limit: usize) -> Vec<JsonLargeTx> {
+        let txs = self.txs.lock().unwrap();
+        txs.iter()
+            .filter(|tx| tx.sats_output >= min_sats)
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+impl BlockConsumer for LargeTxFeed {
+    fn initial_blocks(&self) -> i32 {
+        INITIAL_BLOCKS
+    }
+
+    fn add_block(&self, block_info: &BlockInfo, _block_details: &BlockDetails, txs: &[Tx]) {
+        let large_txs = txs.iter().filter(|tx| !tx.is_coinbase).filter_map(|tx| {
+            let stats = calc_tx_stats(tx, None);
+            if stats.sats_output < MIN_TRACKED_SATS {
+                return None;
+            }
+            Some(JsonLargeTx {
+                tx_hash: to_be_hex(&tx.txid),
+                block_height: block_info.height,
+                timestamp: block_info.timestamp,
+                sats_output: stats.sats_output,
+                num_inputs: tx.inputs.len() as u32,
+                num_outputs: tx.outputs.len() as u32,
+            })
+        });
+
+        let mut feed = self.txs.lock().unwrap();
+        for large_tx in large_txs {
+            feed.push_front(large_tx);
+        }
+        feed.truncate(MAX_TXS);
+    }
+
+    fn remove_blocks_from(&self, height: i32) {
+        let mut txs = self.txs.lock().unwrap();
+        txs.retain(|tx| tx.block_height < height);
+    }
+}
+
+impl Default for LargeTxFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -1,8 +1,42 @@
 mod api;
+mod assets;
+mod auth;
+mod balances;
+mod block_cache;
+mod block_follower;
 mod blockchain;
+mod burn_tracker;
+pub mod chain_events;
 pub mod config;
+#[cfg(feature = "export")]
+pub mod export;
+#[cfg(feature = "graphql")]
+mod graphql;
+mod identicon;
+mod large_tx_feed;
+pub mod logging;
+mod mempool_activity;
+mod meta;
+mod op_return_index;
+mod openapi;
+mod page_cache;
+pub mod preferences;
+mod qr_codes;
 pub mod server;
 pub mod server_error;
 pub mod server_http;
+pub mod server_middleware;
 pub mod server_primitives;
+mod signed_message;
+mod stats;
+pub mod status;
 mod templating;
+mod tip_cache;
+mod token_cache;
+mod token_filter;
+mod token_genesis_feed;
+mod token_stats;
+mod translations;
+mod tx_positions;
+mod version_bits;
+mod watchdog;
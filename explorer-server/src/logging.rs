@@ -0,0 +1,22 @@
+use tracing_subscriber::EnvFilter;
+
+use crate::config::LogFormat;
+
+/// Installs the global `tracing` subscriber; only the first call in a process has an effect.
+///
+/// Levels default to `info` and can be overridden with `RUST_LOG`. Events keep their fields
+/// (e.g. `height`) in both formats. As JSON, each line is one object with the event's fields
+/// at the top level and those of the enclosing request span, like `request_id`, under `span`.
+pub fn init(format: LogFormat) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let subscriber = tracing_subscriber::fmt().with_env_filter(filter);
+    let _ = match format {
+        LogFormat::Text => subscriber.try_init(),
+        LogFormat::Json => subscriber
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .try_init(),
+    };
+}
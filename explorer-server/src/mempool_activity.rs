@@ -0,0 +1,226 @@
+use std::collections::HashMap;
+
+use bitcoinsuite_chronik_client::proto::{SlpToken, Tx, Utxo};
+
+use crate::blockchain::to_be_hex;
+
+/// What the unconfirmed txs of a script do to it.
+#[derive(Default, Debug)]
+pub struct MempoolActivity {
+    /// Outpoints already spent, with the txid spending them
+    spends: HashMap<(Vec<u8>, u32), String>,
+    /// Net change of sats and token amount, by balance key ("main" or the token ID hex)
+    pub deltas: HashMap<String, (i64, i128)>,
+    /// Whether the script has more unconfirmed txs than were scanned, leaving the above
+    /// incomplete
+    pub truncated: bool,
+}
+
+impl MempoolActivity {
+    /// Adds the unconfirmed txs of a page of a script's history, which lists them before any
+    /// confirmed one. Returns whether the page is unconfirmed throughout, so the next page may
+    /// hold more of them.
+    pub fn add_history_page(&mut self, txs: &[Tx], is_own_script: impl Fn(&[u8]) -> bool) -> bool {
+        let num_unconfirmed = txs.iter().take_while(|tx| tx.block.is_none()).count();
+        for tx in &txs[..num_unconfirmed] {
+            self.add_tx(tx, &is_own_script);
+        }
+        num_unconfirmed == txs.len()
+    }
+
+    /// Txid of the unconfirmed tx spending `utxo`, if any.
+    pub fn spent_in(&self, utxo: &Utxo) -> Option<String> {
+        let outpoint = utxo.outpoint.as_ref()?;
+        self.spends
+            .get(&(outpoint.txid.clone(), outpoint.out_idx))
+            .cloned()
+    }
+
+    fn add_tx(&mut self, tx: &Tx, is_own_script: &impl Fn(&[u8]) -> bool) {
+        let token_id = tx
+            .slp_tx_data
+            .as_ref()
+            .and_then(|slp_tx_data| slp_tx_data.slp_meta.as_ref())
+            .map(|slp_meta| hex::encode(&slp_meta.token_id));
+        let balance_key = |slp_token: &Option<SlpToken>| match (slp_token, &token_id) {
+            (Some(_), Some(token_id)) => token_id.clone(),
+            _ => "main".to_string(),
+        };
+        let token_amount = |slp_token: &Option<SlpToken>| {
+            slp_token
+                .as_ref()
+                .map_or(0, |token| i128::from(token.amount))
+        };
+        for input in &tx.inputs {
+            if let Some(prev_out) = &input.prev_out {
+                self.spends.insert(
+                    (prev_out.txid.clone(), prev_out.out_idx),
+                    to_be_hex(&tx.txid),
+                );
+            }
+            if is_own_script(&input.output_script) {
+                let delta = self
+                    .deltas
+                    .entry(balance_key(&input.slp_token))
+                    .or_default();
+                delta.0 -= input.value;
+                delta.1 -= token_amount(&input.slp_token);
+            }
+        }
+        for output in &tx.outputs {
+            if is_own_script(&output.output_script) {
+                let delta = self
+                    .deltas
+                    .entry(balance_key(&output.slp_token))
+                    .or_default();
+                delta.0 += output.value;
+                delta.1 += token_amount(&output.slp_token);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_chronik_client::proto::{
+        BlockMetadata, OutPoint, SlpMeta, SlpToken, SlpTxData, Tx, TxInput, TxOutput, Utxo,
+    };
+
+    use super::MempoolActivity;
+
+    const OWN_SCRIPT: &[u8] = &[0x51];
+
+    fn is_own_script(script: &[u8]) -> bool {
+        script == OWN_SCRIPT
+    }
+
+    fn tx(txid: u8, prev_outs: &[(u8, u32)], is_confirmed: bool) -> Tx {
+        Tx {
+            txid: vec![txid; 32],
+            inputs: prev_outs
+                .iter()
+                .map(|&(prev_txid, out_idx)| TxInput {
+                    prev_out: Some(OutPoint {
+                        txid: vec![prev_txid; 32],
+                        out_idx,
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            block: is_confirmed.then(BlockMetadata::default),
+            ..Default::default()
+        }
+    }
+
+    fn utxo(txid: u8, out_idx: u32) -> Utxo {
+        Utxo {
+            outpoint: Some(OutPoint {
+                txid: vec![txid; 32],
+                out_idx,
+            }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn spends_of_unconfirmed_txs() {
+        let mut activity = MempoolActivity::default();
+        let txs = [tx(0x01, &[(0xaa, 0)], false), tx(0x02, &[(0xbb, 1)], true)];
+        assert!(!activity.add_history_page(&txs, is_own_script));
+        assert_eq!(
+            activity.spent_in(&utxo(0xaa, 0)),
+            Some(hex::encode([0x01; 32]))
+        );
+        // Confirmed txs after the unconfirmed ones are left out
+        assert_eq!(activity.spent_in(&utxo(0xbb, 1)), None);
+        assert_eq!(activity.spent_in(&utxo(0xaa, 1)), None);
+    }
+
+    #[test]
+    fn unconfirmed_page_continues() {
+        let mut activity = MempoolActivity::default();
+        let txs = [tx(0x01, &[(0xaa, 0)], false), tx(0x02, &[(0xaa, 1)], false)];
+        assert!(activity.add_history_page(&txs, is_own_script));
+        assert!(activity.add_history_page(&[], is_own_script));
+        assert_eq!(
+            activity.spent_in(&utxo(0xaa, 1)),
+            Some(hex::encode([0x02; 32]))
+        );
+    }
+
+    fn token(amount: u64) -> Option<SlpToken> {
+        Some(SlpToken {
+            amount,
+            is_mint_baton: false,
+        })
+    }
+
+    fn input(output_script: &[u8], value: i64, slp_token: Option<SlpToken>) -> TxInput {
+        TxInput {
+            output_script: output_script.to_vec(),
+            value,
+            slp_token,
+            ..Default::default()
+        }
+    }
+
+    fn output(output_script: &[u8], value: i64, slp_token: Option<SlpToken>) -> TxOutput {
+        TxOutput {
+            output_script: output_script.to_vec(),
+            value,
+            slp_token,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn deltas_net_out_change() {
+        let mut activity = MempoolActivity::default();
+        let send = Tx {
+            inputs: vec![input(OWN_SCRIPT, 1000, None)],
+            outputs: vec![output(&[0x52], 300, None), output(OWN_SCRIPT, 650, None)],
+            ..tx(0x01, &[], false)
+        };
+        let receive = Tx {
+            inputs: vec![input(&[0x52], 5000, None)],
+            outputs: vec![output(OWN_SCRIPT, 2000, None)],
+            ..tx(0x02, &[], false)
+        };
+        let confirmed = Tx {
+            outputs: vec![output(OWN_SCRIPT, 7000, None)],
+            ..tx(0x03, &[], true)
+        };
+        activity.add_history_page(&[send, receive, confirmed], is_own_script);
+        assert_eq!(activity.deltas.len(), 1);
+        assert_eq!(activity.deltas["main"], (1650, 0));
+        assert!(!activity.truncated);
+    }
+
+    #[test]
+    fn deltas_by_token() {
+        let mut activity = MempoolActivity::default();
+        let token_id = vec![0xcd; 32];
+        let send = Tx {
+            slp_tx_data: Some(SlpTxData {
+                slp_meta: Some(SlpMeta {
+                    token_id: token_id.clone(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            inputs: vec![
+                input(OWN_SCRIPT, 546, token(10)),
+                input(OWN_SCRIPT, 2000, None),
+            ],
+            outputs: vec![
+                output(&[0x52], 546, token(6)),
+                output(OWN_SCRIPT, 546, token(4)),
+                output(OWN_SCRIPT, 1000, None),
+            ],
+            ..tx(0x01, &[], false)
+        };
+        activity.add_history_page(&[send], is_own_script);
+        assert_eq!(activity.deltas["main"], (-1000, 0));
+        assert_eq!(activity.deltas[&hex::encode(&token_id)], (0, -6));
+    }
+}
@@ -0,0 +1,167 @@
+use bitcoinsuite_chronik_client::proto::BlockInfo;
+use bitcoinsuite_error::Result;
+use eyre::{bail, eyre};
+use maud::html;
+use once_cell::sync::OnceCell;
+
+/// Fonts are looked up once, when the first preview is rendered.
+static PREVIEW_OPTIONS: OnceCell<usvg::Options> = OnceCell::new();
+
+/// Kind of page a preview image is for, as in `/preview/:kind/:hash`.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PreviewKind {
+    Tx,
+    Block,
+    Address,
+}
+
+impl PreviewKind {
+    pub fn parse(kind: &str) -> Result<Self> {
+        match kind {
+            "tx" => Ok(PreviewKind::Tx),
+            "block" => Ok(PreviewKind::Block),
+            "address" => Ok(PreviewKind::Address),
+            _ => bail!("Unknown preview kind {}", kind),
+        }
+    }
+}
+
+/// OpenGraph/Twitter card contents of a page, so shared links get a meaningful preview.
+pub struct PageMeta {
+    pub title: String,
+    pub description: String,
+    pub url: String,
+    pub image_url: String,
+}
+
+pub fn tx_meta(
+    public_url: &str,
+    tx_hex: &str,
+    sats_output: i64,
+    token_ticker: Option<&str>,
+) -> PageMeta {
+    let short_hash = short_hash(tx_hex);
+    let description = match token_ticker {
+        Some(token_ticker) => format!(
+            "{} transaction {} moved {} XEC",
+            token_ticker,
+            short_hash,
+            render_xec_compact(sats_output),
+        ),
+        None => format!(
+            "Tx {} moved {} XEC",
+            short_hash,
+            render_xec_compact(sats_output)
+        ),
+    };
+
+    PageMeta {
+        title: format!("Transaction {}", short_hash),
+        description,
+        url: format!("{}/tx/{}", public_url, tx_hex),
+        image_url: format!("{}/preview/tx/{}", public_url, tx_hex),
+    }
+}
+
+pub fn block_meta(public_url: &str, block_hex: &str, block_info: &BlockInfo) -> PageMeta {
+    PageMeta {
+        title: format!("Block #{}", block_info.height),
+        description: format!(
+            "Block #{} with {} transactions, {} bytes",
+            block_info.height, block_info.num_txs, block_info.block_size,
+        ),
+        url: format!("{}/block/{}", public_url, block_hex),
+        image_url: format!("{}/preview/block/{}", public_url, block_hex),
+    }
+}
+
+/// `total_sats` is left out of the description if not given, e.g. for preview images, which
+/// don't fetch the address's UTXOs.
+pub fn address_meta(
+    public_url: &str,
+    address: &str,
+    total_sats: Option<i64>,
+    num_txs: u32,
+) -> PageMeta {
+    let description = match total_sats {
+        Some(total_sats) => format!(
+            "Holds {} XEC across {} transactions",
+            render_xec_compact(total_sats),
+            num_txs,
+        ),
+        None => format!("{} transactions", num_txs),
+    };
+    PageMeta {
+        title: format!("Address {}", short_address(address)),
+        description,
+        url: format!("{}/address/{}", public_url, address),
+        image_url: format!("{}/preview/address/{}", public_url, address),
+    }
+}
+
+/// [`preview_svg`] rasterized, since most OpenGraph consumers don't accept SVG images.
+pub fn preview_png(meta: &PageMeta) -> Result<Vec<u8>> {
+    let options = PREVIEW_OPTIONS.get_or_init(|| {
+        let mut options = usvg::Options::default();
+        options.fontdb.load_system_fonts();
+        options
+    });
+    let tree = usvg::Tree::from_str(&preview_svg(meta), &options.to_ref())?;
+    let size = tree.svg_node().size.to_screen_size();
+    let mut pixmap = tiny_skia::Pixmap::new(size.width(), size.height())
+        .ok_or_else(|| eyre!("Invalid preview size"))?;
+    resvg::render(
+        &tree,
+        usvg::FitTo::Original,
+        tiny_skia::Transform::default(),
+        pixmap.as_mut(),
+    )
+    .ok_or_else(|| eyre!("Failed to render preview"))?;
+    Ok(pixmap.encode_png()?)
+}
+
+/// 1200x630 card (the size OpenGraph consumers expect) showing the page title and description.
+fn preview_svg(meta: &PageMeta) -> String {
+    html! {
+        svg xmlns="http://www.w3.org/2000/svg" width="1200" height="630" viewBox="0 0 1200 630" {
+            rect width="1200" height="630" fill="#0074c2" {}
+            text x="80" y="140" fill="#ffffff" font-family="sans-serif" font-size="40" {
+                "be.cash Block Explorer"
+            }
+            text x="80" y="320" fill="#ffffff" font-family="sans-serif" font-size="72"
+                font-weight="bold" {
+                (meta.title)
+            }
+            text x="80" y="420" fill="#ffffff" font-family="sans-serif" font-size="40" {
+                (meta.description)
+            }
+        }
+    }
+    .into_string()
+}
+
+/// Amount in XEC with a magnitude suffix, e.g. "1.2M".
+pub fn render_xec_compact(sats: i64) -> String {
+    let xec = sats as f64 / 100.0;
+    let (value, suffix) = match xec.abs() {
+        abs if abs >= 1e9 => (xec / 1e9, "B"),
+        abs if abs >= 1e6 => (xec / 1e6, "M"),
+        abs if abs >= 1e3 => (xec / 1e3, "k"),
+        _ => (xec, ""),
+    };
+    let value = format!("{:.1}", value);
+    let value = value.strip_suffix(".0").unwrap_or(&value);
+    format!("{}{}", value, suffix)
+}
+
+fn short_hash(hash: &str) -> String {
+    match (hash.get(..8), hash.get(hash.len().saturating_sub(8)..)) {
+        (Some(prefix), Some(suffix)) if hash.len() > 16 => format!("{}…{}", prefix, suffix),
+        _ => hash.to_string(),
+    }
+}
+
+fn short_address(address: &str) -> String {
+    let payload = address.split(':').last().unwrap_or(address);
+    short_hash(payload)
+}
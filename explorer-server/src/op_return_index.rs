@@ -0,0 +1,134 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use bitcoinsuite_chronik_client::proto::{BlockDetails, BlockInfo, Tx};
+
+use crate::{
+    block_follower::BlockConsumer,
+    blockchain::{op_return_protocol, op_return_pushes, to_be_hex},
+    server_primitives::JsonOpReturn,
+};
+
+/// How far back the index is backfilled on startup, about a week.
+const INITIAL_BLOCKS: i32 = 1008;
+const MAX_ENTRIES: usize = 100_000;
+
+/// Text messages in OP_RETURN outputs of recent blocks, newest first, for full-text search.
+///
+/// Kept in memory only and backfilled from the last [`INITIAL_BLOCKS`] blocks on startup,
+/// like the large tx feed. Entries of reorged blocks are dropped and the replacement blocks
+/// are scanned again.
+pub struct OpReturnIndex {
+    entries: Mutex<VecDeque<IndexEntry>>,
+}
+
+struct IndexEntry {
+    op_return: JsonOpReturn,
+    /// Lowercased message, so searches are case-insensitive
+    haystack: String,
+}
+
+impl OpReturnIndex {
+    pub fn new() -> Self {
+        OpReturnIndex {
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Entries containing `query` (case-insensitive), optionally of a single protocol, along
+    /// with the total number of matches.
+    pub fn search(
+        &self,
+        query: &str,
+        protocol: Option<&str>,
+        skip: usize,
+        take: usize,
+    ) -> (Vec<JsonOpReturn>, usize) {
+        let query = query.to_lowercase();
+        let entries = self.entries.lock().unwrap();
+        let matches = entries
+            .iter()
+            .filter(|entry| protocol.map_or(true, |protocol| entry.op_return.protocol == protocol))
+            .filter(|entry| entry.haystack.contains(&query))
+            .collect::<Vec<_>>();
+        let page = matches
+            .iter()
+            .skip(skip)
+            .take(take)
+            .map(|entry| entry.op_return.clone())
+            .collect();
+        (page, matches.len())
+    }
+}
+
+impl BlockConsumer for OpReturnIndex {
+    fn initial_blocks(&self) -> i32 {
+        INITIAL_BLOCKS
+    }
+
+    fn add_block(&self, block_info: &BlockInfo, _block_details: &BlockDetails, txs: &[Tx]) {
+        let new_entries = txs
+            .iter()
+            .flat_map(|tx| tx_op_returns(tx, block_info.height, block_info.timestamp))
+            .collect::<Vec<_>>();
+
+        let mut entries = self.entries.lock().unwrap();
+        for entry in new_entries {
+            entries.push_front(entry);
+        }
+        entries.truncate(MAX_ENTRIES);
+    }
+
+    fn remove_blocks_from(&self, height: i32) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.retain(|entry| entry.op_return.block_height < height);
+    }
+}
+
+impl Default for OpReturnIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn tx_op_returns(tx: &Tx, block_height: i32, timestamp: i64) -> Vec<IndexEntry> {
+    tx.outputs
+        .iter()
+        .enumerate()
+        .filter_map(|(out_idx, output)| {
+            let pushes = op_return_pushes(&output.output_script)?;
+            let (protocol, message) = decode_message(&pushes)?;
+            Some(IndexEntry {
+                haystack: message.to_lowercase(),
+                op_return: JsonOpReturn {
+                    tx_hash: to_be_hex(&tx.txid),
+                    out_idx: out_idx as u32,
+                    block_height,
+                    timestamp,
+                    protocol,
+                    message,
+                },
+            })
+        })
+        .collect()
+}
+
+/// Protocol ("cashtab", "memo" or "other") and text of the pushes, if they're readable.
+fn decode_message(pushes: &[&[u8]]) -> Option<(&'static str, String)> {
+    let (protocol, num_prefix_pushes) = op_return_protocol(pushes);
+    if protocol == "slp" {
+        return None;
+    }
+    let mut texts = Vec::new();
+    for push in &pushes[num_prefix_pushes..] {
+        let text = std::str::from_utf8(push).ok()?;
+        if text.chars().any(|c| c.is_control() && !c.is_whitespace()) {
+            return None;
+        }
+        texts.push(text);
+    }
+    let message = texts.join(" ");
+    if message.trim().is_empty() {
+        return None;
+    }
+    Some((protocol, message))
+}
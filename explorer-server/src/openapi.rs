@@ -0,0 +1,113 @@
+use utoipa::OpenApi;
+
+use crate::{
+    server_http,
+    server_primitives::{
+        JsonAddressBalances, JsonAddressForms, JsonAddressValidation, JsonBalance,
+        JsonBalanceBreakdown, JsonBlock, JsonBlockFullness, JsonBlockFullnessPoint,
+        JsonBlockIntervals, JsonBlocksResponse, JsonBroadcastTxRequest, JsonBroadcastTxResponse,
+        JsonBurned, JsonCoinbaseOutput, JsonCoinbaseSplit, JsonCountdown, JsonCountdowns,
+        JsonCounterpartiesResponse, JsonCounterparty, JsonDoubleSpentInput, JsonHashrate,
+        JsonHashratePoint, JsonHeaders, JsonLargeTx, JsonLargeTxsResponse, JsonMerkleProof,
+        JsonNdjsonTrailer, JsonNdjsonTrailerInfo, JsonNewToken, JsonOpReturn, JsonOpReturnData,
+        JsonOpReturnSearchResponse, JsonPagination, JsonRecentTokensResponse, JsonSignaling,
+        JsonSignalingBit, JsonSlpError, JsonSupply, JsonToken, JsonTokenStats, JsonTokenStatsPoint,
+        JsonTokenTopTransfers, JsonTokenTransfer, JsonTx, JsonTxCountChart, JsonTxCountPoint,
+        JsonTxStats, JsonTxStatus, JsonTxsResponse, JsonUtxo, JsonUtxosResponse,
+        JsonVerifyMessageRequest, JsonVerifyMessageResponse,
+    },
+};
+
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        server_http::data_blocks,
+        server_http::data_block_txs,
+        server_http::data_block_by_height,
+        server_http::data_block_tx,
+        server_http::data_headers,
+        server_http::data_merkle_proof,
+        server_http::data_address_txs,
+        server_http::data_address_balances,
+        server_http::data_address_utxos,
+        server_http::data_address_counterparties,
+        server_http::data_address_forms,
+        server_http::data_validate_address,
+        server_http::data_script_txs,
+        server_http::data_script_balances,
+        server_http::data_script_utxos,
+        server_http::data_supply,
+        server_http::data_burned,
+        server_http::data_hashrate,
+        server_http::data_countdowns,
+        server_http::data_block_intervals,
+        server_http::data_tx_count_chart,
+        server_http::data_tx_status,
+        server_http::data_block_fullness,
+        server_http::data_signaling,
+        server_http::data_large_txs,
+        server_http::data_token_stats,
+        server_http::data_token_top_transfers,
+        server_http::data_recent_tokens,
+        server_http::data_search_op_return,
+        server_http::broadcast_tx,
+        server_http::export_address_txs,
+        server_http::verify_message,
+    ),
+    components(schemas(
+        JsonAddressBalances,
+        JsonAddressForms,
+        JsonAddressValidation,
+        JsonBalance,
+        JsonBalanceBreakdown,
+        JsonBlock,
+        JsonBlockFullness,
+        JsonBlockFullnessPoint,
+        JsonBlockIntervals,
+        JsonBlocksResponse,
+        JsonBroadcastTxRequest,
+        JsonBroadcastTxResponse,
+        JsonBurned,
+        JsonCoinbaseOutput,
+        JsonCoinbaseSplit,
+        JsonCountdown,
+        JsonCountdowns,
+        JsonCounterpartiesResponse,
+        JsonCounterparty,
+        JsonDoubleSpentInput,
+        JsonHashrate,
+        JsonHashratePoint,
+        JsonHeaders,
+        JsonLargeTx,
+        JsonLargeTxsResponse,
+        JsonMerkleProof,
+        JsonNdjsonTrailer,
+        JsonNdjsonTrailerInfo,
+        JsonNewToken,
+        JsonOpReturn,
+        JsonOpReturnData,
+        JsonOpReturnSearchResponse,
+        JsonPagination,
+        JsonRecentTokensResponse,
+        JsonSignaling,
+        JsonSignalingBit,
+        JsonSlpError,
+        JsonSupply,
+        JsonToken,
+        JsonTokenStats,
+        JsonTokenStatsPoint,
+        JsonTokenTopTransfers,
+        JsonTokenTransfer,
+        JsonTx,
+        JsonTxCountChart,
+        JsonTxCountPoint,
+        JsonTxStats,
+        JsonTxStatus,
+        JsonTxsResponse,
+        JsonUtxo,
+        JsonUtxosResponse,
+        JsonVerifyMessageRequest,
+        JsonVerifyMessageResponse,
+    ))
+)]
+pub struct ApiDoc;
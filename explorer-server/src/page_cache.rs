@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bitcoinsuite_chronik_client::proto::BlockInfo;
+
+use crate::{preferences::UserLocale, server_primitives::JsonBlocksResponse};
+
+/// Entries are re-rendered after this long even if the tip hasn't moved, since the homepage
+/// also shows feeds that catch up with a new block a little later than the warmer runs.
+const MAX_AGE: Duration = Duration::from_secs(60);
+/// Block ranges cached per tip; the blocks page only asks for a handful of page sizes.
+const MAX_BLOCK_RANGES: usize = 16;
+
+/// Responses that only change when the chain tip does, and the data they're built from.
+///
+/// The warmer fetches the data as soon as a block arrives so the first visitors after it
+/// don't wait on chronik; pages are rendered on their first request, once per locale.
+/// Everything is tagged with the tip it was fetched at and dropped once the tip moves on.
+pub struct PageCache {
+    state: Mutex<CacheState>,
+}
+
+struct CacheState {
+    tip_hash: Vec<u8>,
+    tip_block: Option<BlockInfo>,
+    homepages: HashMap<UserLocale, (String, Instant)>,
+    /// Ranges reaching up to the tip, by start and end height as requested
+    latest_blocks: HashMap<(i32, i32), (JsonBlocksResponse, Instant)>,
+}
+
+impl CacheState {
+    /// Drops everything if it was rendered at a different tip.
+    fn at_tip(&mut self, tip_hash: &[u8]) -> &mut Self {
+        if self.tip_hash != tip_hash {
+            self.tip_hash = tip_hash.to_vec();
+            self.tip_block = None;
+            self.homepages.clear();
+            self.latest_blocks.clear();
+        }
+        self
+    }
+}
+
+impl PageCache {
+    pub fn new() -> Self {
+        PageCache {
+            state: Mutex::new(CacheState {
+                tip_hash: Vec::new(),
+                tip_block: None,
+                homepages: HashMap::new(),
+                latest_blocks: HashMap::new(),
+            }),
+        }
+    }
+
+    /// Info of the tip block itself, which never changes for a given tip.
+    pub fn tip_block(&self, tip_hash: &[u8]) -> Option<BlockInfo> {
+        let mut state = self.state.lock().unwrap();
+        state.at_tip(tip_hash).tip_block.clone()
+    }
+
+    pub fn insert_tip_block(&self, tip_hash: &[u8], tip_block: BlockInfo) {
+        let mut state = self.state.lock().unwrap();
+        state.at_tip(tip_hash).tip_block = Some(tip_block);
+    }
+
+    pub fn homepage(&self, tip_hash: &[u8], locale: UserLocale) -> Option<String> {
+        let mut state = self.state.lock().unwrap();
+        let (html, rendered_at) = state.at_tip(tip_hash).homepages.get(&locale)?;
+        (rendered_at.elapsed() < MAX_AGE).then(|| html.clone())
+    }
+
+    pub fn insert_homepage(&self, tip_hash: &[u8], locale: UserLocale, html: String) {
+        let mut state = self.state.lock().unwrap();
+        state
+            .at_tip(tip_hash)
+            .homepages
+            .insert(locale, (html, Instant::now()));
+    }
+
+    pub fn latest_blocks(
+        &self,
+        tip_hash: &[u8],
+        start_height: i32,
+        end_height: i32,
+    ) -> Option<JsonBlocksResponse> {
+        let mut state = self.state.lock().unwrap();
+        let (blocks, rendered_at) = state
+            .at_tip(tip_hash)
+            .latest_blocks
+            .get(&(start_height, end_height))?;
+        (rendered_at.elapsed() < MAX_AGE).then(|| blocks.clone())
+    }
+
+    pub fn insert_latest_blocks(
+        &self,
+        tip_hash: &[u8],
+        start_height: i32,
+        end_height: i32,
+        blocks: JsonBlocksResponse,
+    ) {
+        let mut state = self.state.lock().unwrap();
+        let latest_blocks = &mut state.at_tip(tip_hash).latest_blocks;
+        if latest_blocks.len() < MAX_BLOCK_RANGES {
+            latest_blocks.insert((start_height, end_height), (blocks, Instant::now()));
+        }
+    }
+}
+
+impl Default for PageCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,283 @@
+use std::{cmp::Ordering, convert::Infallible};
+
+use axum::{
+    async_trait,
+    extract::{FromRequest, RequestParts},
+    http::{header, HeaderMap},
+};
+use chrono_tz::Tz;
+use num_format::Locale;
+
+/// Display preferences of the visitor, from the preference cookies if set and otherwise
+/// negotiated from the request headers.
+#[derive(Clone, Copy)]
+pub struct Preferences {
+    pub locale: UserLocale,
+    pub unit: AmountUnit,
+    /// Timezone timestamps are shown in, UTC unless chosen via `?tz=` or the `tz` cookie
+    pub timezone: Tz,
+}
+
+/// Unit amounts of XEC are displayed in; amounts are always stored and served as sats.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum AmountUnit {
+    Xec,
+    Sats,
+    /// 8 decimals like BCH, i.e. the unit eCash had before the XEC redenomination
+    Bcha,
+}
+
+/// Locales the explorer formats numbers and dates for.
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UserLocale {
+    En,
+    De,
+    Es,
+    Fr,
+    It,
+    Ja,
+    Nl,
+    Pt,
+    Ru,
+    Zh,
+}
+
+impl UserLocale {
+    pub const ALL: [UserLocale; 10] = [
+        UserLocale::En,
+        UserLocale::De,
+        UserLocale::Es,
+        UserLocale::Fr,
+        UserLocale::It,
+        UserLocale::Ja,
+        UserLocale::Nl,
+        UserLocale::Pt,
+        UserLocale::Ru,
+        UserLocale::Zh,
+    ];
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            UserLocale::En => "en",
+            UserLocale::De => "de",
+            UserLocale::Es => "es",
+            UserLocale::Fr => "fr",
+            UserLocale::It => "it",
+            UserLocale::Ja => "ja",
+            UserLocale::Nl => "nl",
+            UserLocale::Pt => "pt",
+            UserLocale::Ru => "ru",
+            UserLocale::Zh => "zh",
+        }
+    }
+
+    /// Parses a language tag like `de-CH`; only the primary language subtag is considered.
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = tag
+            .split(|c| c == '-' || c == '_')
+            .next()?
+            .to_ascii_lowercase();
+        let locale = match language.as_str() {
+            "en" => UserLocale::En,
+            "de" => UserLocale::De,
+            "es" => UserLocale::Es,
+            "fr" => UserLocale::Fr,
+            "it" => UserLocale::It,
+            "ja" => UserLocale::Ja,
+            "nl" => UserLocale::Nl,
+            "pt" => UserLocale::Pt,
+            "ru" => UserLocale::Ru,
+            "zh" => UserLocale::Zh,
+            _ => return None,
+        };
+        Some(locale)
+    }
+
+    pub fn number_locale(self) -> Locale {
+        match self {
+            UserLocale::En => Locale::en,
+            UserLocale::De => Locale::de,
+            UserLocale::Es => Locale::es,
+            UserLocale::Fr => Locale::fr,
+            UserLocale::It => Locale::it,
+            UserLocale::Ja => Locale::ja,
+            UserLocale::Nl => Locale::nl,
+            UserLocale::Pt => Locale::pt,
+            UserLocale::Ru => Locale::ru,
+            UserLocale::Zh => Locale::zh,
+        }
+    }
+
+    /// `strftime` pattern for a full date and time; numeric, so no month names need translating.
+    pub fn datetime_format(self) -> &'static str {
+        match self {
+            UserLocale::En => "%b %-d, %Y, %H:%M:%S",
+            UserLocale::De | UserLocale::Ru => "%d.%m.%Y, %H:%M:%S",
+            UserLocale::Es | UserLocale::Fr | UserLocale::It | UserLocale::Pt => {
+                "%d/%m/%Y %H:%M:%S"
+            }
+            UserLocale::Nl => "%d-%m-%Y %H:%M:%S",
+            UserLocale::Ja | UserLocale::Zh => "%Y/%m/%d %H:%M:%S",
+        }
+    }
+}
+
+impl AmountUnit {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "xec" => Some(AmountUnit::Xec),
+            "sats" => Some(AmountUnit::Sats),
+            "bcha" => Some(AmountUnit::Bcha),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            AmountUnit::Xec => "XEC",
+            AmountUnit::Sats => "sats",
+            AmountUnit::Bcha => "BCHA",
+        }
+    }
+
+    pub fn decimals(self) -> u32 {
+        match self {
+            AmountUnit::Xec => 2,
+            AmountUnit::Sats => 0,
+            AmountUnit::Bcha => 8,
+        }
+    }
+}
+
+impl Default for AmountUnit {
+    fn default() -> Self {
+        AmountUnit::Xec
+    }
+}
+
+impl Preferences {
+    /// Query parameters (e.g. `?unit=sats`) take precedence over cookies, so links can pin them.
+    pub fn from_request_parts(query: Option<&str>, headers: &HeaderMap) -> Self {
+        let locale = cookie(headers, "locale")
+            .and_then(UserLocale::from_tag)
+            .or_else(|| accept_language(headers))
+            .unwrap_or(UserLocale::En);
+        let unit = query_param(query, "unit")
+            .or_else(|| cookie(headers, "unit"))
+            .and_then(AmountUnit::from_name)
+            .unwrap_or_default();
+        let timezone = query_param(query, "tz")
+            .or_else(|| cookie(headers, "tz"))
+            .and_then(percent_decode)
+            .and_then(|timezone| timezone.parse().ok())
+            .unwrap_or(Tz::UTC);
+
+        Preferences {
+            locale,
+            unit,
+            timezone,
+        }
+    }
+}
+
+#[async_trait]
+impl<B: Send> FromRequest<B> for Preferences {
+    type Rejection = Infallible;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        Ok(Preferences::from_request_parts(
+            req.uri().query(),
+            req.headers(),
+        ))
+    }
+}
+
+/// Timezone names like `America/New_York` or `Etc/GMT+1` arrive URL-encoded.
+fn percent_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(byte) = iter.next() {
+        if byte == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            // from_str_radix alone would also accept a sign, e.g. "%+1"
+            if !hex.iter().all(u8::is_ascii_hexdigit) {
+                return None;
+            }
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(byte);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query?.split('&').find_map(|pair| {
+        let (key, value) = pair.split_once('=')?;
+        (key == name).then(|| value)
+    })
+}
+
+pub fn cookie<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .find_map(|pair| {
+            let (key, value) = pair.trim().split_once('=')?;
+            (key == name).then(|| value)
+        })
+}
+
+/// Most preferred supported locale of the `Accept-Language` header.
+fn accept_language(headers: &HeaderMap) -> Option<UserLocale> {
+    let accept_language = headers.get(header::ACCEPT_LANGUAGE)?.to_str().ok()?;
+    let mut ranges = accept_language
+        .split(',')
+        .filter_map(|range| {
+            let mut parts = range.split(';');
+            let tag = parts.next()?.trim();
+            let quality = parts
+                .find_map(|param| param.trim().strip_prefix("q="))
+                .map(|quality| quality.parse::<f32>().unwrap_or(0.0))
+                .unwrap_or(1.0);
+            Some((tag, quality))
+        })
+        .collect::<Vec<_>>();
+    // Stable, so ranges of equal quality keep the client's order
+    ranges.sort_by(|(_, a), (_, b)| b.partial_cmp(a).unwrap_or(Ordering::Equal));
+
+    ranges
+        .into_iter()
+        .filter(|(_, quality)| *quality > 0.0)
+        .find_map(|(tag, _)| UserLocale::from_tag(tag))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::percent_decode;
+
+    #[test]
+    fn percent_decode_timezones() {
+        assert_eq!(
+            percent_decode("America%2FNew_York").as_deref(),
+            Some("America/New_York")
+        );
+        assert_eq!(
+            percent_decode("Etc%2fGMT%2B1").as_deref(),
+            Some("Etc/GMT+1")
+        );
+        assert_eq!(percent_decode("UTC").as_deref(), Some("UTC"));
+    }
+
+    #[test]
+    fn percent_decode_rejects_malformed() {
+        assert_eq!(percent_decode("Europe%2"), None);
+        assert_eq!(percent_decode("Europe%"), None);
+        assert_eq!(percent_decode("Europe%zzBerlin"), None);
+        assert_eq!(percent_decode("Europe%+1Berlin"), None);
+        // Decodes to bytes that aren't UTF-8
+        assert_eq!(percent_decode("%ff%fe"), None);
+    }
+}
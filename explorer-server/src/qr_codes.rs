@@ -0,0 +1,163 @@
+use std::{collections::HashMap, path::Path, sync::Mutex};
+
+use bitcoinsuite_error::Result;
+use eyre::{bail, eyre};
+use qrcode_generator::QrCodeEcc;
+
+use crate::status::StatusRegistry;
+
+pub const DEFAULT_SIZE: usize = 160;
+const MIN_SIZE: usize = 64;
+const MAX_SIZE: usize = 1024;
+const MAX_ENTRIES: usize = 1000;
+/// Width of the center logo, as a fraction of the code's width; small enough for the
+/// high error correction level to recover the modules it covers.
+const LOGO_FRACTION: f64 = 0.2;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QrFormat {
+    Png,
+    Svg,
+}
+
+impl QrFormat {
+    pub fn parse(format: &str) -> Result<Self> {
+        match format {
+            "png" => Ok(QrFormat::Png),
+            "svg" => Ok(QrFormat::Svg),
+            _ => bail!("format must be png or svg"),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            QrFormat::Png => "image/png",
+            QrFormat::Svg => "image/svg+xml",
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct QrCodeKey {
+    address: String,
+    size: usize,
+    format: QrFormat,
+    with_logo: bool,
+}
+
+/// Generated address QR codes, keyed by every parameter they were generated with.
+///
+/// Codes are cheap to regenerate, so the whole cache is simply dropped once it's full.
+pub struct QrCodes {
+    logo_png: Vec<u8>,
+    codes: Mutex<HashMap<QrCodeKey, Vec<u8>>>,
+}
+
+/// Checks the parameters of a QR code before it's requested with [`QrCodes::get`].
+pub fn validate_qr_params(
+    address: &str,
+    size: usize,
+    format: QrFormat,
+    with_logo: bool,
+) -> Result<()> {
+    if address.len() > 60 {
+        bail!("Invalid address length");
+    }
+    if !(MIN_SIZE..=MAX_SIZE).contains(&size) {
+        bail!("size must be between {} and {}", MIN_SIZE, MAX_SIZE);
+    }
+    if with_logo && format != QrFormat::Svg {
+        bail!("logo is only supported with format=svg");
+    }
+    Ok(())
+}
+
+impl QrCodes {
+    /// Loads the logo put in the center of codes that ask for one from `assets/logo.png`;
+    /// fails if it's missing, rather than leaving it out of those codes.
+    pub fn load(base_dir: &Path) -> Result<Self> {
+        let logo_path = base_dir.join("assets").join("logo.png");
+        let logo_png = std::fs::read(&logo_path)
+            .map_err(|err| eyre!("Failed to read {}: {}", logo_path.display(), err))?;
+        Ok(QrCodes {
+            logo_png,
+            codes: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// A QR code of `address`, with parameters checked by [`validate_qr_params`].
+    pub fn get(
+        &self,
+        status: &StatusRegistry,
+        address: &str,
+        size: usize,
+        format: QrFormat,
+        with_logo: bool,
+    ) -> Result<Vec<u8>> {
+        let key = QrCodeKey {
+            address: address.to_string(),
+            size,
+            format,
+            with_logo,
+        };
+        if let Some(code) = self.codes.lock().unwrap().get(&key) {
+            status.record_cache_lookup("qr_codes", true);
+            return Ok(code.clone());
+        }
+        status.record_cache_lookup("qr_codes", false);
+
+        let code = self.generate(&key)?;
+        let mut codes = self.codes.lock().unwrap();
+        if codes.len() >= MAX_ENTRIES {
+            codes.clear();
+        }
+        codes.insert(key, code.clone());
+        Ok(code)
+    }
+
+    fn generate(&self, key: &QrCodeKey) -> Result<Vec<u8>> {
+        match key.format {
+            QrFormat::Png => Ok(qrcode_generator::to_png_to_vec(
+                &key.address,
+                QrCodeEcc::Quartile,
+                key.size,
+            )?),
+            QrFormat::Svg if key.with_logo => {
+                let svg = qrcode_generator::to_svg_to_string(
+                    &key.address,
+                    QrCodeEcc::High,
+                    key.size,
+                    None::<&str>,
+                )?;
+                Ok(with_center_logo(&svg, key.size, &self.logo_png).into_bytes())
+            }
+            QrFormat::Svg => Ok(qrcode_generator::to_svg_to_string(
+                &key.address,
+                QrCodeEcc::Quartile,
+                key.size,
+                None::<&str>,
+            )?
+            .into_bytes()),
+        }
+    }
+}
+
+/// Embeds `logo_png` on a white square in the middle of `svg`.
+fn with_center_logo(svg: &str, size: usize, logo_png: &[u8]) -> String {
+    let logo_size = (size as f64 * LOGO_FRACTION).round();
+    let offset = (size as f64 - logo_size) / 2.0;
+    let logo = format!(
+        concat!(
+            r#"<rect x="{offset}" y="{offset}" width="{logo_size}" height="{logo_size}" fill="#FFFFFF"/>"#,
+            r#"<image x="{offset}" y="{offset}" width="{logo_size}" height="{logo_size}" "#,
+            r#"href="data:image/png;base64,{logo}"/>"#,
+        ),
+        offset = offset,
+        logo_size = logo_size,
+        logo = base64::encode(logo_png),
+    );
+    match svg.rfind("</svg>") {
+        Some(end) => format!("{}{}{}", &svg[..end], logo, &svg[end..]),
+        None => svg.to_string(),
+    }
+}
@@ -1,117 +1,1415 @@
 use askama::Template;
-use axum::{response::Redirect, routing::get, Router};
-use bitcoinsuite_chronik_client::proto::{SlpTokenType, SlpTxType, Token, Utxo};
-use bitcoinsuite_chronik_client::{proto::OutPoint, ChronikClient};
+use axum::{
+    http::HeaderMap,
+    middleware,
+    response::Redirect,
+    routing::{get, post},
+    Router,
+};
+use bitcoinsuite_chronik_client::proto::{
+    Block, BlockInfo, BlockchainInfo, SlpTokenType, SlpTxType, Token, Tx, TxHistoryPage, Utxo,
+};
+use bitcoinsuite_chronik_client::{ChronikClient, ScriptType};
 use bitcoinsuite_core::{CashAddress, Hashed, Sha256d};
 use bitcoinsuite_error::Result;
 use chrono::{TimeZone, Utc};
 use eyre::{bail, eyre};
-use futures::future;
-use std::path::PathBuf;
+use futures::{
+    future::{self, BoxFuture, Shared},
+    stream,
+    stream::BoxStream,
+    Future, FutureExt, Stream, StreamExt,
+};
+use std::time::{Duration, Instant};
 use std::{
     borrow::Cow,
     collections::{hash_map::Entry, HashMap, HashSet},
 };
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+};
+use tokio::sync::{watch, OwnedSemaphorePermit, Semaphore};
+use tower_http::{compression::CompressionLayer, cors::CorsLayer};
+use tracing::{error, warn};
 
+#[cfg(feature = "graphql")]
+use crate::graphql::ExplorerSchema;
 use crate::{
-    api::{block_txs_to_json, calc_tx_stats, tokens_to_json, tx_history_to_json},
+    api::{
+        block_info_to_json, block_txs_to_json, calc_tx_stats, coinbase_split_to_json,
+        double_spent_inputs, raw_token_section, slp_errors, tokens_to_json, tx_history_to_json,
+        tx_to_json, utxo_balance_key, utxo_to_json, HistoryCursor,
+    },
+    assets::load_asset_hashes,
+    auth::ApiKeys,
+    balances::BalanceBreakdown,
+    block_cache::BlockCache,
+    block_follower::{BlockConsumer, BlockFollower},
     blockchain::{
-        calculate_block_difficulty, cash_addr_to_script_type_payload, from_be_hex, to_be_hex,
-        to_legacy_address,
+        address_network, address_type_name, calculate_block_difficulty,
+        cash_addr_to_script_type_payload, destination_from_script, from_be_hex, merkle_branch,
+        parse_address, parse_payment_uri, parse_xec_amount, script_to_script_type_payload,
+        to_be_hex, to_legacy_address, total_issued_sats, Destination, MAX_BLOCK_SIZE,
+        TARGET_BLOCK_SPACING_SECS,
     },
+    burn_tracker::BurnTracker,
+    chain_events::{receiver_stream, script_events, ChainEvent, ChainEvents},
+    config::{Config, Network},
+    identicon::token_identicon_svg,
+    large_tx_feed::{LargeTxFeed, MIN_TRACKED_SATS},
+    logging,
+    mempool_activity::MempoolActivity,
+    meta::{address_meta, block_meta, preview_png, tx_meta, PreviewKind},
+    op_return_index::OpReturnIndex,
+    page_cache::PageCache,
+    preferences::{AmountUnit, Preferences, UserLocale},
+    qr_codes::{self, validate_qr_params, QrCodes, QrFormat},
+    server_error::not_found_to_none,
     server_http::{
-        address, address_qr, block, block_height, blocks, data_address_txs, data_block_txs,
-        data_blocks, homepage, search, serve_files, tx,
+        address, address_qr, admin, api_docs, block, block_height, blocks, broadcast_tx,
+        data_address_balances, data_address_counterparties, data_address_forms, data_address_txs,
+        data_address_utxos, data_block_by_height, data_block_fullness, data_block_intervals,
+        data_block_tx, data_block_txs, data_blocks, data_burned, data_countdowns, data_hashrate,
+        data_headers, data_large_txs, data_merkle_proof, data_recent_tokens, data_script_balances,
+        data_script_txs, data_script_utxos, data_search_op_return, data_signaling, data_supply,
+        data_token_stats, data_token_top_transfers, data_tx_count_chart, data_tx_status,
+        data_validate_address, events, export_address_txs, hashrate_chart, homepage, identicon,
+        large_txs_rss, metrics, openapi_spec, preview_image, ready, script, search, serve_files,
+        sitemap_blocks, sitemap_index, tx, verify_message, ws,
+    },
+    server_middleware::{self, basic_auth_password, secrets_match, API_VERSION},
+    server_primitives::{
+        JsonAddressBalances, JsonAddressForms, JsonAddressValidation, JsonBalance, JsonBlock,
+        JsonBlockFullness, JsonBlockFullnessPoint, JsonBlockIntervals, JsonBlocksResponse,
+        JsonBroadcastTxResponse, JsonBurned, JsonCountdowns, JsonCounterpartiesResponse,
+        JsonDoubleSpentInput, JsonHashrate, JsonHashratePoint, JsonHeaders, JsonLargeTxsResponse,
+        JsonMerkleProof, JsonNdjsonTrailer, JsonNdjsonTrailerInfo, JsonOpReturnSearchResponse,
+        JsonPagination, JsonRecentTokensResponse, JsonSignaling, JsonSupply, JsonToken,
+        JsonTokenStats, JsonTokenTopTransfers, JsonTx, JsonTxCountChart, JsonTxStatus,
+        JsonTxsResponse, JsonUtxosResponse, JsonVerifyMessageRequest, JsonVerifyMessageResponse,
     },
-    server_primitives::{JsonBalance, JsonBlock, JsonBlocksResponse, JsonTxsResponse, JsonUtxo},
+    signed_message,
+    stats::{
+        average_difficulty, block_fullness, block_intervals, countdowns, counterparties,
+        daily_tx_counts, estimate_hashrate, SECONDS_PER_DAY,
+    },
+    status::StatusRegistry,
     templating::{
-        AddressTemplate, BlockTemplate, BlocksTemplate, HomepageTemplate, TransactionTemplate,
+        AddressTemplate, AdminTemplate, ApiDocsTemplate, BlockTemplate, BlocksTemplate,
+        HashrateTemplate, HomepageTemplate, LargeTxsRssTemplate, ScriptTemplate,
+        SitemapBlocksTemplate, SitemapIndexTemplate, TokenBalance, TransactionTemplate,
     },
+    tip_cache::{PerTipCache, TipCache},
+    token_cache::TokenCache,
+    token_filter::TokenFilter,
+    token_genesis_feed::TokenGenesisFeed,
+    token_stats::{TokenStats, MAX_TOP_TRANSFERS, MAX_WINDOW_DAYS},
+    translations::Translations,
+    tx_positions::TxPositionCache,
+    version_bits::{signaled_bits, VersionBitsTracker, MAX_SIGNALING_WINDOW},
+    watchdog::Watchdog,
 };
 
+/// Which UTXOs of a script to list, see [`Server::validate_utxos_query`].
+pub struct UtxosQuery {
+    /// Token ID in lowercase hex, plain XEC UTXOs if `None`
+    pub token_id: Option<String>,
+    pub page: usize,
+    pub take: usize,
+}
+
+struct AddressBalances {
+    json_balances: HashMap<String, JsonBalance>,
+    token_utxos: Vec<Utxo>,
+    tokens: HashMap<String, Token>,
+    breakdown: BalanceBreakdown,
+    /// Net change of the XEC balance by unconfirmed txs, already included in it
+    unconfirmed_xec: i64,
+    /// Whether the unconfirmed amounts leave out some of the unconfirmed txs
+    unconfirmed_truncated: bool,
+    num_hidden_tokens: usize,
+}
+
+/// Summaries of the token balances for address and script pages, in a stable order.
+fn token_balances(json_balances: HashMap<String, JsonBalance>) -> Vec<TokenBalance> {
+    let mut token_balances = json_balances
+        .into_iter()
+        .filter(|(token_id, _)| token_id != "main")
+        .map(|(token_id, json_balance)| TokenBalance {
+            token_id,
+            sats_amount: json_balance.sats_amount,
+            token_amount: json_balance.token_amount,
+            num_utxos: json_balance.utxos.len(),
+        })
+        .collect::<Vec<_>>();
+    token_balances.sort_by(|a, b| a.token_id.cmp(&b.token_id));
+    token_balances
+}
+
+/// Pending or finished status lookup of a tx, `None` if it's unknown or failed to load.
+type TxStatusCheck = Shared<BoxFuture<'static, Option<JsonTxStatus>>>;
+
+fn to_ndjson(txs: &[JsonTx]) -> Result<String> {
+    let mut lines = String::new();
+    for tx in txs {
+        lines.push_str(&serde_json::to_string(tx)?);
+        lines.push('\n');
+    }
+    Ok(lines)
+}
+
+/// Encodes a query string value, for pagination links echoing user input.
+fn urlencode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{:02X}", byte),
+        })
+        .collect()
+}
+
+/// Parses `?page=` and `?take=` of a script's txs. With `?token_id=`, the txs are filtered
+/// from the most recent [`TOKEN_HISTORY_MAX_TXS`] only, so pages past those are refused.
+fn parse_txs_paging(query: &HashMap<String, String>) -> Result<(usize, usize)> {
+    let page: usize = query
+        .get("page")
+        .map(|s| s.as_str())
+        .unwrap_or("0")
+        .parse()?;
+    let take: usize = query
+        .get("take")
+        .map(|s| s.as_str())
+        .unwrap_or("200")
+        .parse()?;
+    if !(1..=MAX_TXS_PAGE_SIZE).contains(&take) {
+        bail!("take must be between 1 and {}", MAX_TXS_PAGE_SIZE);
+    }
+    let offset = page
+        .checked_mul(take)
+        .ok_or_else(|| eyre!("page is too large"))?;
+    if query.contains_key("token_id") && offset >= TOKEN_HISTORY_MAX_TXS {
+        bail!(
+            "With token_id, page * take must be below {}",
+            TOKEN_HISTORY_MAX_TXS
+        );
+    }
+    Ok((page, take))
+}
+
+/// Page `page` of `items` in pages of `take`, and whether there are items after it.
+fn page_of<T>(items: Vec<T>, page: usize, take: usize) -> (Vec<T>, bool) {
+    let has_next = items.len() > page.saturating_add(1).saturating_mul(take);
+    let page_items = items
+        .into_iter()
+        .skip(page.saturating_mul(take))
+        .take(take)
+        .collect();
+    (page_items, has_next)
+}
+
+/// Parses `?window=` and `?points=` of the charts over consecutive block windows, returning
+/// them in that order. The number of points defaults to as many as fit into
+/// [`MAX_CHART_BLOCKS`], up to 30.
+fn parse_chart_windows(query: &HashMap<String, String>, min_window: i32) -> Result<(i32, i32)> {
+    const MAX_POINTS: i32 = 100;
+
+    let window: i32 = query
+        .get("window")
+        .map(|s| s.as_str())
+        .unwrap_or("144")
+        .parse()?;
+    if !(min_window..=MAX_CHART_BLOCKS).contains(&window) {
+        bail!(
+            "window must be between {} and {}",
+            min_window,
+            MAX_CHART_BLOCKS
+        );
+    }
+    let max_points = (MAX_CHART_BLOCKS / window).min(MAX_POINTS);
+    let num_points: i32 = match query.get("points") {
+        Some(points) => points.parse()?,
+        None => max_points.min(30),
+    };
+    if !(1..=max_points).contains(&num_points) {
+        bail!("points must be between 1 and {}", max_points);
+    }
+    Ok((window, num_points))
+}
+
+fn script_kind(script_type: ScriptType) -> &'static str {
+    match script_type {
+        ScriptType::P2pk => "Pay to Public Key",
+        ScriptType::P2pkh => "Pay to Public Key Hash",
+        ScriptType::P2sh => "Pay to Script Hash",
+        _ => "Nonstandard",
+    }
+}
+
+const MAX_BLOCK_TXS_PAGE_SIZE: usize = 1000;
+const MAX_UTXOS_PAGE_SIZE: usize = 1000;
+const MAX_TXS_PAGE_SIZE: usize = 200;
+const NDJSON_CHUNK_SIZE: usize = 200;
+/// Txs an address history NDJSON stream holds at most; exports page through the full history
+/// with the cursor of their trailer line.
+const MAX_NDJSON_TXS: usize = 10_000;
+const _: () = assert!(MAX_NDJSON_TXS % NDJSON_CHUNK_SIZE == 0);
+const MAX_BLOCKS_PER_PAGE: i32 = 1000;
+/// Chronik only has raw headers along with the whole block, txs included, so this is kept
+/// small.
+const MAX_HEADERS: i32 = 10;
+/// Counterparties are counted over at most this many of an address's most recent txs.
+const COUNTERPARTIES_MAX_TXS: usize = 2000;
+const COUNTERPARTIES_PAGE_SIZE: usize = 200;
+const MAX_COUNTERPARTIES_LIMIT: usize = 1000;
+/// Addresses whose counterparties are kept per tip.
+const MAX_CACHED_COUNTERPARTIES: usize = 256;
+/// Counterparties also count mempool txs, so they're recomputed this often within a block.
+const COUNTERPARTIES_MAX_AGE: Duration = Duration::from_secs(60);
+/// A token's history of a script is filtered from at most this many of its most recent txs.
+const TOKEN_HISTORY_MAX_TXS: usize = 2000;
+const TOKEN_HISTORY_PAGE_SIZE: usize = 200;
+/// Page size of the history scanned for a script's unconfirmed txs; most scripts have fewer,
+/// so one page ending in a confirmed tx covers them all.
+const MEMPOOL_PAGE_SIZE: usize = 25;
+/// Pages of a script's history scanned at most for unconfirmed txs.
+const MEMPOOL_MAX_PAGES: usize = 20;
+/// Previous txs looked up to find conflicting spends of an unconfirmed tx's inputs, and how
+/// many of them at once.
+const DOUBLE_SPEND_MAX_PREV_TXS: usize = 20;
+const DOUBLE_SPEND_CONCURRENT_LOOKUPS: usize = 4;
+const MAX_CACHED_DOUBLE_SPENDS: usize = 1024;
+/// Conflicts can show up in the mempool within a block, so they're rechecked this often.
+const DOUBLE_SPENDS_MAX_AGE: Duration = Duration::from_secs(30);
+
+/// Charts over block infos span at most this many blocks, about two weeks.
+const MAX_CHART_BLOCKS: i32 = 2016;
+/// Charts over block infos only change with the tip, so they're cached per tip for each of
+/// this many parameter combinations.
+const MAX_CACHED_CHARTS: usize = 64;
+const CHART_MAX_AGE: Duration = Duration::from_secs(600);
+/// Pages whose preview image is kept per tip.
+const MAX_CACHED_PREVIEWS: usize = 256;
+/// Address previews count mempool txs, so they're rerendered this often within a block.
+const PREVIEW_MAX_AGE: Duration = Duration::from_secs(60);
+
+/// Sitemaps may list at most 50,000 URLs; smaller chunks keep each one cheap to generate.
+const SITEMAP_BLOCKS_PER_CHUNK: i32 = 10_000;
+/// Enough for every block sitemap chunk, about a hundred, to stay cached within a block.
+const MAX_CACHED_SITEMAPS: usize = 256;
+/// Each `?address=` subscriber of `/events` and `/ws` gets its own chronik WebSocket.
+const MAX_ADDRESS_SUBSCRIPTIONS: usize = 100;
+/// Rows per page of the blocks page, whose first page the warmer pre-renders.
+const LATEST_BLOCKS_PAGE_SIZE: i32 = 100;
+
 pub struct Server {
     chronik: ChronikClient,
     base_dir: PathBuf,
+    public_url: String,
+    cors: Option<CorsLayer>,
+    status: StatusRegistry,
+    translations: Translations,
+    admin_password: Option<String>,
+    api_keys: ApiKeys,
+    burn_addresses: Vec<CashAddress<'static>>,
+    burn_scripts: HashSet<Vec<u8>>,
+    burn_tracker: BurnTracker,
+    token_filter: TokenFilter,
+    block_follower: BlockFollower,
+    large_tx_feed: LargeTxFeed,
+    tx_positions: TxPositionCache,
+    op_return_index: OpReturnIndex,
+    token_genesis_feed: TokenGenesisFeed,
+    token_stats: TokenStats,
+    version_bits: VersionBitsTracker,
+    chain_events: ChainEvents,
+    /// Status lookups of `?tx=` subscribers for the newest connected block, by txid, shared
+    /// by everyone waiting on the same tx
+    tx_status_checks: Mutex<(String, HashMap<Sha256d, TxStatusCheck>)>,
+    /// Chronik WebSockets left for `?address=` subscribers
+    address_subscriptions: Arc<Semaphore>,
+    tip_cache: TipCache,
+    /// By txid of the unconfirmed tx
+    double_spend_cache: PerTipCache<Vec<u8>, Vec<JsonDoubleSpentInput>>,
+    /// By output script, before `?limit=` is applied
+    counterparties_cache: PerTipCache<Vec<u8>, JsonCounterpartiesResponse>,
+    /// By window and number of points
+    hashrate_cache: PerTipCache<(i32, i32), JsonHashrate>,
+    /// By window and number of points
+    block_fullness_cache: PerTipCache<(i32, i32), JsonBlockFullness>,
+    /// By `from` and `to`
+    tx_count_cache: PerTipCache<(i64, i64), JsonTxCountChart>,
+    /// PNGs by page kind and normalized hash
+    preview_cache: PerTipCache<(PreviewKind, String), Vec<u8>>,
+    /// Block sitemaps by chunk
+    sitemap_cache: PerTipCache<i32, String>,
+    token_cache: TokenCache,
+    block_cache: BlockCache,
+    page_cache: PageCache,
+    qr_codes: QrCodes,
+    watchdog: Watchdog,
+    run_indexer: bool,
+    /// Flipped to `true` once the process is asked to stop; the receiver is kept so sending
+    /// never fails for lack of subscribers
+    shutdown: (watch::Sender<bool>, watch::Receiver<bool>),
+    network: Network,
     satoshi_addr_prefix: &'static str,
     tokens_addr_prefix: &'static str,
+    #[cfg(feature = "graphql")]
+    pub graphql_schema: ExplorerSchema,
 }
 
 impl Server {
-    pub async fn setup(chronik: ChronikClient, base_dir: PathBuf) -> Result<Self> {
+    pub async fn setup(chronik: ChronikClient, config: &Config) -> Result<Self> {
+        let base_dir = config
+            .base_dir
+            .clone()
+            .unwrap_or_else(|| "../explorer-server".into());
+        let translations = Translations::load(&base_dir.join("locales"))?;
+        logging::init(config.log_format);
+        load_asset_hashes(&base_dir)?;
+        let public_url = config
+            .public_url
+            .as_deref()
+            .unwrap_or("https://explorer.be.cash")
+            .trim_end_matches('/')
+            .to_string();
+        let cors = config
+            .cors
+            .as_ref()
+            .map(server_middleware::cors_layer)
+            .transpose()?;
+        let burn_addresses = config
+            .burn_addresses
+            .iter()
+            .map(|address| CashAddress::parse_cow(address.clone().into()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let burn_scripts = burn_addresses
+            .iter()
+            .map(|address| address.to_script().bytecode().to_vec())
+            .collect();
+
         Ok(Server {
             chronik,
             base_dir,
-            satoshi_addr_prefix: "ecash",
-            tokens_addr_prefix: "etoken",
+            public_url,
+            cors,
+            status: StatusRegistry::new(),
+            translations,
+            admin_password: config.admin_password.clone(),
+            api_keys: ApiKeys::new(config.api_keys.clone()),
+            burn_addresses,
+            burn_scripts,
+            burn_tracker: BurnTracker::new(),
+            token_filter: TokenFilter::new(&config.token_filter),
+            block_follower: BlockFollower::new(),
+            large_tx_feed: LargeTxFeed::new(),
+            tx_positions: TxPositionCache::new(),
+            op_return_index: OpReturnIndex::new(),
+            token_genesis_feed: TokenGenesisFeed::new(),
+            token_stats: TokenStats::new(),
+            version_bits: VersionBitsTracker::new(),
+            chain_events: ChainEvents::new(&config.chronik_ws),
+            tx_status_checks: Mutex::new((String::new(), HashMap::new())),
+            address_subscriptions: Arc::new(Semaphore::new(MAX_ADDRESS_SUBSCRIPTIONS)),
+            tip_cache: TipCache::new(),
+            double_spend_cache: PerTipCache::new(MAX_CACHED_DOUBLE_SPENDS, DOUBLE_SPENDS_MAX_AGE),
+            counterparties_cache: PerTipCache::new(
+                MAX_CACHED_COUNTERPARTIES,
+                COUNTERPARTIES_MAX_AGE,
+            ),
+            hashrate_cache: PerTipCache::new(MAX_CACHED_CHARTS, CHART_MAX_AGE),
+            block_fullness_cache: PerTipCache::new(MAX_CACHED_CHARTS, CHART_MAX_AGE),
+            tx_count_cache: PerTipCache::new(MAX_CACHED_CHARTS, CHART_MAX_AGE),
+            preview_cache: PerTipCache::new(MAX_CACHED_PREVIEWS, PREVIEW_MAX_AGE),
+            sitemap_cache: PerTipCache::new(MAX_CACHED_SITEMAPS, CHART_MAX_AGE),
+            token_cache: TokenCache::new(),
+            block_cache: BlockCache::new(),
+            page_cache: PageCache::new(),
+            qr_codes: QrCodes::load(&base_dir)?,
+            watchdog: Watchdog::new(config.watchdog.clone())?,
+            run_indexer: config.run_indexer,
+            shutdown: watch::channel(false),
+            network: config.network,
+            satoshi_addr_prefix: config.network.satoshi_addr_prefix(),
+            tokens_addr_prefix: config.network.tokens_addr_prefix(),
+            #[cfg(feature = "graphql")]
+            graphql_schema: crate::graphql::build_schema(),
         })
     }
 
+    pub fn spawn_background_tasks(self: &Arc<Self>) {
+        if self.run_indexer {
+            self.spawn_indexer();
+        }
+        let server = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = server.chain_events.run(&server.chronik) => {}
+                _ = server.until_shutdown() => {}
+            }
+        });
+        let server = Arc::clone(self);
+        tokio::spawn(async move {
+            let events = receiver_stream(server.chain_events.subscribe());
+            tokio::select! {
+                _ = server.tip_cache.run(&server.chronik, events) => {}
+                _ = server.until_shutdown() => {}
+            }
+        });
+        let server = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = server.token_cache.run(&server.chronik) => {}
+                _ = server.until_shutdown() => {}
+            }
+        });
+        let server = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = server.watch_sync() => {}
+                _ = server.until_shutdown() => {}
+            }
+        });
+        let server = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = server.refresh_on_chain_events() => {}
+                _ = server.until_shutdown() => {}
+            }
+        });
+        let server = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = server.warm_hot_pages() => {}
+                _ = server.until_shutdown() => {}
+            }
+        });
+    }
+
+    /// Block feeds that scan the chain themselves; replicas started with `--no-indexer` skip
+    /// these and only serve what comes straight from chronik.
+    fn spawn_indexer(self: &Arc<Self>) {
+        let server = Arc::clone(self);
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = server.burn_tracker.run(&server.chronik) => {}
+                _ = server.until_shutdown() => {}
+            }
+        });
+        let server = Arc::clone(self);
+        tokio::spawn(async move {
+            let consumers: [&dyn BlockConsumer; 5] = [
+                &server.large_tx_feed,
+                &server.op_return_index,
+                &server.token_genesis_feed,
+                &server.token_stats,
+                &server.version_bits,
+            ];
+            tokio::select! {
+                _ = server.block_follower.run(&server.chronik, &consumers) => {}
+                _ = server.until_shutdown() => {}
+            }
+        });
+    }
+
+    /// Lets state derived from the chain catch up as soon as chronik reports a new block.
+    async fn refresh_on_chain_events(&self) {
+        let mut events = receiver_stream(self.chain_events.subscribe());
+        while let Some(event) = events.next().await {
+            if let ChainEvent::BlockDisconnected { block_hash } = &event {
+                if let Ok(block_hash) = Sha256d::from_hex_be(block_hash) {
+                    self.block_cache.invalidate(&block_hash);
+                }
+            }
+            if let ChainEvent::BlockConnected { .. } | ChainEvent::BlockDisconnected { .. } = event
+            {
+                self.block_follower.notify_chain_changed();
+            }
+        }
+    }
+
+    /// Reserves one of the chronik WebSockets `?address=` subscribers may open, if `address`
+    /// is given; fails if all of them are taken.
+    pub fn reserve_address_subscription(
+        &self,
+        address: Option<&str>,
+    ) -> Result<Option<OwnedSemaphorePermit>> {
+        if address.is_none() {
+            return Ok(None);
+        }
+        match Arc::clone(&self.address_subscriptions).try_acquire_owned() {
+            Ok(permit) => Ok(Some(permit)),
+            Err(_) => bail!("Too many address subscriptions, try again later"),
+        }
+    }
+
+    /// Fetches what everyone asks for right after a block arrives (the tip block info behind
+    /// the homepage, the newest blocks and the new block itself), so the first visitors don't
+    /// all hit chronik at once.
+    async fn warm_hot_pages(&self) {
+        let mut events = receiver_stream(self.chain_events.subscribe());
+        while let Some(event) = events.next().await {
+            if let ChainEvent::BlockConnected { block_hash } = event {
+                if let Err(err) = self.warm_for_block(&block_hash).await {
+                    warn!("Failed to warm caches for block {}: {}", block_hash, err);
+                }
+            }
+        }
+    }
+
+    async fn warm_for_block(&self, block_hash: &str) -> Result<()> {
+        // The tip cache may still point at the previous block
+        let blockchain_info = self.tip_cache.refresh(&self.chronik).await?;
+
+        let block_hash = Sha256d::from_hex_be(block_hash)?;
+        let block = self
+            .timed("block_by_hash", self.chronik.block_by_hash(&block_hash))
+            .await?;
+        self.block_cache.insert(&block_hash, &Arc::new(block));
+
+        // All the homepage needs from chronik; rendering it is cheap
+        self.tip_block_info().await?;
+
+        let tip_height = blockchain_info.tip_height;
+        let start_height = (tip_height - LATEST_BLOCKS_PAGE_SIZE + 1).max(0);
+        let blocks = self.fetch_blocks(start_height, tip_height).await?;
+        self.page_cache.insert_latest_blocks(
+            &blockchain_info.tip_hash,
+            start_height,
+            tip_height,
+            blocks,
+        );
+        Ok(())
+    }
+
+    /// Checks that chronik's tip keeps advancing and the block feeds keep up with it, and
+    /// alerts the webhook when that changes.
+    async fn watch_sync(&self) {
+        loop {
+            tokio::time::sleep(self.watchdog.check_interval()).await;
+            // Asks chronik directly, the tip cache would hide it being unreachable
+            let became_healthy = match self
+                .timed("blockchain_info", self.chronik.blockchain_info())
+                .await
+            {
+                Ok(blockchain_info) => self
+                    .watchdog
+                    .record_check(blockchain_info.tip_height, &self.feed_heights()),
+                Err(err) => self.watchdog.record_failed_check(&err.to_string()),
+            };
+            if became_healthy.is_some() {
+                if let Err(err) = self.watchdog.alert().await {
+                    error!("Watchdog failed to send alert: {}", err);
+                }
+            }
+        }
+    }
+
+    /// How far each block feed has scanned, empty if they don't run in this process.
+    fn feed_heights(&self) -> Vec<(&'static str, Option<i32>)> {
+        if !self.run_indexer {
+            return Vec::new();
+        }
+        vec![("Block follower", self.block_follower.scanned_height())]
+    }
+
+    /// Why the explorer isn't ready to serve traffic, empty if it is.
+    pub fn readiness_problems(&self) -> Vec<String> {
+        self.watchdog.problems()
+    }
+
+    /// Awaits a chronik call, recording how long it took under `call` for `/metrics` and the
+    /// admin dashboard.
+    async fn timed<T>(
+        &self,
+        call: &'static str,
+        request: impl Future<Output = Result<T>>,
+    ) -> Result<T> {
+        let started = Instant::now();
+        let result = request.await;
+        self.status
+            .record_upstream_latency(call, started.elapsed(), result.is_err());
+        result
+    }
+
+    /// Block from chronik, or from the cache if it was finalized when requested before or
+    /// was put there by the warmer.
+    async fn block_by_hash(&self, block_hash: &Sha256d) -> Result<Arc<Block>> {
+        if let Some(block) = self.block_cache.get(block_hash) {
+            self.status.record_cache_lookup("blocks", true);
+            return Ok(block);
+        }
+        self.status.record_cache_lookup("blocks", false);
+        let block = self
+            .timed("block_by_hash", self.chronik.block_by_hash(block_hash))
+            .await?;
+        let block = Arc::new(block);
+        let blockchain_info = self.blockchain_info().await?;
+        self.block_cache
+            .insert_if_final(block_hash, &block, blockchain_info.tip_height);
+        Ok(block)
+    }
+
+    /// Current chain tip, from the cache rather than asking chronik every time.
+    async fn blockchain_info(&self) -> Result<BlockchainInfo> {
+        self.tip_cache.get(&self.chronik).await
+    }
+
+    /// Checks `?address=` and `?tx=` of `/events` and `/ws` subscribers.
+    pub fn validate_chain_events_query(
+        &self,
+        address: Option<&str>,
+        txid: Option<&str>,
+    ) -> Result<()> {
+        if let Some(address) = address {
+            parse_address(address, self.network)?;
+        }
+        if let Some(txid) = txid {
+            Sha256d::from_hex_be(txid)?;
+        }
+        Ok(())
+    }
+
+    /// Chain events for `/events` and `/ws` subscribers: block events for everyone, plus the
+    /// mempool and confirmation events of `address`, or [`ChainEvent::TxConfirmed`] once `txid`
+    /// is mined, if given. Ends once the server shuts down, and only then gives `subscription`
+    /// back.
+    pub async fn chain_events(
+        self: &Arc<Self>,
+        address: Option<&str>,
+        txid: Option<&str>,
+        subscription: Option<OwnedSemaphorePermit>,
+    ) -> Result<BoxStream<'static, ChainEvent>> {
+        let events = match (address, txid) {
+            (Some(address), _) => {
+                let address = parse_address(address, self.network)?;
+                let (script_type, script_payload) = cash_addr_to_script_type_payload(&address);
+                script_events(&self.chronik, script_type, &script_payload)
+                    .await?
+                    .scan((), |_, event| {
+                        future::ready(match event {
+                            Ok(event) => Some(event),
+                            Err(err) => {
+                                warn!("Chronik WebSocket disconnected: {}", err);
+                                None
+                            }
+                        })
+                    })
+                    .boxed()
+            }
+            (None, Some(txid)) => self.tx_confirmation_events(txid)?,
+            (None, None) => receiver_stream(self.chain_events.subscribe()).boxed(),
+        };
+        let server = Arc::clone(self);
+        Ok(events
+            .take_until(async move {
+                let _subscription = subscription;
+                server.until_shutdown().await
+            })
+            .boxed())
+    }
+
+    /// Block events, each connected block followed by a [`ChainEvent::TxConfirmed`] if it
+    /// mined `txid`. A disconnected block may have unmined it, so the next one is checked again.
+    fn tx_confirmation_events(
+        self: &Arc<Self>,
+        txid: &str,
+    ) -> Result<BoxStream<'static, ChainEvent>> {
+        let tx_hash = Sha256d::from_hex_be(txid)?;
+        let server = Arc::clone(self);
+        let is_confirmed = Arc::new(AtomicBool::new(false));
+        Ok(receiver_stream(self.chain_events.subscribe())
+            .then(move |event| {
+                let server = Arc::clone(&server);
+                let tx_hash = tx_hash.clone();
+                let is_confirmed = Arc::clone(&is_confirmed);
+                async move {
+                    let mut events = vec![event.clone()];
+                    match event {
+                        ChainEvent::BlockConnected {
+                            block_hash: connected_hash,
+                        } if !is_confirmed.load(Ordering::Relaxed) => {
+                            if let Some(JsonTxStatus {
+                                txid,
+                                block_hash: Some(block_hash),
+                                block_height: Some(block_height),
+                                confirmations,
+                                ..
+                            }) = server.shared_tx_status(connected_hash, tx_hash).await
+                            {
+                                is_confirmed.store(true, Ordering::Relaxed);
+                                events.push(ChainEvent::TxConfirmed {
+                                    txid,
+                                    block_hash,
+                                    block_height,
+                                    confirmations,
+                                });
+                            }
+                        }
+                        ChainEvent::BlockDisconnected { .. } => {
+                            is_confirmed.store(false, Ordering::Relaxed)
+                        }
+                        _ => {}
+                    }
+                    stream::iter(events)
+                }
+            })
+            .flatten()
+            .boxed())
+    }
+
+    /// Status of `tx_hash` once `block_hash` is connected, looked up once for all subscribers
+    /// waiting on the tx. Lookups for earlier blocks are dropped.
+    async fn shared_tx_status(
+        self: &Arc<Self>,
+        block_hash: String,
+        tx_hash: Sha256d,
+    ) -> Option<JsonTxStatus> {
+        let check = {
+            let mut checks = self.tx_status_checks.lock().unwrap();
+            let (checked_block_hash, checks_by_txid) = &mut *checks;
+            if *checked_block_hash != block_hash {
+                *checked_block_hash = block_hash;
+                checks_by_txid.clear();
+            }
+            let server = Arc::clone(self);
+            checks_by_txid
+                .entry(tx_hash.clone())
+                .or_insert_with(|| {
+                    async move { server.data_tx_status(&tx_hash).await.ok().flatten() }
+                        .boxed()
+                        .shared()
+                })
+                .clone()
+        };
+        check.await
+    }
+
+    /// Signals background tasks and long-lived streams to wind down.
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.0.send(true);
+    }
+
+    /// Resolves once [`Server::shutdown`] has been called.
+    pub async fn until_shutdown(&self) {
+        let mut shutdown = self.shutdown.1.clone();
+        while !*shutdown.borrow() {
+            if shutdown.changed().await.is_err() {
+                return;
+            }
+        }
+    }
+
     pub fn router(&self) -> Router {
-        Router::new()
+        let router = Router::new()
             .route("/", get(homepage))
             .route("/tx/:hash", get(tx))
             .route("/blocks", get(blocks))
+            .route("/charts/hashrate", get(hashrate_chart))
             .route("/block/:hash", get(block))
             .route("/block-height/:height", get(block_height))
             .route("/address/:hash", get(address))
             .route("/address-qr/:hash", get(address_qr))
+            .route("/script/:hex", get(script))
+            .route("/preview/:kind/:hash", get(preview_image))
+            .route("/identicon/:token_id", get(identicon))
             .route("/search/:query", get(search))
-            .route("/api/blocks/:start_height/:end_height", get(data_blocks))
-            .route("/api/block/:hash/transactions", get(data_block_txs))
-            .route("/api/address/:hash/transactions", get(data_address_txs))
+            .route(
+                "/feed/large-txs.rss",
+                get(large_txs_rss)
+                    .route_layer(middleware::from_fn(server_middleware::require_indexer)),
+            )
+            .route("/sitemap.xml", get(sitemap_index))
+            .route("/sitemap/blocks/:chunk", get(sitemap_blocks))
+            .route("/admin", get(admin))
+            .route("/ready", get(ready))
+            .route("/metrics", get(metrics))
+            .route("/events", get(events))
+            .route("/ws", get(ws))
+            .nest(
+                &format!("/api/v{}", API_VERSION),
+                self.api_router()
+                    .layer(middleware::from_fn(server_middleware::api_version)),
+            )
+            .nest(
+                "/api",
+                self.api_router()
+                    .layer(middleware::from_fn(server_middleware::legacy_api))
+                    .layer(middleware::from_fn(server_middleware::api_version)),
+            )
             .nest("/code", serve_files(&self.base_dir.join("code")))
             .nest("/assets", serve_files(&self.base_dir.join("assets")))
             .nest(
                 "/favicon.ico",
                 serve_files(&self.base_dir.join("assets").join("favicon.png")),
+            );
+
+        #[cfg(feature = "graphql")]
+        let router = router.route(
+            "/graphql",
+            get(crate::server_http::graphql_playground).post(crate::server_http::graphql),
+        );
+
+        router
+            .layer(middleware::from_fn(server_middleware::language))
+            .layer(middleware::from_fn(server_middleware::request_id))
+    }
+
+    fn api_router(&self) -> Router {
+        let router = Router::new()
+            .route("/blocks/:start_height/:end_height", get(data_blocks))
+            .route("/block/:hash/transactions", get(data_block_txs))
+            .route("/block-height/:height", get(data_block_by_height))
+            .route("/headers/:start_height/:count", get(data_headers))
+            .route("/block/:hash/tx/:index", get(data_block_tx))
+            .route("/tx/:hash/merkle-proof", get(data_merkle_proof))
+            .route("/tx/:hash/status", get(data_tx_status))
+            .route("/address/:hash/transactions", get(data_address_txs))
+            .route("/address/:hash/balances", get(data_address_balances))
+            .route("/address/:hash/utxos", get(data_address_utxos))
+            .route(
+                "/address/:hash/counterparties",
+                get(data_address_counterparties),
+            )
+            .route("/address/convert/:hash", get(data_address_forms))
+            .route("/validate/:hash", get(data_validate_address))
+            .route("/script/:hex/transactions", get(data_script_txs))
+            .route("/script/:hex/balances", get(data_script_balances))
+            .route("/script/:hex/utxos", get(data_script_utxos))
+            .route("/openapi.json", get(openapi_spec))
+            .route("/docs", get(api_docs))
+            .route("/stats/hashrate", get(data_hashrate))
+            .route("/stats/countdowns", get(data_countdowns))
+            .route("/charts/tx-count", get(data_tx_count_chart))
+            .route("/charts/block-fullness", get(data_block_fullness))
+            .route("/stats/block-intervals", get(data_block_intervals))
+            .route("/verify-message", post(verify_message))
+            .merge(
+                Router::new()
+                    .route("/stats/supply", get(data_supply))
+                    .route("/stats/burned", get(data_burned))
+                    .route("/stats/signaling", get(data_signaling))
+                    .route("/feed/large-txs", get(data_large_txs))
+                    .route("/tokens/recent", get(data_recent_tokens))
+                    .route("/token/:hex/stats", get(data_token_stats))
+                    .route("/token/:hex/top-transfers", get(data_token_top_transfers))
+                    .route("/search/opreturn", get(data_search_op_return))
+                    .route_layer(middleware::from_fn(server_middleware::require_indexer)),
             )
+            .merge(
+                Router::new()
+                    .route("/broadcast-tx", post(broadcast_tx))
+                    .route(
+                        "/export/address/:hash",
+                        get(export_address_txs).layer(CompressionLayer::new()),
+                    )
+                    .route_layer(middleware::from_fn(server_middleware::require_api_key)),
+            );
+
+        match &self.cors {
+            Some(cors) => router.layer(cors.clone()),
+            None => router,
+        }
     }
 }
 
 impl Server {
-    pub async fn homepage(&self) -> Result<String> {
-        let homepage = HomepageTemplate {};
-        Ok(homepage.render().unwrap())
+    pub async fn homepage(&self, locale: UserLocale) -> Result<String> {
+        let blockchain_info = match self.blockchain_info().await {
+            Ok(blockchain_info) => blockchain_info,
+            Err(err) => return Ok(self.render_homepage_without_tip(err)),
+        };
+        if let Some(homepage) = self.page_cache.homepage(&blockchain_info.tip_hash, locale) {
+            self.status.record_cache_lookup("pages", true);
+            return Ok(homepage);
+        }
+        self.status.record_cache_lookup("pages", false);
+        let tip = match self.tip_block_info().await {
+            Ok(tip) => tip,
+            Err(err) => return Ok(self.render_homepage_without_tip(err)),
+        };
+        let homepage = self.render_homepage(Some(&tip));
+        self.page_cache
+            .insert_homepage(&blockchain_info.tip_hash, locale, homepage.clone());
+        Ok(homepage)
+    }
+
+    /// Only the countdowns need chronik, so a chronik error leaves them out rather than failing
+    /// the whole page. Such a page isn't cached, so the next request retries.
+    fn render_homepage_without_tip(&self, err: impl std::fmt::Display) -> String {
+        warn!("Rendering the homepage without countdowns: {}", err);
+        self.render_homepage(None)
+    }
+
+    /// Renders the homepage in the language of the current catalog.
+    fn render_homepage(&self, tip: Option<&BlockInfo>) -> String {
+        const NUM_NEW_TOKENS: usize = 10;
+
+        let homepage = HomepageTemplate {
+            countdowns: tip.map(|tip| countdowns(tip.height, tip.timestamp)),
+            last_block_timestamp: tip.map(|tip| tip.timestamp),
+            new_tokens: self.token_genesis_feed.tokens(NUM_NEW_TOKENS, |new_token| {
+                !self.token_filter.is_spam(&new_token.token.token_id)
+            }),
+        };
+        homepage.render().unwrap()
     }
 
     pub async fn blocks(&self) -> Result<String> {
-        let blockchain_info = self.chronik.blockchain_info().await?;
+        let tip = self.tip_block_info().await?;
 
         let blocks_template = BlocksTemplate {
-            last_block_height: blockchain_info.tip_height as u32,
+            last_block_height: tip.height as u32,
+            last_block_timestamp: tip.timestamp,
         };
 
         Ok(blocks_template.render().unwrap())
     }
+
+    pub fn status(&self) -> &StatusRegistry {
+        &self.status
+    }
+
+    pub fn translations(&self) -> &Translations {
+        &self.translations
+    }
+
+    pub fn api_keys(&self) -> &ApiKeys {
+        &self.api_keys
+    }
+
+    /// Whether the block feeds run in this process, see [`Config::run_indexer`].
+    pub fn runs_indexer(&self) -> bool {
+        self.run_indexer
+    }
+
+    pub fn is_admin_enabled(&self) -> bool {
+        self.admin_password.is_some()
+    }
+
+    pub fn is_admin_authorized(&self, headers: &HeaderMap) -> bool {
+        match (&self.admin_password, basic_auth_password(headers)) {
+            (Some(admin_password), Some(password)) => secrets_match(&password, admin_password),
+            _ => false,
+        }
+    }
+
+    pub async fn admin(&self) -> Result<String> {
+        let started = Instant::now();
+        let blockchain_info = self
+            .timed("blockchain_info", self.chronik.blockchain_info())
+            .await;
+        let chronik_latency_ms = started.elapsed().as_millis();
+        let (chronik_tip_height, chronik_error) = match blockchain_info {
+            Ok(blockchain_info) => (Some(blockchain_info.tip_height), None),
+            Err(err) => (None, Some(err.to_string())),
+        };
+
+        let admin_template = AdminTemplate {
+            uptime: self.status.uptime(),
+            num_requests: self.status.num_requests(),
+            num_errors: self.status.num_errors(),
+            chronik_tip_height,
+            chronik_error,
+            chronik_latency_ms,
+            cache_stats: self.status.cache_stats(),
+            route_latencies: self.status.route_latencies(),
+            upstream_latencies: self.status.upstream_latencies(),
+            recent_errors: self.status.recent_errors(),
+        };
+
+        Ok(admin_template.render().unwrap())
+    }
+
+    pub async fn hashrate_chart(&self) -> Result<String> {
+        let hashrate_template = HashrateTemplate {};
+        Ok(hashrate_template.render().unwrap())
+    }
+
+    pub async fn api_docs(&self) -> Result<String> {
+        let api_docs = ApiDocsTemplate {};
+        Ok(api_docs.render().unwrap())
+    }
+
+    pub async fn sitemap_index(&self) -> Result<String> {
+        let blockchain_info = self.blockchain_info().await?;
+        let num_block_chunks = blockchain_info.tip_height / SITEMAP_BLOCKS_PER_CHUNK + 1;
+
+        let sitemap_index_template = SitemapIndexTemplate {
+            public_url: &self.public_url,
+            num_block_chunks,
+        };
+        Ok(sitemap_index_template.render().unwrap())
+    }
+
+    /// Sitemap of the blocks of `chunk`, `None` if the chain doesn't reach that far.
+    pub async fn sitemap_blocks(&self, chunk: i32) -> Result<Option<Arc<String>>> {
+        let blockchain_info = self.blockchain_info().await?;
+        if chunk < 0 || chunk > blockchain_info.tip_height / SITEMAP_BLOCKS_PER_CHUNK {
+            return Ok(None);
+        }
+        if let Some(sitemap) = self.sitemap_cache.get(&blockchain_info.tip_hash, &chunk) {
+            return Ok(Some(sitemap));
+        }
+        let start_height = chunk * SITEMAP_BLOCKS_PER_CHUNK;
+        let end_height =
+            (start_height + SITEMAP_BLOCKS_PER_CHUNK - 1).min(blockchain_info.tip_height);
+        let blocks = self.block_infos(start_height, end_height).await?;
+
+        let sitemap_blocks_template = SitemapBlocksTemplate {
+            public_url: &self.public_url,
+            blocks: blocks
+                .iter()
+                .map(|block| block_info_to_json(block, blockchain_info.tip_height))
+                .collect(),
+        };
+        let sitemap = sitemap_blocks_template.render().unwrap();
+        Ok(Some(self.sitemap_cache.insert(
+            &blockchain_info.tip_hash,
+            chunk,
+            sitemap,
+        )))
+    }
+
+    pub async fn large_txs_rss(&self, query: HashMap<String, String>) -> Result<String> {
+        let large_txs = self.data_large_txs(query).await?;
+        let rss_template = LargeTxsRssTemplate {
+            public_url: &self.public_url,
+            txs: large_txs.data,
+        };
+        Ok(rss_template.render().unwrap())
+    }
 }
 
 impl Server {
+    /// Checked up front, since errors once an export is streaming can only cut it short.
+    pub fn validate_history_export(&self, address: &str, cursor: Option<&str>) -> Result<()> {
+        parse_address(address, self.network)?;
+        if let Some(cursor) = cursor {
+            HistoryCursor::decode(cursor)?;
+        }
+        Ok(())
+    }
+
+    /// Checks `?cursor=` decodes and `?page=` and `?take=` are in range. `?token_id=` filters over offset pages only, so it can't be
+    /// combined with cursors or the NDJSON stream.
+    pub fn validate_txs_query(&self, query: &HashMap<String, String>) -> Result<()> {
+        if let Some(cursor) = query.get("cursor") {
+            HistoryCursor::decode(cursor)?;
+        }
+        parse_txs_paging(query)?;
+        let token_id = match query.get("token_id") {
+            Some(token_id) => token_id,
+            None => return Ok(()),
+        };
+        Sha256d::from_hex_be(token_id)?;
+        if query.contains_key("cursor") || query.get("format").map(|s| s.as_str()) == Some("ndjson")
+        {
+            bail!("token_id can't be combined with cursor or format=ndjson");
+        }
+        Ok(())
+    }
+
+    pub fn validate_block_range(&self, start_height: i32, end_height: i32) -> Result<()> {
+        if start_height < 0 || end_height < 0 {
+            bail!("Block heights must not be negative");
+        }
+        if start_height > end_height {
+            bail!(
+                "start_height {} is above end_height {}",
+                start_height,
+                end_height
+            );
+        }
+        Ok(())
+    }
+
+    pub fn validate_headers_range(&self, start_height: i32, count: i32) -> Result<()> {
+        if start_height < 0 {
+            bail!("Block heights must not be negative");
+        }
+        if !(1..=MAX_HEADERS).contains(&count) {
+            bail!("count must be between 1 and {}", MAX_HEADERS);
+        }
+        Ok(())
+    }
+
+    /// Raw headers of `count` blocks from `start_height` upwards, stopping at the tip. The
+    /// range is checked by callers with [`Server::validate_headers_range`].
+    pub async fn data_headers(&self, start_height: i32, count: i32) -> Result<JsonHeaders> {
+        let blockchain_info = self.blockchain_info().await?;
+        let end_height = start_height
+            .saturating_add(count - 1)
+            .min(blockchain_info.tip_height);
+        let blocks = future::try_join_all(
+            (start_height..=end_height)
+                .map(|height| self.timed("block_by_height", self.chronik.block_by_height(height))),
+        )
+        .await?;
+
+        let mut headers = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            if block.raw_header.len() != 80 {
+                bail!("Block header isn't 80 bytes");
+            }
+            headers.push(hex::encode(&block.raw_header));
+        }
+
+        Ok(JsonHeaders {
+            start_height,
+            headers,
+        })
+    }
+
+    /// Blocks from `end_height` downwards; ranges spanning more than `MAX_BLOCKS_PER_PAGE`
+    /// blocks are cut off at the bottom, with the `next` link pointing at the rest. The range
+    /// is checked by callers with [`Server::validate_block_range`].
     pub async fn data_blocks(
         &self,
         start_height: i32,
         end_height: i32,
     ) -> Result<JsonBlocksResponse> {
-        let blocks = self.chronik.blocks(start_height, end_height).await?;
+        let blockchain_info = self.blockchain_info().await?;
+        if end_height < blockchain_info.tip_height {
+            return self.fetch_blocks(start_height, end_height).await;
+        }
+        let tip_hash = &blockchain_info.tip_hash;
+        if let Some(blocks) = self
+            .page_cache
+            .latest_blocks(tip_hash, start_height, end_height)
+        {
+            self.status.record_cache_lookup("pages", true);
+            return Ok(blocks);
+        }
+        self.status.record_cache_lookup("pages", false);
+        let blocks = self.fetch_blocks(start_height, end_height).await?;
+        self.page_cache
+            .insert_latest_blocks(tip_hash, start_height, end_height, blocks.clone());
+        Ok(blocks)
+    }
+
+    async fn fetch_blocks(&self, start_height: i32, end_height: i32) -> Result<JsonBlocksResponse> {
+        let page_size = (end_height - start_height + 1).min(MAX_BLOCKS_PER_PAGE);
+        let start_height = end_height - page_size + 1;
+        let blockchain_info = self.blockchain_info().await?;
+        let tip_height = blockchain_info.tip_height;
+        let blocks = self
+            .timed("blocks", self.chronik.blocks(start_height, end_height))
+            .await?;
 
         let mut json_blocks = Vec::with_capacity(blocks.len());
         for block in blocks.into_iter().rev() {
-            json_blocks.push(JsonBlock {
-                hash: to_be_hex(&block.hash),
-                height: block.height,
-                timestamp: block.timestamp,
-                difficulty: calculate_block_difficulty(block.n_bits),
-                size: block.block_size,
-                num_txs: block.num_txs,
-            });
+            json_blocks.push(block_info_to_json(&block, tip_height));
         }
 
-        Ok(JsonBlocksResponse { data: json_blocks })
+        // Pages count down from the tip, like the blocks page
+        let blocks_path = |end_height: i32| {
+            let start_height = (end_height - page_size + 1).max(0);
+            format!(
+                "/api/v{}/blocks/{}/{}",
+                API_VERSION, start_height, end_height
+            )
+        };
+        let pagination = JsonPagination {
+            page: ((tip_height - end_height).max(0) / page_size) as usize,
+            page_size: page_size as usize,
+            total: Some((tip_height + 1) as usize),
+            num_pages: ((tip_height + page_size) / page_size) as usize,
+            next: (start_height > 0).then(|| blocks_path(start_height - 1)),
+            prev: (end_height < tip_height)
+                .then(|| blocks_path((end_height + page_size).min(tip_height))),
+        };
+
+        Ok(JsonBlocksResponse {
+            data: json_blocks,
+            pagination: Some(pagination),
+        })
+    }
+
+    pub async fn data_block(&self, block_hex: &str) -> Result<JsonBlock> {
+        let block_hash = Sha256d::from_hex_be(block_hex)?;
+        let block = self.block_by_hash(&block_hash).await?;
+        self.block_to_json(&block).await
+    }
+
+    /// The block at `height` in the main chain; `None` if there is none (yet).
+    pub async fn data_block_by_height(&self, height: i32) -> Result<Option<JsonBlock>> {
+        let blockchain_info = self.blockchain_info().await?;
+        if !(0..=blockchain_info.tip_height).contains(&height) {
+            return Ok(None);
+        }
+        // The chain may have been reorged to below `height` since
+        let block = not_found_to_none(
+            self.timed("block_by_height", self.chronik.block_by_height(height))
+                .await,
+        )?;
+        match block {
+            Some(block) => Ok(Some(self.block_to_json(&block).await?)),
+            None => Ok(None),
+        }
+    }
+
+    async fn block_to_json(&self, block: &Block) -> Result<JsonBlock> {
+        let block_info = block
+            .block_info
+            .as_ref()
+            .ok_or_else(|| eyre!("Block has no info"))?;
+        let blockchain_info = self.blockchain_info().await?;
+
+        let mut json_block = block_info_to_json(block_info, blockchain_info.tip_height);
+        json_block.coinbase_split = block
+            .txs
+            .first()
+            .map(|coinbase| coinbase_split_to_json(coinbase, self.network));
+        Ok(json_block)
+    }
+
+    /// Parses `?page=` and `?take=`, returning them in that order.
+    pub fn validate_block_txs_query(
+        &self,
+        query: &HashMap<String, String>,
+    ) -> Result<(usize, usize)> {
+        let page: usize = query
+            .get("page")
+            .map(|s| s.as_str())
+            .unwrap_or("0")
+            .parse()?;
+        let take: usize = query
+            .get("take")
+            .map(|s| s.as_str())
+            .unwrap_or("200")
+            .parse()?;
+        if take == 0 || take > MAX_BLOCK_TXS_PAGE_SIZE {
+            bail!("take must be between 1 and {}", MAX_BLOCK_TXS_PAGE_SIZE);
+        }
+        Ok((page, take))
+    }
+
+    pub async fn data_block_txs(
+        &self,
+        block_hex: &str,
+        query: HashMap<String, String>,
+    ) -> Result<JsonTxsResponse> {
+        let (page, take) = self.validate_block_txs_query(&query)?;
+        let block_hash = Sha256d::from_hex_be(block_hex)?;
+        // Chronik returns the whole block; only the requested page gets its tokens looked up
+        // and converted
+        let block = self.block_by_hash(&block_hash).await?;
+        let total = block.txs.len();
+        let num_pages = (total + take - 1) / take;
+        let first_position = page.saturating_mul(take);
+        let page_block = Block {
+            block_info: block.block_info.clone(),
+            txs: block
+                .txs
+                .iter()
+                .skip(first_position)
+                .take(take)
+                .cloned()
+                .collect(),
+            ..Default::default()
+        };
+        let json_txs = self.block_txs_json(page_block, first_position).await?;
+
+        let page_path = |page: usize| {
+            format!(
+                "/api/v{}/block/{}/transactions?page={}&take={}",
+                API_VERSION, block_hex, page, take
+            )
+        };
+        let pagination = JsonPagination {
+            page,
+            page_size: take,
+            total: Some(total),
+            num_pages,
+            next: (page + 1 < num_pages).then(|| page_path(page + 1)),
+            prev: (page > 0).then(|| page_path(page - 1)),
+        };
+
+        Ok(JsonTxsResponse {
+            data: json_txs,
+            next_cursor: None,
+            pagination: Some(pagination),
+            display_unit: AmountUnit::default().name(),
+        })
     }
 
-    pub async fn data_block_txs(&self, block_hex: &str) -> Result<JsonTxsResponse> {
+    /// The txs of a block as NDJSON, converted in chunks so only one chunk's JSON is held in
+    /// memory at a time.
+    pub async fn block_txs_ndjson(
+        self: Arc<Self>,
+        block_hex: &str,
+    ) -> Result<impl Stream<Item = Result<String>>> {
         let block_hash = Sha256d::from_hex_be(block_hex)?;
-        let block = self.chronik.block_by_hash(&block_hash).await?;
+        let block = self.block_by_hash(&block_hash).await?;
+        let first_positions = (0..block.txs.len()).step_by(NDJSON_CHUNK_SIZE);
+
+        Ok(stream::iter(first_positions).then(move |first_position| {
+            let server = Arc::clone(&self);
+            let chunk = Block {
+                block_info: block.block_info.clone(),
+                txs: block.txs[first_position..]
+                    .iter()
+                    .take(NDJSON_CHUNK_SIZE)
+                    .cloned()
+                    .collect(),
+                ..Default::default()
+            };
+            async move { to_ndjson(&server.block_txs_json(chunk, first_position).await?) }
+        }))
+    }
+
+    /// The tx at `index` within the block, 0 being the coinbase, or `None` if there's no such
+    /// block or it has fewer txs.
+    pub async fn data_block_tx(
+        &self,
+        block_hash: &Sha256d,
+        index: usize,
+    ) -> Result<Option<JsonTx>> {
+        let block = match not_found_to_none(self.block_by_hash(block_hash).await)? {
+            Some(block) => block,
+            None => return Ok(None),
+        };
+        let tx = match block.txs.get(index) {
+            Some(tx) => tx.clone(),
+            None => return Ok(None),
+        };
+        self.tx_positions.insert(block_hash.as_slice(), &block.txs);
+        let tx_block = Block {
+            block_info: block.block_info.clone(),
+            txs: vec![tx],
+            ..Default::default()
+        };
+        let mut json_txs = self.block_txs_json(tx_block, index).await?;
+        Ok(Some(json_txs.remove(0)))
+    }
+
+    /// Index of a confirmed tx within its block; the block is only fetched if its positions
+    /// aren't cached yet.
+    async fn tx_position_in_block(&self, tx: &Tx) -> Result<Option<u32>> {
+        let block_meta = match &tx.block {
+            Some(block_meta) => block_meta,
+            None => return Ok(None),
+        };
+        let positions = match self.tx_positions.get(&block_meta.hash) {
+            Some(positions) => positions,
+            None => {
+                let block_hash = Sha256d::from_slice(&block_meta.hash)?;
+                let block = self.block_by_hash(&block_hash).await?;
+                self.tx_positions.insert(&block_meta.hash, &block.txs)
+            }
+        };
+        Ok(positions.get(&tx.txid).copied())
+    }
 
+    async fn block_txs_json(&self, block: Block, first_position: usize) -> Result<Vec<JsonTx>> {
         let token_ids = block
             .txs
             .iter()
@@ -125,101 +1423,973 @@ impl Server {
             })
             .collect::<HashSet<_>>();
 
-        let tokens_by_hex = self.batch_get_chronik_tokens(token_ids).await?;
-        let json_txs = block_txs_to_json(block, &tokens_by_hex)?;
+        let tokens_by_hex = self.batch_get_chronik_tokens(token_ids).await?;
+        block_txs_to_json(block, &tokens_by_hex, first_position)
+    }
+
+    /// `?limit=` of an address history NDJSON stream, the number of newest txs to stream.
+    pub fn parse_ndjson_limit(&self, query: &HashMap<String, String>) -> Result<usize> {
+        let limit: usize = match query.get("limit") {
+            Some(limit) => limit.parse()?,
+            None => MAX_NDJSON_TXS,
+        };
+        if !(1..=MAX_NDJSON_TXS).contains(&limit) {
+            bail!("limit must be between 1 and {}", MAX_NDJSON_TXS);
+        }
+        Ok(limit)
+    }
+
+    /// One page of an address history export: up to [`MAX_NDJSON_TXS`] txs after `cursor`,
+    /// then the trailer with the cursor of the next page.
+    pub fn address_txs_export(
+        self: Arc<Self>,
+        address: String,
+        cursor: Option<String>,
+    ) -> impl Stream<Item = Result<String>> {
+        self.address_txs_ndjson(address, cursor, MAX_NDJSON_TXS, true)
+    }
+
+    /// Up to `max_txs` txs of the history of an address as NDJSON, fetched page by page
+    /// following the cursor, or from after `cursor` if given. With `with_trailer`, the last
+    /// line is a [`JsonNdjsonTrailer`] with the cursor to continue from.
+    ///
+    /// `max_txs` has to be a multiple of the chunk size when paging with the trailer, so the
+    /// cursor never skips the rest of a truncated chunk.
+    pub fn address_txs_ndjson(
+        self: Arc<Self>,
+        address: String,
+        cursor: Option<String>,
+        max_txs: usize,
+        with_trailer: bool,
+    ) -> impl Stream<Item = Result<String>> {
+        // None once the last page is out, Some(None) before the first page; along with the
+        // number of txs still to stream
+        let first_page: (Option<Option<String>>, usize) = (Some(cursor), max_txs);
+        stream::try_unfold(first_page, move |(cursor, num_remaining)| {
+            let server = Arc::clone(&self);
+            let address = address.clone();
+            async move {
+                let cursor = match cursor {
+                    Some(cursor) if num_remaining > 0 => cursor,
+                    _ => return Ok(None),
+                };
+                let mut query =
+                    HashMap::from([("take".to_string(), NDJSON_CHUNK_SIZE.to_string())]);
+                if let Some(cursor) = cursor {
+                    query.insert("cursor".to_string(), cursor);
+                }
+                let mut txs = server.data_address_txs(&address, query).await?;
+                txs.data.truncate(num_remaining);
+                let num_remaining = num_remaining - txs.data.len();
+                let mut lines = to_ndjson(&txs.data)?;
+                if with_trailer && (num_remaining == 0 || txs.next_cursor.is_none()) {
+                    let trailer = JsonNdjsonTrailer {
+                        trailer: JsonNdjsonTrailerInfo {
+                            next_cursor: txs.next_cursor.clone(),
+                        },
+                    };
+                    lines.push_str(&serde_json::to_string(&trailer)?);
+                    lines.push('\n');
+                }
+                Ok(Some((lines, (txs.next_cursor.map(Some), num_remaining))))
+            }
+        })
+    }
+
+    pub async fn data_address_txs(
+        &self,
+        address: &str,
+        query: HashMap<String, String>,
+    ) -> Result<JsonTxsResponse> {
+        let address = parse_address(address, self.network)?;
+        let (script_type, script_payload) = cash_addr_to_script_type_payload(&address);
+        let output_script = address.to_script().bytecode().to_vec();
+        let path = format!(
+            "/api/v{}/address/{}/transactions",
+            API_VERSION,
+            address.as_str()
+        );
+
+        self.script_txs(script_type, &script_payload, &output_script, &path, query)
+            .await
+    }
+
+    pub async fn data_script_txs(
+        &self,
+        script_hex: &str,
+        query: HashMap<String, String>,
+    ) -> Result<JsonTxsResponse> {
+        let output_script = hex::decode(script_hex)?;
+        let (script_type, script_payload) = script_to_script_type_payload(&output_script);
+        let path = format!(
+            "/api/v{}/script/{}/transactions",
+            API_VERSION,
+            hex::encode(&output_script)
+        );
+
+        self.script_txs(script_type, &script_payload, &output_script, &path, query)
+            .await
+    }
+
+    async fn script_txs(
+        &self,
+        script_type: ScriptType,
+        script_payload: &[u8],
+        output_script: &[u8],
+        path: &str,
+        query: HashMap<String, String>,
+    ) -> Result<JsonTxsResponse> {
+        let (page, take) = parse_txs_paging(&query)?;
+        if let Some(token_id_hex) = query.get("token_id") {
+            let token_id_hex = token_id_hex.to_lowercase();
+            let token_id = Sha256d::from_hex_be(&token_id_hex)?;
+            let ((txs, has_next), tokens) = tokio::try_join!(
+                self.token_history_page(script_type, script_payload, &token_id_hex, page, take),
+                self.batch_get_chronik_tokens(HashSet::from([token_id])),
+            )?;
+            let json_tokens = tokens_to_json(&tokens)?;
+            let page_path = |page: usize| {
+                format!(
+                    "{}?token_id={}&page={}&take={}",
+                    path, token_id_hex, page, take
+                )
+            };
+            return Ok(JsonTxsResponse {
+                data: txs
+                    .iter()
+                    .map(|tx| tx_to_json(tx, &json_tokens, Some(output_script)))
+                    .collect(),
+                next_cursor: None,
+                pagination: Some(JsonPagination {
+                    page,
+                    page_size: take,
+                    total: None,
+                    num_pages: if has_next { page + 2 } else { page + 1 },
+                    next: has_next.then(|| page_path(page + 1)),
+                    prev: (page > 0).then(|| page_path(page - 1)),
+                }),
+                display_unit: AmountUnit::default().name(),
+            });
+        }
+        let (address_tx_history, next_cursor, page) = match query.get("cursor") {
+            Some(cursor) => {
+                let cursor = HistoryCursor::decode(cursor)?;
+                let (history, next_cursor) = self
+                    .history_after_cursor(script_type, script_payload, &cursor, take)
+                    .await?;
+                (history, next_cursor, cursor.page)
+            }
+            None => {
+                let script_endpoint = self.chronik.script(script_type, script_payload);
+                let history = self
+                    .timed(
+                        "script_history",
+                        script_endpoint.history_with_page_size(page, take),
+                    )
+                    .await?;
+                let next_cursor = match history.txs.last() {
+                    Some(tx) if history.txs.len() == take => Some(HistoryCursor::from_tx(tx, page)),
+                    _ => None,
+                };
+                (history, next_cursor, page)
+            }
+        };
+        let num_pages = address_tx_history.num_pages as usize;
+        let next_cursor = next_cursor.map(|cursor| cursor.encode());
+
+        let token_ids = address_tx_history
+            .txs
+            .iter()
+            .filter_map(|tx| {
+                let slp_tx_data = tx.slp_tx_data.as_ref()?;
+                let slp_meta = slp_tx_data.slp_meta.as_ref()?;
+                if slp_meta.token_type() == SlpTokenType::UnknownTokenType {
+                    return None;
+                }
+                Some(Sha256d::from_slice_be_or_null(&slp_meta.token_id))
+            })
+            .collect();
+
+        let tokens = self.batch_get_chronik_tokens(token_ids).await?;
+        let json_tokens = tokens_to_json(&tokens)?;
+        let json_txs = tx_history_to_json(output_script, address_tx_history, &json_tokens)?;
+
+        // Following the cursor is stable against new txs shifting the offset pages; there's
+        // no cursor pointing backwards, so prev is an offset page
+        let pagination = JsonPagination {
+            page,
+            page_size: take,
+            total: None,
+            num_pages,
+            next: next_cursor
+                .as_ref()
+                .map(|cursor| format!("{}?cursor={}&take={}", path, cursor, take)),
+            prev: (page > 0).then(|| format!("{}?page={}&take={}", path, page - 1, take)),
+        };
+
+        Ok(JsonTxsResponse {
+            data: json_txs,
+            next_cursor,
+            pagination: Some(pagination),
+            display_unit: AmountUnit::default().name(),
+        })
+    }
+
+    pub async fn data_tx(&self, tx_hex: &str) -> Result<JsonTx> {
+        let tx_hash = Sha256d::from_hex_be(tx_hex)?;
+        let tx = self.timed("tx", self.chronik.tx(&tx_hash)).await?;
+
+        let token_ids = tx
+            .slp_tx_data
+            .as_ref()
+            .and_then(|slp_tx_data| slp_tx_data.slp_meta.as_ref())
+            .filter(|slp_meta| slp_meta.token_type() != SlpTokenType::UnknownTokenType)
+            .map(|slp_meta| Sha256d::from_slice_be_or_null(&slp_meta.token_id))
+            .into_iter()
+            .collect();
+
+        let (tokens, position_in_block, double_spent_inputs) = tokio::try_join!(
+            self.batch_get_chronik_tokens(token_ids),
+            self.tx_position_in_block(&tx),
+            self.double_spent_inputs(&tx),
+        )?;
+        let json_tokens = tokens_to_json(&tokens)?;
+
+        Ok(JsonTx {
+            position_in_block,
+            double_spent_inputs,
+            ..tx_to_json(&tx, &json_tokens, None)
+        })
+    }
+
+    /// Whether `tx_hash` is mined, `None` if chronik doesn't know it.
+    pub async fn data_tx_status(&self, tx_hash: &Sha256d) -> Result<Option<JsonTxStatus>> {
+        let (tx, blockchain_info) = tokio::try_join!(
+            async { not_found_to_none(self.timed("tx", self.chronik.tx(tx_hash)).await) },
+            self.blockchain_info(),
+        )?;
+        let tx = match tx {
+            Some(tx) => tx,
+            None => return Ok(None),
+        };
+        let status = match tx.block {
+            Some(block_meta) => JsonTxStatus {
+                txid: to_be_hex(&tx.txid),
+                is_confirmed: true,
+                block_hash: Some(to_be_hex(&block_meta.hash)),
+                block_height: Some(block_meta.height),
+                // The tip cache may not have caught up with the block yet
+                confirmations: (blockchain_info.tip_height - block_meta.height + 1).max(1),
+            },
+            None => JsonTxStatus {
+                txid: to_be_hex(&tx.txid),
+                is_confirmed: false,
+                block_hash: None,
+                block_height: None,
+                confirmations: 0,
+            },
+        };
+        Ok(Some(status))
+    }
+
+    pub async fn data_merkle_proof(&self, tx_hex: &str) -> Result<JsonMerkleProof> {
+        let tx_hash = Sha256d::from_hex_be(tx_hex)?;
+        let tx = self.timed("tx", self.chronik.tx(&tx_hash)).await?;
+        let block_meta = tx.block.ok_or_else(|| eyre!("Tx is not mined yet"))?;
+        let block_hash = Sha256d::from_slice(&block_meta.hash)?;
+        let block = self.block_by_hash(&block_hash).await?;
+
+        let txids = block
+            .txs
+            .iter()
+            .map(|block_tx| block_tx.txid.clone())
+            .collect::<Vec<_>>();
+        let position = txids
+            .iter()
+            .position(|txid| txid == &tx.txid)
+            .ok_or_else(|| eyre!("Tx not found in its block"))?;
+        let (branch, merkle_root) = merkle_branch(&txids, position);
+        if block.raw_header.len() != 80 || block.raw_header[36..68] != merkle_root[..] {
+            bail!("Merkle root doesn't match the block header");
+        }
+
+        Ok(JsonMerkleProof {
+            tx_hash: tx_hex.to_string(),
+            block_hash: to_be_hex(&block_meta.hash),
+            block_height: block_meta.height,
+            block_header: hex::encode(&block.raw_header),
+            merkle_root: to_be_hex(&merkle_root),
+            position_in_block: position as u32,
+            branch: branch.iter().map(|hash| to_be_hex(hash)).collect(),
+        })
+    }
+
+    pub async fn data_token(&self, token_hex: &str) -> Result<Option<JsonToken>> {
+        let token_id = Sha256d::from_hex_be(token_hex)?;
+        let tokens = self
+            .batch_get_chronik_tokens([token_id].into_iter().collect())
+            .await?;
+        let json_tokens = tokens_to_json(&tokens)?;
+
+        Ok(json_tokens.into_values().next())
+    }
+
+    pub async fn data_supply(&self) -> Result<JsonSupply> {
+        let blockchain_info = self.blockchain_info().await?;
+        let issued_sats = total_issued_sats(blockchain_info.tip_height);
+        let burn_totals = self.burn_tracker.totals();
+        let burned_sats = self.burn_address_sats().await? + burn_totals.op_return_burned_sats;
+
+        Ok(JsonSupply {
+            block_height: blockchain_info.tip_height,
+            issued_sats,
+            burned_sats,
+            circulating_sats: issued_sats - burned_sats,
+            is_complete: burn_totals.is_complete,
+        })
+    }
+
+    pub async fn data_burned(&self) -> Result<JsonBurned> {
+        let burn_totals = self.burn_tracker.totals();
+        let burn_address_sats = self.burn_address_sats().await?;
+
+        Ok(JsonBurned {
+            scanned_height: burn_totals.scanned_height,
+            is_complete: burn_totals.is_complete,
+            op_return_burned_sats: burn_totals.op_return_burned_sats,
+            burn_address_sats,
+            total_burned_sats: burn_totals.op_return_burned_sats + burn_address_sats,
+        })
+    }
+
+    /// Parses `?window=` and `?points=`, returning them in that order. An estimate needs at
+    /// least one block interval, so windows have at least 2 blocks.
+    pub fn validate_hashrate_query(&self, query: &HashMap<String, String>) -> Result<(i32, i32)> {
+        parse_chart_windows(query, 2)
+    }
+
+    /// Hashrate estimates over the `num_points` most recent windows of `window` blocks, as
+    /// checked by [`Server::validate_hashrate_query`].
+    pub async fn data_hashrate(&self, window: i32, num_points: i32) -> Result<Arc<JsonHashrate>> {
+        let blockchain_info = self.blockchain_info().await?;
+        let key = (window, num_points);
+        if let Some(hashrate) = self.hashrate_cache.get(&blockchain_info.tip_hash, &key) {
+            return Ok(hashrate);
+        }
+        let tip_height = blockchain_info.tip_height;
+        let start_height = (tip_height - window * num_points + 1).max(0);
+        let blocks = self.block_infos(start_height, tip_height).await?;
+
+        // Align windows to the tip, so the most recent estimate is always a full window
+        let mut points = blocks
+            .rchunks(window as usize)
+            .filter_map(|window_blocks| {
+                let last_block = window_blocks.last()?;
+                Some(JsonHashratePoint {
+                    height: last_block.height,
+                    timestamp: last_block.timestamp,
+                    hashrate: estimate_hashrate(window_blocks)?,
+                    difficulty: average_difficulty(window_blocks),
+                })
+            })
+            .collect::<Vec<_>>();
+        points.reverse();
+
+        let (hashrate, difficulty) = points
+            .last()
+            .map(|point| (point.hashrate, point.difficulty))
+            .unwrap_or_default();
+
+        let hashrate = JsonHashrate {
+            window,
+            hashrate,
+            difficulty,
+            points,
+        };
+        Ok(self
+            .hashrate_cache
+            .insert(&blockchain_info.tip_hash, key, hashrate))
+    }
+
+    pub async fn data_countdowns(&self) -> Result<JsonCountdowns> {
+        let tip = self.tip_block_info().await?;
+
+        Ok(countdowns(tip.height, tip.timestamp))
+    }
+
+    /// `?window=` of the block interval stats, in blocks.
+    pub fn parse_block_intervals_window(&self, query: &HashMap<String, String>) -> Result<i32> {
+        let window: i32 = query
+            .get("window")
+            .map(|s| s.as_str())
+            .unwrap_or("144")
+            .parse()?;
+        if !(1..=MAX_CHART_BLOCKS).contains(&window) {
+            bail!("window must be between 1 and {}", MAX_CHART_BLOCKS);
+        }
+        Ok(window)
+    }
+
+    pub async fn data_block_intervals(&self, window: i32) -> Result<JsonBlockIntervals> {
+        let blockchain_info = self.blockchain_info().await?;
+        let tip_height = blockchain_info.tip_height;
+        // One more block than intervals
+        let start_height = (tip_height - window).max(0);
+        let blocks = self.block_infos(start_height, tip_height).await?;
+
+        Ok(block_intervals(&blocks, Utc::now().timestamp()))
+    }
+
+    /// Parses `?window=` and `?points=`, returning them in that order.
+    pub fn validate_block_fullness_query(
+        &self,
+        query: &HashMap<String, String>,
+    ) -> Result<(i32, i32)> {
+        parse_chart_windows(query, 1)
+    }
+
+    /// Block fullness over the `num_points` most recent windows of `window` blocks, as checked
+    /// by [`Server::validate_block_fullness_query`].
+    pub async fn data_block_fullness(
+        &self,
+        window: i32,
+        num_points: i32,
+    ) -> Result<Arc<JsonBlockFullness>> {
+        let blockchain_info = self.blockchain_info().await?;
+        let key = (window, num_points);
+        if let Some(fullness) = self
+            .block_fullness_cache
+            .get(&blockchain_info.tip_hash, &key)
+        {
+            return Ok(fullness);
+        }
+        let tip_height = blockchain_info.tip_height;
+        let start_height = (tip_height - window * num_points + 1).max(0);
+        let blocks = self.block_infos(start_height, tip_height).await?;
+
+        // Align windows to the tip, like the hashrate chart
+        let mut points = blocks
+            .rchunks(window as usize)
+            .filter_map(|window_blocks| {
+                let last_block = window_blocks.last()?;
+                let (average_percent, max_percent) = block_fullness(window_blocks)?;
+                Some(JsonBlockFullnessPoint {
+                    height: last_block.height,
+                    timestamp: last_block.timestamp,
+                    average_percent,
+                    max_percent,
+                })
+            })
+            .collect::<Vec<_>>();
+        points.reverse();
+
+        let fullness = JsonBlockFullness {
+            window,
+            max_block_size: MAX_BLOCK_SIZE,
+            points,
+        };
+        Ok(self
+            .block_fullness_cache
+            .insert(&blockchain_info.tip_hash, key, fullness))
+    }
+
+    /// Parses `?from=` and `?to=` of the tx count chart. `to` defaults to the end of the
+    /// current UTC day, so the default chart stays the same (and cached) for the whole day.
+    pub fn validate_tx_count_query(&self, query: &HashMap<String, String>) -> Result<(i64, i64)> {
+        const MAX_DAYS: i64 = 90;
+
+        let to: i64 = match query.get("to") {
+            Some(to) => to.parse()?,
+            None => {
+                let today = Utc::now().timestamp().div_euclid(SECONDS_PER_DAY);
+                (today + 1) * SECONDS_PER_DAY
+            }
+        };
+        let from: i64 = match query.get("from") {
+            Some(from) => from.parse()?,
+            None => to.saturating_sub(30 * SECONDS_PER_DAY),
+        };
+        if from >= to {
+            bail!("from must be before to");
+        }
+        if to.saturating_sub(from) > MAX_DAYS * SECONDS_PER_DAY {
+            bail!("from and to must be at most {} days apart", MAX_DAYS);
+        }
+        Ok((from, to))
+    }
+
+    /// Daily tx counts from `from` to `to`, as checked by [`Server::validate_tx_count_query`].
+    pub async fn data_tx_count_chart(&self, from: i64, to: i64) -> Result<Arc<JsonTxCountChart>> {
+        let blockchain_info = self.blockchain_info().await?;
+        if let Some(chart) = self
+            .tx_count_cache
+            .get(&blockchain_info.tip_hash, &(from, to))
+        {
+            return Ok(chart);
+        }
+
+        // Block timestamps only roughly follow the target spacing, so the heights are estimated
+        // with a generous margin and the blocks filtered by timestamp afterwards
+        let tip = self.tip_block_info().await?;
+        let blocks_since = |timestamp: i64| {
+            let num_blocks =
+                tip.timestamp.saturating_sub(timestamp).max(0) / TARGET_BLOCK_SPACING_SECS;
+            i32::try_from(num_blocks).unwrap_or(i32::MAX)
+        };
+        let start_height = tip
+            .height
+            .saturating_sub(blocks_since(from).saturating_mul(5) / 4 + 12)
+            .max(0);
+        let end_height = tip
+            .height
+            .saturating_sub(blocks_since(to).saturating_mul(3) / 4)
+            .max(0);
+        let blocks = self.block_infos(start_height, end_height).await?;
+
+        let chart = JsonTxCountChart {
+            from,
+            to,
+            points: daily_tx_counts(&blocks, from, to),
+        };
+        Ok(self
+            .tx_count_cache
+            .insert(&blockchain_info.tip_hash, (from, to), chart))
+    }
+
+    pub async fn data_signaling(&self, query: HashMap<String, String>) -> Result<JsonSignaling> {
+        let window: usize = query
+            .get("window")
+            .map(|s| s.as_str())
+            .unwrap_or("1008")
+            .parse()?;
+        if !(1..=MAX_SIGNALING_WINDOW).contains(&window) {
+            bail!("window must be between 1 and {}", MAX_SIGNALING_WINDOW);
+        }
+        Ok(self.version_bits.signaling(window))
+    }
+
+    pub async fn data_large_txs(
+        &self,
+        query: HashMap<String, String>,
+    ) -> Result<JsonLargeTxsResponse> {
+        const MAX_LIMIT: usize = 200;
+
+        let min_value: i64 = query
+            .get("min_value")
+            .map(|s| s.as_str())
+            .unwrap_or("100000000")
+            .parse()?;
+        let limit: usize = query
+            .get("limit")
+            .map(|s| s.as_str())
+            .unwrap_or("50")
+            .parse()?;
+        if min_value < MIN_TRACKED_SATS {
+            bail!("min_value must be at least {}", MIN_TRACKED_SATS);
+        }
+        if limit > MAX_LIMIT {
+            bail!("limit must be at most {}", MAX_LIMIT);
+        }
+
+        Ok(JsonLargeTxsResponse {
+            data: self.large_tx_feed.txs(min_value, limit),
+        })
+    }
+
+    /// `?window=` of the token stats endpoints, in days.
+    pub fn parse_token_stats_window(&self, query: &HashMap<String, String>) -> Result<i64> {
+        let window: i64 = query
+            .get("window")
+            .map(|s| s.as_str())
+            .unwrap_or("30")
+            .parse()?;
+        if !(1..=MAX_WINDOW_DAYS).contains(&window) {
+            bail!("window must be between 1 and {}", MAX_WINDOW_DAYS);
+        }
+        Ok(window)
+    }
+
+    pub fn data_token_stats(&self, token_id: &Sha256d, window: i64) -> JsonTokenStats {
+        let token_id_hex = token_id.to_hex_be();
+        let (points, is_complete) =
+            self.token_stats
+                .daily_stats(&token_id_hex, window, Utc::now().timestamp());
+
+        JsonTokenStats {
+            token_id: token_id_hex,
+            window,
+            is_complete,
+            points,
+        }
+    }
+
+    pub fn parse_top_transfers_limit(&self, query: &HashMap<String, String>) -> Result<usize> {
+        let limit: usize = query
+            .get("limit")
+            .map(|s| s.as_str())
+            .unwrap_or("20")
+            .parse()?;
+        if !(1..=MAX_TOP_TRANSFERS).contains(&limit) {
+            bail!("limit must be between 1 and {}", MAX_TOP_TRANSFERS);
+        }
+        Ok(limit)
+    }
+
+    pub fn data_token_top_transfers(
+        &self,
+        token_id: &Sha256d,
+        window: i64,
+        limit: usize,
+    ) -> JsonTokenTopTransfers {
+        let token_id_hex = token_id.to_hex_be();
+        let (data, is_complete) =
+            self.token_stats
+                .top_transfers(&token_id_hex, window, Utc::now().timestamp(), limit);
+
+        JsonTokenTopTransfers {
+            token_id: token_id_hex,
+            window,
+            is_complete,
+            data,
+        }
+    }
+
+    pub fn parse_recent_tokens_limit(&self, query: &HashMap<String, String>) -> Result<usize> {
+        const MAX_LIMIT: usize = 200;
+
+        let limit: usize = query
+            .get("limit")
+            .map(|s| s.as_str())
+            .unwrap_or("50")
+            .parse()?;
+        if limit > MAX_LIMIT {
+            bail!("limit must be at most {}", MAX_LIMIT);
+        }
+        Ok(limit)
+    }
+
+    pub fn data_recent_tokens(&self, limit: usize, show_spam: bool) -> JsonRecentTokensResponse {
+        JsonRecentTokensResponse {
+            data: self.token_genesis_feed.tokens(limit, |new_token| {
+                show_spam || !self.token_filter.is_spam(&new_token.token.token_id)
+            }),
+        }
+    }
+
+    pub async fn data_search_op_return(
+        &self,
+        query: HashMap<String, String>,
+    ) -> Result<JsonOpReturnSearchResponse> {
+        const MIN_QUERY_LEN: usize = 2;
+        const MAX_TAKE: usize = 200;
+
+        let search = query.get("q").map(|s| s.trim()).unwrap_or("");
+        let protocol = query.get("protocol").map(|s| s.as_str());
+        let page: usize = query
+            .get("page")
+            .map(|s| s.as_str())
+            .unwrap_or("0")
+            .parse()?;
+        let take: usize = query
+            .get("take")
+            .map(|s| s.as_str())
+            .unwrap_or("50")
+            .parse()?;
+        if search.chars().count() < MIN_QUERY_LEN {
+            bail!("q must be at least {} characters", MIN_QUERY_LEN);
+        }
+        if take == 0 || take > MAX_TAKE {
+            bail!("take must be between 1 and {}", MAX_TAKE);
+        }
+        if let Some(protocol) = protocol {
+            if !["cashtab", "memo", "other"].contains(&protocol) {
+                bail!("Unknown protocol {}", protocol);
+            }
+        }
+
+        let (data, total) =
+            self.op_return_index
+                .search(search, protocol, page.saturating_mul(take), take);
+        let num_pages = (total + take - 1) / take;
+        let page_path = |page: usize| {
+            let mut params = vec![("q", search), ("protocol", protocol.unwrap_or(""))];
+            params.retain(|(_, value)| !value.is_empty());
+            let mut path = format!("/api/v{}/search/opreturn?", API_VERSION);
+            for (name, value) in params {
+                path.push_str(&format!("{}={}&", name, urlencode(value)));
+            }
+            format!("{}page={}&take={}", path, page, take)
+        };
+        let pagination = JsonPagination {
+            page,
+            page_size: take,
+            total: Some(total),
+            num_pages,
+            next: (page + 1 < num_pages).then(|| page_path(page + 1)),
+            prev: (page > 0).then(|| page_path(page - 1)),
+        };
+
+        Ok(JsonOpReturnSearchResponse {
+            data,
+            pagination: Some(pagination),
+        })
+    }
+
+    async fn tip_block_info(&self) -> Result<BlockInfo> {
+        let blockchain_info = self.blockchain_info().await?;
+        if let Some(tip) = self.page_cache.tip_block(&blockchain_info.tip_hash) {
+            return Ok(tip);
+        }
+        let tip_height = blockchain_info.tip_height;
+        let tip = self
+            .block_infos(tip_height, tip_height)
+            .await?
+            .pop()
+            .ok_or_else(|| eyre!("Tip block not found"))?;
+        // Chronik may have moved on to another block at that height in the meantime
+        if tip.hash == blockchain_info.tip_hash {
+            self.page_cache
+                .insert_tip_block(&blockchain_info.tip_hash, tip.clone());
+        }
+        Ok(tip)
+    }
+
+    /// Block infos from `start_height` to `end_height` inclusive, fetched in concurrent batches.
+    async fn block_infos(&self, start_height: i32, end_height: i32) -> Result<Vec<BlockInfo>> {
+        const BLOCKS_PER_REQUEST: i32 = 500;
+
+        let requests = (start_height..=end_height)
+            .step_by(BLOCKS_PER_REQUEST as usize)
+            .map(|batch_start| {
+                let batch_end = (batch_start + BLOCKS_PER_REQUEST - 1).min(end_height);
+                self.timed("blocks", self.chronik.blocks(batch_start, batch_end))
+            });
+        let batches = future::try_join_all(requests).await?;
+
+        Ok(batches.into_iter().flatten().collect())
+    }
+
+    async fn burn_address_sats(&self) -> Result<i64> {
+        let mut burned_sats = 0;
+        for burn_address in &self.burn_addresses {
+            let (script_type, script_payload) = cash_addr_to_script_type_payload(burn_address);
+            let script_endpoint = self.chronik.script(script_type, &script_payload);
+            for script_utxos in self.timed("script_utxos", script_endpoint.utxos()).await? {
+                burned_sats += script_utxos
+                    .utxos
+                    .iter()
+                    .map(|utxo| utxo.value)
+                    .sum::<i64>();
+            }
+        }
+        Ok(burned_sats)
+    }
+
+    pub async fn verify_message(
+        &self,
+        request: &JsonVerifyMessageRequest,
+    ) -> Result<JsonVerifyMessageResponse> {
+        let address = parse_address(&request.address, self.network)?;
+        let is_valid =
+            signed_message::verify_message(&address, &request.message, &request.signature)?;
 
-        Ok(JsonTxsResponse { data: json_txs })
+        Ok(JsonVerifyMessageResponse { is_valid })
     }
 
-    pub async fn data_address_txs(
+    pub async fn broadcast_tx(&self, raw_tx: Vec<u8>) -> Result<JsonBroadcastTxResponse> {
+        let response = self
+            .timed("broadcast_tx", self.chronik.broadcast_tx(raw_tx))
+            .await?;
+
+        Ok(JsonBroadcastTxResponse {
+            txid: to_be_hex(&response.txid),
+        })
+    }
+
+    async fn history_after_cursor(
         &self,
-        address: &str,
-        query: HashMap<String, String>,
-    ) -> Result<JsonTxsResponse> {
-        let address = CashAddress::parse_cow(address.into())?;
-        let (script_type, script_payload) = cash_addr_to_script_type_payload(&address);
-        let script_endpoint = self.chronik.script(script_type, &script_payload);
+        script_type: ScriptType,
+        script_payload: &[u8],
+        cursor: &HistoryCursor,
+        take: usize,
+    ) -> Result<(TxHistoryPage, Option<HistoryCursor>)> {
+        // New txs only ever get prepended, so the cursor tx can only have moved to a later page
+        const MAX_CURSOR_SCAN_PAGES: usize = 10;
 
-        let page: usize = query
-            .get("page")
-            .map(|s| s.as_str())
-            .unwrap_or("0")
-            .parse()?;
-        let take: usize = query
-            .get("take")
-            .map(|s| s.as_str())
-            .unwrap_or("200")
-            .parse()?;
-        let address_tx_history = script_endpoint.history_with_page_size(page, take).await?;
+        let script_endpoint = self.chronik.script(script_type, script_payload);
+        let mut txs = Vec::with_capacity(take);
+        let mut found_cursor = false;
+        let mut num_pages = 0;
+        let mut page = cursor.page;
+        let mut last_page = page;
 
-        let token_ids = address_tx_history
-            .txs
-            .iter()
-            .filter_map(|tx| {
-                let slp_tx_data = tx.slp_tx_data.as_ref()?;
-                let slp_meta = slp_tx_data.slp_meta.as_ref()?;
-                if slp_meta.token_type() == SlpTokenType::UnknownTokenType {
-                    return None;
+        for _ in 0..MAX_CURSOR_SCAN_PAGES {
+            let history = self
+                .timed(
+                    "script_history",
+                    script_endpoint.history_with_page_size(page, take),
+                )
+                .await?;
+            num_pages = history.num_pages;
+            for tx in history.txs {
+                if txs.len() == take {
+                    break;
                 }
-                Some(Sha256d::from_slice_be_or_null(&slp_meta.token_id))
-            })
-            .collect();
+                if found_cursor {
+                    txs.push(tx);
+                    last_page = page;
+                } else if tx.txid == cursor.txid {
+                    found_cursor = true;
+                } else if cursor.is_past(&tx) {
+                    // Cursor tx was reorged out; resume at the first tx below its block
+                    found_cursor = true;
+                    txs.push(tx);
+                    last_page = page;
+                }
+            }
+            if txs.len() == take || page + 1 >= num_pages as usize {
+                break;
+            }
+            page += 1;
+        }
 
-        let tokens = self.batch_get_chronik_tokens(token_ids).await?;
-        let json_tokens = tokens_to_json(&tokens)?;
-        let json_txs = tx_history_to_json(&address, address_tx_history, &json_tokens)?;
+        if !found_cursor {
+            bail!("Cursor is no longer valid, restart from the first page");
+        }
+
+        let next_cursor = match txs.last() {
+            Some(tx) if txs.len() == take => Some(HistoryCursor::from_tx(tx, last_page)),
+            _ => None,
+        };
 
-        Ok(JsonTxsResponse { data: json_txs })
+        Ok((TxHistoryPage { txs, num_pages }, next_cursor))
     }
 }
 
 impl Server {
-    pub async fn block(&self, block_hex: &str) -> Result<String> {
+    pub async fn block(&self, block_hex: &str, preferences: Preferences) -> Result<String> {
         let block_hash = Sha256d::from_hex_be(block_hex)?;
 
-        let block = self.chronik.block_by_hash(&block_hash).await?;
-        let block_info = block.block_info.ok_or_else(|| eyre!("Block has no info"))?;
+        let block = self.block_by_hash(&block_hash).await?;
+        let block_info = block
+            .block_info
+            .clone()
+            .ok_or_else(|| eyre!("Block has no info"))?;
         let block_details = block
             .block_details
+            .clone()
             .ok_or_else(|| eyre!("Block has details"))?;
 
-        let blockchain_info = self.chronik.blockchain_info().await?;
+        let blockchain_info = self.blockchain_info().await?;
         let best_height = blockchain_info.tip_height;
 
         let difficulty = calculate_block_difficulty(block_info.n_bits);
         let timestamp = Utc.timestamp(block_info.timestamp, 0);
         let coinbase_data = block.txs[0].inputs[0].input_script.clone();
+        let coinbase_split = coinbase_split_to_json(&block.txs[0], self.network);
+        let signaled_deployments = signaled_bits(block_details.version)
+            .into_iter()
+            .map(|bit| format!("bit {}", bit))
+            .collect();
         let confirmations = best_height - block_info.height + 1;
 
+        let meta = block_meta(&self.public_url, block_hex, &block_info);
+
         let block_template = BlockTemplate {
             block_hex,
-            block_header: block.raw_header,
+            block_header: block.raw_header.clone(),
             block_info,
             block_details,
             confirmations,
             timestamp,
             difficulty,
             coinbase_data,
+            coinbase_split,
+            signaled_deployments,
+            meta,
+            preferences,
         };
 
         Ok(block_template.render().unwrap())
     }
 
-    pub async fn tx(&self, tx_hex: &str) -> Result<String> {
+    /// Inputs of an unconfirmed `tx` whose previous output chronik records as spent by another
+    /// tx, i.e. a conflicting mempool tx or a mined competitor. Only the first
+    /// [`DOUBLE_SPEND_MAX_PREV_TXS`] previous txs are checked, and those that fail to load are
+    /// skipped, so a flaky lookup never fails the whole tx.
+    async fn double_spent_inputs(&self, tx: &Tx) -> Result<Vec<JsonDoubleSpentInput>> {
+        if tx.block.is_some() || tx.is_coinbase {
+            return Ok(Vec::new());
+        }
+        let blockchain_info = self.blockchain_info().await?;
+        if let Some(double_spent) = self
+            .double_spend_cache
+            .get(&blockchain_info.tip_hash, &tx.txid)
+        {
+            return Ok(Vec::clone(&double_spent));
+        }
+
+        let prev_txids = tx
+            .inputs
+            .iter()
+            .filter_map(|input| input.prev_out.as_ref())
+            .map(|prev_out| Sha256d::from_slice(&prev_out.txid))
+            .collect::<Result<HashSet<_>, _>>()?;
+        let prev_txs = stream::iter(prev_txids.into_iter().take(DOUBLE_SPEND_MAX_PREV_TXS))
+            .map(|txid| async move { self.timed("tx", self.chronik.tx(&txid)).await })
+            .buffer_unordered(DOUBLE_SPEND_CONCURRENT_LOOKUPS)
+            .filter_map(|prev_tx| future::ready(prev_tx.ok()))
+            .map(|prev_tx| (prev_tx.txid.clone(), prev_tx))
+            .collect::<HashMap<_, _>>()
+            .await;
+
+        let double_spent = double_spent_inputs(tx, &prev_txs);
+        let double_spent = self.double_spend_cache.insert(
+            &blockchain_info.tip_hash,
+            tx.txid.clone(),
+            double_spent,
+        );
+        Ok(Vec::clone(&double_spent))
+    }
+
+    pub async fn tx(&self, tx_hex: &str, preferences: Preferences) -> Result<String> {
         let tx_hash = Sha256d::from_hex_be(tx_hex)?;
-        let tx = self.chronik.tx(&tx_hash).await?;
-        let (token_id, token) = match &tx.slp_tx_data {
+        let (tx, raw_tx, blockchain_info) = tokio::try_join!(
+            self.timed("tx", self.chronik.tx(&tx_hash)),
+            self.timed("raw_tx", self.chronik.raw_tx(&tx_hash)),
+            self.blockchain_info(),
+        )?;
+
+        // The tx's own token and every token burned by an input are looked up once each,
+        // shared by the token section and the burn list
+        let mut token_ids = HashSet::new();
+        let token_id = match &tx.slp_tx_data {
             Some(slp_tx_data) => {
                 let slp_meta = slp_tx_data.slp_meta.as_ref().expect("Impossible");
                 let token_id = Sha256d::from_slice_be(&slp_meta.token_id)?;
-                let mut token = None;
                 if slp_meta.token_type() != SlpTokenType::UnknownTokenType {
-                    token = Some(self.chronik.token(&token_id).await?);
+                    token_ids.insert(token_id.clone());
                 }
-                (Some(token_id), token)
+                Some(token_id)
             }
-            None => (None, None),
+            None => None,
         };
+        for input in &tx.inputs {
+            if let Some(slp_burn) = &input.slp_burn {
+                token_ids.insert(Sha256d::from_slice_be(&slp_burn.token_id)?);
+            }
+        }
+        let (tokens, position_in_block, double_spent_inputs) = tokio::try_join!(
+            self.batch_get_chronik_tokens(token_ids),
+            self.tx_position_in_block(&tx),
+            self.double_spent_inputs(&tx),
+        )?;
+        let token = tx
+            .slp_tx_data
+            .as_ref()
+            .and_then(|slp_tx_data| slp_tx_data.slp_meta.as_ref())
+            .and_then(|slp_meta| tokens.get(&hex::encode(&slp_meta.token_id)))
+            .cloned();
         let token_ticker = token.as_ref().and_then(|token| {
             Some(String::from_utf8_lossy(
                 &token
@@ -230,9 +2400,26 @@ impl Server {
                     .token_ticker,
             ))
         });
-        let (title, is_token): (Cow<str>, bool) = match &token_ticker {
-            Some(token_ticker) => (format!("{} Transaction", token_ticker).into(), true),
-            None => {
+        // Chronik only parses the token types it knows; the others are shown from the raw
+        // OP_RETURN rather than as plain XEC txs
+        let is_known_token_type = tx
+            .slp_tx_data
+            .as_ref()
+            .and_then(|slp_tx_data| slp_tx_data.slp_meta.as_ref())
+            .map_or(false, |slp_meta| {
+                slp_meta.token_type() != SlpTokenType::UnknownTokenType
+            });
+        let raw_token_section = match is_known_token_type {
+            true => None,
+            false => raw_token_section(&tx),
+        };
+        let (title, is_token): (Cow<str>, bool) = match (&token_ticker, &raw_token_section) {
+            (Some(token_ticker), _) => (format!("{} Transaction", token_ticker).into(), true),
+            (None, Some(section)) => (
+                format!("{} Token Transaction", section.token_type_name).into(),
+                true,
+            ),
+            (None, None) => {
                 if tx.slp_error_msg.is_empty() {
                     ("eCash Transaction".into(), false)
                 } else {
@@ -270,14 +2457,21 @@ impl Server {
             }
             None => {
                 if tx.slp_error_msg.is_empty() {
-                    "Token Details (Invalid Transaction)".into()
-                } else {
                     "".into()
+                } else {
+                    "Token Details (Invalid Transaction)".into()
                 }
             }
         };
+        let token_section_title: Cow<str> = match &raw_token_section {
+            Some(section) => format!(
+                "Token Details ({} Type {} {} Transaction)",
+                section.token_type_name, section.token_type, section.tx_type,
+            )
+            .into(),
+            None => token_section_title,
+        };
 
-        let blockchain_info = self.chronik.blockchain_info().await?;
         let confirmations = match &tx.block {
             Some(block_meta) => blockchain_info.tip_height - block_meta.height + 1,
             None => 0,
@@ -287,10 +2481,24 @@ impl Server {
             None => Utc.timestamp(tx.time_first_seen, 0),
         };
 
-        let raw_tx = self.chronik.raw_tx(&tx_hash).await?;
         let raw_tx = raw_tx.hex();
 
         let tx_stats = calc_tx_stats(&tx, None);
+        let token_tickers = tokens
+            .iter()
+            .filter_map(|(token_id, token)| {
+                let genesis_info = token.slp_tx_data.as_ref()?.genesis_info.as_ref()?;
+                let token_ticker = String::from_utf8_lossy(&genesis_info.token_ticker);
+                Some((token_id.clone(), token_ticker.to_string()))
+            })
+            .collect();
+        let tx_slp_errors = slp_errors(&tx, &token_tickers);
+        let meta = tx_meta(
+            &self.public_url,
+            tx_hex,
+            tx_stats.sats_output,
+            token_ticker.as_deref(),
+        );
 
         let transaction_template = TransactionTemplate {
             title: &title,
@@ -310,7 +2518,14 @@ impl Server {
             token_output: tx_stats.token_output,
             raw_tx,
             confirmations,
+            position_in_block,
             timestamp,
+            burn_scripts: &self.burn_scripts,
+            slp_errors: tx_slp_errors,
+            double_spent_inputs,
+            raw_token_section,
+            meta,
+            preferences,
         };
 
         Ok(transaction_template.render().unwrap())
@@ -318,25 +2533,461 @@ impl Server {
 }
 
 impl Server {
-    pub async fn address<'a>(&'a self, address: &str) -> Result<String> {
-        let address = CashAddress::parse_cow(address.into())?;
+    pub async fn address<'a>(
+        &'a self,
+        address: &str,
+        query: HashMap<String, String>,
+        preferences: Preferences,
+    ) -> Result<String> {
+        let address = parse_address(address, self.network)?;
         let sats_address = address.with_prefix(self.satoshi_addr_prefix);
         let token_address = address.with_prefix(self.tokens_addr_prefix);
 
-        let legacy_address = to_legacy_address(&address);
+        let legacy_address = to_legacy_address(&address, self.network);
         let sats_address = sats_address.as_str();
         let token_address = token_address.as_str();
 
         let (script_type, script_payload) = cash_addr_to_script_type_payload(&address);
         let script_endpoint = self.chronik.script(script_type, &script_payload);
         let page_size = 1; // Set to minimum so that num_pages == total existing tx's
-        let address_tx_history = script_endpoint.history_with_page_size(0, page_size).await?;
+        let address_tx_history = self
+            .timed(
+                "script_history",
+                script_endpoint.history_with_page_size(0, page_size),
+            )
+            .await?;
         let address_num_txs = address_tx_history.num_pages;
 
-        let utxos = script_endpoint.utxos().await?;
+        let show_spam = query.get("show_spam").map(|s| s.as_str()) == Some("true");
+        // History tab of a single token, empty for all txs
+        let selected_token_id = query
+            .get("token_id")
+            .filter(|token_id| Sha256d::from_hex_be(token_id).is_ok())
+            .map(|token_id| token_id.to_lowercase())
+            .unwrap_or_default();
+        // Kept from a payment URI pasted into the search
+        let requested_sats = query
+            .get("amount")
+            .and_then(|amount| parse_xec_amount(amount));
+        let balances = self
+            .address_balances(script_type, &script_payload, show_spam)
+            .await?;
+        let token_balances = token_balances(balances.json_balances);
+
+        let meta = address_meta(
+            &self.public_url,
+            address.as_str(),
+            Some(balances.breakdown.total_xec_sats()),
+            address_num_txs,
+        );
+
+        let address_template = AddressTemplate {
+            tokens: balances.tokens,
+            token_utxos: balances.token_utxos,
+            breakdown: balances.breakdown,
+            unconfirmed_xec: balances.unconfirmed_xec,
+            unconfirmed_truncated: balances.unconfirmed_truncated,
+            num_hidden_tokens: balances.num_hidden_tokens,
+            requested_sats,
+            selected_token_id: &selected_token_id,
+            address_num_txs,
+            address: address.as_str(),
+            sats_address,
+            token_address,
+            legacy_address,
+            token_balances,
+            meta,
+            preferences,
+        };
+
+        Ok(address_template.render().unwrap())
+    }
+
+    pub async fn data_address_forms(&self, address: &str) -> Result<JsonAddressForms> {
+        let address = parse_address(address, self.network)?;
+
+        Ok(self.address_forms(&address))
+    }
+
+    pub async fn data_validate_address(&self, address: &str) -> Result<JsonAddressValidation> {
+        let validation = match parse_address(address, self.network) {
+            Ok(parsed) => JsonAddressValidation {
+                is_valid: true,
+                network: address_network(address).map(Network::name),
+                forms: Some(self.address_forms(&parsed)),
+                error: None,
+            },
+            Err(err) => JsonAddressValidation {
+                is_valid: false,
+                network: address_network(address).map(Network::name),
+                forms: None,
+                error: Some(err.to_string()),
+            },
+        };
+
+        Ok(validation)
+    }
+
+    fn address_forms(&self, address: &CashAddress) -> JsonAddressForms {
+        JsonAddressForms {
+            address_type: address_type_name(address.addr_type()),
+            cash_address: address
+                .with_prefix(self.satoshi_addr_prefix)
+                .as_str()
+                .to_string(),
+            token_address: address
+                .with_prefix(self.tokens_addr_prefix)
+                .as_str()
+                .to_string(),
+            legacy_address: to_legacy_address(&address, self.network),
+            output_script: hex::encode(address.to_script().bytecode()),
+        }
+    }
+
+    pub async fn data_address_balances(
+        &self,
+        address: &str,
+        query: HashMap<String, String>,
+    ) -> Result<JsonAddressBalances> {
+        let address = parse_address(address, self.network)?;
+        let (script_type, script_payload) = cash_addr_to_script_type_payload(&address);
+        let show_spam = query.get("show_spam").map(|s| s.as_str()) == Some("true");
+        let balances = self
+            .address_balances(script_type, &script_payload, show_spam)
+            .await?;
+
+        Ok(JsonAddressBalances {
+            tokens: tokens_to_json(&balances.tokens)?,
+            balances: balances.json_balances,
+            breakdown: balances.breakdown.to_json(),
+            unconfirmed_truncated: balances.unconfirmed_truncated,
+            num_hidden_tokens: balances.num_hidden_tokens,
+        })
+    }
+
+    pub async fn script(
+        &self,
+        script_hex: &str,
+        query: HashMap<String, String>,
+        preferences: Preferences,
+    ) -> Result<String> {
+        let output_script = hex::decode(script_hex)?;
+        if output_script.is_empty() {
+            bail!("Empty output script");
+        }
+        let (script_type, script_payload) = script_to_script_type_payload(&output_script);
+        // Only decode standard scripts, nulldata ops of arbitrary scripts may not parse
+        let address = match script_type {
+            ScriptType::P2pkh | ScriptType::P2sh => {
+                match destination_from_script(self.satoshi_addr_prefix, &output_script) {
+                    Destination::Address(address) => Some(address.as_str().to_string()),
+                    _ => None,
+                }
+            }
+            _ => None,
+        };
+
+        let script_endpoint = self.chronik.script(script_type, &script_payload);
+        let page_size = 1; // Set to minimum so that num_pages == total existing tx's
+        let script_tx_history = self
+            .timed(
+                "script_history",
+                script_endpoint.history_with_page_size(0, page_size),
+            )
+            .await?;
+        let script_num_txs = script_tx_history.num_pages;
+
+        let show_spam = query.get("show_spam").map(|s| s.as_str()) == Some("true");
+        let balances = self
+            .address_balances(script_type, &script_payload, show_spam)
+            .await?;
+        let token_balances = token_balances(balances.json_balances);
+
+        let script_template = ScriptTemplate {
+            script_hex: hex::encode(&output_script),
+            script_kind: script_kind(script_type),
+            address,
+            tokens: balances.tokens,
+            breakdown: balances.breakdown,
+            unconfirmed_xec: balances.unconfirmed_xec,
+            unconfirmed_truncated: balances.unconfirmed_truncated,
+            num_hidden_tokens: balances.num_hidden_tokens,
+            script_num_txs,
+            token_balances,
+            preferences,
+        };
+
+        Ok(script_template.render().unwrap())
+    }
+
+    pub async fn data_script_balances(
+        &self,
+        script_hex: &str,
+        query: HashMap<String, String>,
+    ) -> Result<JsonAddressBalances> {
+        let output_script = hex::decode(script_hex)?;
+        let (script_type, script_payload) = script_to_script_type_payload(&output_script);
+        let show_spam = query.get("show_spam").map(|s| s.as_str()) == Some("true");
+        let balances = self
+            .address_balances(script_type, &script_payload, show_spam)
+            .await?;
+
+        Ok(JsonAddressBalances {
+            tokens: tokens_to_json(&balances.tokens)?,
+            balances: balances.json_balances,
+            breakdown: balances.breakdown.to_json(),
+            unconfirmed_truncated: balances.unconfirmed_truncated,
+            num_hidden_tokens: balances.num_hidden_tokens,
+        })
+    }
+
+    /// A page of the txs of a script that involve one token, and whether there are more.
+    /// Chronik has no per-token history of a script, so this filters its most recent
+    /// [`TOKEN_HISTORY_MAX_TXS`] txs; older ones are left out.
+    async fn token_history_page(
+        &self,
+        script_type: ScriptType,
+        script_payload: &[u8],
+        token_id_hex: &str,
+        page: usize,
+        take: usize,
+    ) -> Result<(Vec<Tx>, bool)> {
+        let script_endpoint = self.chronik.script(script_type, script_payload);
+        // One more match than the page holds tells whether there is a next page
+        let num_wanted = page
+            .saturating_add(1)
+            .saturating_mul(take)
+            .saturating_add(1);
+        let mut token_txs = Vec::new();
+        let mut num_txs_scanned = 0;
+        let mut history_page = 0;
+        loop {
+            let history = self
+                .timed(
+                    "script_history",
+                    script_endpoint.history_with_page_size(history_page, TOKEN_HISTORY_PAGE_SIZE),
+                )
+                .await?;
+            num_txs_scanned += history.txs.len();
+            token_txs.extend(history.txs.into_iter().filter(|tx| {
+                tx.slp_tx_data
+                    .as_ref()
+                    .and_then(|slp_tx_data| slp_tx_data.slp_meta.as_ref())
+                    .map_or(false, |slp_meta| {
+                        hex::encode(&slp_meta.token_id) == token_id_hex
+                    })
+            }));
+            history_page += 1;
+            if history_page >= history.num_pages as usize
+                || num_txs_scanned >= TOKEN_HISTORY_MAX_TXS
+                || token_txs.len() >= num_wanted
+            {
+                break;
+            }
+        }
+
+        Ok(page_of(token_txs, page, take))
+    }
+
+    /// Address from a path or query parameter, in any form [`parse_address`] accepts.
+    pub fn parse_address(&self, address: &str) -> Result<CashAddress<'static>> {
+        parse_address(address, self.network)
+    }
+
+    pub fn parse_counterparties_limit(&self, query: &HashMap<String, String>) -> Result<usize> {
+        let limit: usize = query
+            .get("limit")
+            .map(|s| s.as_str())
+            .unwrap_or("50")
+            .parse()?;
+        if !(1..=MAX_COUNTERPARTIES_LIMIT).contains(&limit) {
+            bail!("limit must be between 1 and {}", MAX_COUNTERPARTIES_LIMIT);
+        }
+        Ok(limit)
+    }
+
+    pub async fn data_address_counterparties(
+        &self,
+        address: &CashAddress<'_>,
+        limit: usize,
+    ) -> Result<JsonCounterpartiesResponse> {
+        let output_script = address.to_script().bytecode().to_vec();
+        let blockchain_info = self.blockchain_info().await?;
+        let response = match self
+            .counterparties_cache
+            .get(&blockchain_info.tip_hash, &output_script)
+        {
+            Some(response) => response,
+            None => {
+                let response = self.address_counterparties(address).await?;
+                self.counterparties_cache
+                    .insert(&blockchain_info.tip_hash, output_script, response)
+            }
+        };
+
+        Ok(JsonCounterpartiesResponse {
+            data: response.data.iter().take(limit).cloned().collect(),
+            num_txs_scanned: response.num_txs_scanned,
+            is_complete: response.is_complete,
+        })
+    }
+
+    /// Scans up to [`COUNTERPARTIES_MAX_TXS`] of the most recent txs of `address`; at 2000
+    /// txs that's 10 chronik requests, hence the cache in front of this.
+    async fn address_counterparties(
+        &self,
+        address: &CashAddress<'_>,
+    ) -> Result<JsonCounterpartiesResponse> {
+        let (script_type, script_payload) = cash_addr_to_script_type_payload(address);
+        let output_script = address.to_script().bytecode().to_vec();
+        let script_endpoint = self.chronik.script(script_type, &script_payload);
+
+        let mut txs = Vec::new();
+        let mut page = 0;
+        let is_complete = loop {
+            let history = self
+                .timed(
+                    "script_history",
+                    script_endpoint.history_with_page_size(page, COUNTERPARTIES_PAGE_SIZE),
+                )
+                .await?;
+            txs.extend(history.txs);
+            page += 1;
+            if page >= history.num_pages as usize {
+                break true;
+            }
+            if txs.len() >= COUNTERPARTIES_MAX_TXS {
+                break false;
+            }
+        };
+
+        Ok(JsonCounterpartiesResponse {
+            data: counterparties(&txs, &output_script, self.satoshi_addr_prefix),
+            num_txs_scanned: txs.len(),
+            is_complete,
+        })
+    }
+
+    /// Parses `?token_id=`, `?page=` and `?take=` of a UTXO listing.
+    pub fn validate_utxos_query(&self, query: &HashMap<String, String>) -> Result<UtxosQuery> {
+        let token_id = match query.get("token_id") {
+            Some(token_id) => Some(Sha256d::from_hex_be(token_id)?.to_hex_be()),
+            None => None,
+        };
+        let page: usize = query
+            .get("page")
+            .map(|s| s.as_str())
+            .unwrap_or("0")
+            .parse()?;
+        let take: usize = match query.get("take") {
+            Some(take) => take.parse()?,
+            None => MAX_UTXOS_PAGE_SIZE,
+        };
+        if !(1..=MAX_UTXOS_PAGE_SIZE).contains(&take) {
+            bail!("take must be between 1 and {}", MAX_UTXOS_PAGE_SIZE);
+        }
+        Ok(UtxosQuery {
+            token_id,
+            page,
+            take,
+        })
+    }
+
+    pub async fn data_address_utxos(
+        &self,
+        address: &CashAddress<'_>,
+        query: &UtxosQuery,
+    ) -> Result<JsonUtxosResponse> {
+        let (script_type, script_payload) = cash_addr_to_script_type_payload(address);
+        let path = format!("/api/v{}/address/{}/utxos", API_VERSION, address.as_str());
+        self.script_utxos(script_type, &script_payload, &path, query)
+            .await
+    }
+
+    pub async fn data_script_utxos(
+        &self,
+        output_script: &[u8],
+        query: &UtxosQuery,
+    ) -> Result<JsonUtxosResponse> {
+        let (script_type, script_payload) = script_to_script_type_payload(output_script);
+        let path = format!(
+            "/api/v{}/script/{}/utxos",
+            API_VERSION,
+            hex::encode(output_script)
+        );
+        self.script_utxos(script_type, &script_payload, &path, query)
+            .await
+    }
+
+    /// A page of the UTXOs of one balance of a script, the token's if `query` has one, plain
+    /// XEC otherwise.
+    async fn script_utxos(
+        &self,
+        script_type: ScriptType,
+        script_payload: &[u8],
+        path: &str,
+        query: &UtxosQuery,
+    ) -> Result<JsonUtxosResponse> {
+        let balance_key = query.token_id.as_deref().unwrap_or("main");
+        let script_endpoint = self.chronik.script(script_type, script_payload);
+        let (utxos, mempool) = tokio::try_join!(
+            self.timed("script_utxos", script_endpoint.utxos()),
+            self.mempool_activity(script_type, script_payload),
+        )?;
+        let balance_utxos = utxos
+            .iter()
+            .flat_map(|script_utxos| &script_utxos.utxos)
+            .filter(|utxo| utxo_balance_key(utxo) == balance_key)
+            .collect::<Vec<_>>();
+        let data = balance_utxos
+            .iter()
+            .skip(query.page.saturating_mul(query.take))
+            .take(query.take)
+            .map(|utxo| {
+                let mut json_utxo = utxo_to_json(utxo);
+                json_utxo.spent_in = mempool.spent_in(utxo);
+                json_utxo
+            })
+            .collect();
+
+        let total = balance_utxos.len();
+        let num_pages = (total + query.take - 1) / query.take;
+        let token_param = match &query.token_id {
+            Some(token_id) => format!("token_id={}&", token_id),
+            None => String::new(),
+        };
+        let page_path =
+            |page: usize| format!("{}?{}page={}&take={}", path, token_param, page, query.take);
+        Ok(JsonUtxosResponse {
+            data,
+            pagination: Some(JsonPagination {
+                page: query.page,
+                page_size: query.take,
+                total: Some(total),
+                num_pages,
+                next: (query.page.saturating_add(1) < num_pages).then(|| page_path(query.page + 1)),
+                prev: (query.page > 0).then(|| page_path(query.page - 1)),
+            }),
+        })
+    }
+
+    /// Groups the UTXOs of a script into its XEC balance (keyed "main") and one balance per
+    /// token, leaving out spam tokens unless `show_spam` is set. UTXOs already spent by an
+    /// unconfirmed tx don't count.
+    async fn address_balances(
+        &self,
+        script_type: ScriptType,
+        script_payload: &[u8],
+        show_spam: bool,
+    ) -> Result<AddressBalances> {
+        let script_endpoint = self.chronik.script(script_type, script_payload);
+        let (utxos, mempool, blockchain_info) = tokio::try_join!(
+            self.timed("script_utxos", script_endpoint.utxos()),
+            self.mempool_activity(script_type, script_payload),
+            self.blockchain_info(),
+        )?;
 
-        let mut token_dust: i64 = 0;
-        let mut total_xec: i64 = 0;
+        let mut breakdown = BalanceBreakdown::default();
 
         let mut token_ids: HashSet<Sha256d> = HashSet::new();
         let mut token_utxos: Vec<Utxo> = Vec::new();
@@ -345,27 +2996,29 @@ impl Server {
             token_id: None,
             sats_amount: 0,
             token_amount: 0,
+            unconfirmed_sats: 0,
+            unconfirmed_token_amount: 0,
             utxos: Vec::new(),
         };
+        let mut hidden_token_ids: HashSet<String> = HashSet::new();
 
         for utxo_script in utxos.into_iter() {
             for utxo in utxo_script.utxos.into_iter() {
-                let OutPoint { txid, out_idx } = &utxo.outpoint.as_ref().unwrap();
-                let mut json_utxo = JsonUtxo {
-                    tx_hash: to_be_hex(txid),
-                    out_idx: *out_idx,
-                    sats_amount: utxo.value,
-                    token_amount: 0,
-                    is_coinbase: utxo.is_coinbase,
-                    block_height: utxo.block_height,
-                };
+                if mempool.spent_in(&utxo).is_some() {
+                    continue;
+                }
+                let json_utxo = utxo_to_json(&utxo);
+                breakdown.add(&utxo, blockchain_info.tip_height);
 
                 match (&utxo.slp_meta, &utxo.slp_token) {
                     (Some(slp_meta), Some(slp_token)) => {
                         let token_id_hex = hex::encode(&slp_meta.token_id);
                         let token_id_hash = Sha256d::from_slice_be_or_null(&slp_meta.token_id);
 
-                        json_utxo.token_amount = slp_token.amount;
+                        if !show_spam && self.token_filter.is_spam(&token_id_hex) {
+                            hidden_token_ids.insert(token_id_hex);
+                            continue;
+                        }
 
                         match json_balances.entry(token_id_hex) {
                             Entry::Occupied(mut entry) => {
@@ -379,60 +3032,169 @@ impl Server {
                                     token_id: Some(hex::encode(&slp_meta.token_id)),
                                     sats_amount: utxo.value,
                                     token_amount: slp_token.amount.into(),
+                                    unconfirmed_sats: 0,
+                                    unconfirmed_token_amount: 0,
                                     utxos: vec![json_utxo],
                                 });
                             }
                         }
 
                         token_ids.insert(token_id_hash);
-                        token_dust += utxo.value;
                         token_utxos.push(utxo);
                     }
-                    _ => {
-                        total_xec += utxo.value;
-                        main_json_balance.utxos.push(json_utxo);
-                    }
+                    // Token dust without token info still counts in `breakdown`
+                    (None, Some(_)) => {}
+                    _ => main_json_balance.utxos.push(json_utxo),
                 };
             }
         }
+        main_json_balance.sats_amount = breakdown.total_xec_sats();
         json_balances.insert(String::from("main"), main_json_balance);
+        // Only for balances still held; a token sent away entirely just disappears
+        for (balance_key, (sats, token_amount)) in &mempool.deltas {
+            if let Some(balance) = json_balances.get_mut(balance_key) {
+                balance.unconfirmed_sats = *sats;
+                balance.unconfirmed_token_amount = *token_amount;
+            }
+        }
+        let unconfirmed_xec = json_balances["main"].unconfirmed_sats;
 
         let tokens = self.batch_get_chronik_tokens(token_ids).await?;
-        let json_tokens = tokens_to_json(&tokens)?;
-
-        let encoded_tokens = serde_json::to_string(&json_tokens)?.replace('\'', r"\'");
-        let encoded_balances = serde_json::to_string(&json_balances)?.replace('\'', r"\'");
 
-        let address_template = AddressTemplate {
-            tokens,
-            token_utxos,
-            token_dust,
-            total_xec,
-            address_num_txs,
-            address: address.as_str(),
-            sats_address,
-            token_address,
-            legacy_address,
+        Ok(AddressBalances {
             json_balances,
-            encoded_tokens,
-            encoded_balances,
+            token_utxos,
+            tokens,
+            breakdown,
+            unconfirmed_xec,
+            unconfirmed_truncated: mempool.truncated,
+            num_hidden_tokens: hidden_token_ids.len(),
+        })
+    }
+
+    /// Outpoints spent by the unconfirmed txs of a script, and how these txs change its
+    /// balances. Unconfirmed txs come first in the history, so its pages are scanned until the
+    /// first confirmed tx.
+    ///
+    /// Chronik's UTXOs normally leave spent outpoints out already, but around a new block its
+    /// UTXO set and mempool can briefly disagree, which would show coins as spendable twice over.
+    async fn mempool_activity(
+        &self,
+        script_type: ScriptType,
+        script_payload: &[u8],
+    ) -> Result<MempoolActivity> {
+        let script_endpoint = self.chronik.script(script_type, script_payload);
+        let is_own_script = |script: &[u8]| {
+            let (other_type, other_payload) = script_to_script_type_payload(script);
+            other_type == script_type && other_payload == script_payload
         };
 
-        Ok(address_template.render().unwrap())
+        let mut activity = MempoolActivity::default();
+        for page in 0..MEMPOOL_MAX_PAGES {
+            let history = self
+                .timed(
+                    "script_history",
+                    script_endpoint.history_with_page_size(page, MEMPOOL_PAGE_SIZE),
+                )
+                .await?;
+            let has_more = activity.add_history_page(&history.txs, &is_own_script);
+            if !has_more || page + 1 >= history.num_pages as usize {
+                return Ok(activity);
+            }
+        }
+        activity.truncated = true;
+        Ok(activity)
+    }
+
+    /// Token ID of an identicon URL, as hex in the order the identicon is derived from.
+    pub fn parse_identicon_token_id(&self, token_id_hex: &str) -> Result<[u8; 32]> {
+        hex::decode(token_id_hex)?
+            .try_into()
+            .map_err(|_| eyre!("Token ID must be 32 bytes"))
+    }
+
+    pub fn identicon(&self, token_id: &[u8; 32]) -> String {
+        token_identicon_svg(token_id)
+    }
+
+    /// Checks `hash` is a txid, block hash or address, whichever `kind` asks for, and
+    /// normalizes it.
+    pub fn parse_preview_hash(&self, kind: PreviewKind, hash: &str) -> Result<String> {
+        match kind {
+            PreviewKind::Tx | PreviewKind::Block => Ok(Sha256d::from_hex_be(hash)?.to_hex_be()),
+            PreviewKind::Address => Ok(self.parse_address(hash)?.as_str().to_string()),
+        }
+    }
+
+    /// PNG preview of the page of a tx, block or address, `hash` as normalized by
+    /// [`Server::parse_preview_hash`]. `None` if chronik doesn't know it.
+    pub async fn preview_image(
+        &self,
+        kind: PreviewKind,
+        hash: String,
+    ) -> Result<Option<Arc<Vec<u8>>>> {
+        let blockchain_info = self.blockchain_info().await?;
+        let key = (kind, hash);
+        if let Some(png) = self.preview_cache.get(&blockchain_info.tip_hash, &key) {
+            return Ok(Some(png));
+        }
+        let hash = &key.1;
+        let meta = match kind {
+            PreviewKind::Tx => {
+                let tx = match not_found_to_none(self.data_tx(hash).await)? {
+                    Some(tx) => tx,
+                    None => return Ok(None),
+                };
+                let token_ticker = tx.token.as_ref().map(|token| token.token_ticker.as_str());
+                tx_meta(&self.public_url, hash, tx.stats.sats_output, token_ticker)
+            }
+            PreviewKind::Block => {
+                let block_hash = Sha256d::from_hex_be(hash)?;
+                let block = match not_found_to_none(self.block_by_hash(&block_hash).await)? {
+                    Some(block) => block,
+                    None => return Ok(None),
+                };
+                let block_info = block
+                    .block_info
+                    .as_ref()
+                    .ok_or_else(|| eyre!("Block has no info"))?;
+                block_meta(&self.public_url, hash, block_info)
+            }
+            PreviewKind::Address => {
+                let address = self.parse_address(hash)?;
+                let (script_type, script_payload) = cash_addr_to_script_type_payload(&address);
+                let script_endpoint = self.chronik.script(script_type, &script_payload);
+                // With one tx per page, the number of pages is the number of txs
+                let num_txs = self
+                    .timed(
+                        "script_history",
+                        script_endpoint.history_with_page_size(0, 1),
+                    )
+                    .await?
+                    .num_pages;
+                address_meta(&self.public_url, hash, None, num_txs)
+            }
+        };
+
+        // Rasterizing takes a while, so it's kept off the async workers
+        let png = tokio::task::spawn_blocking(move || preview_png(&meta)).await??;
+        Ok(Some(self.preview_cache.insert(
+            &blockchain_info.tip_hash,
+            key,
+            png,
+        )))
     }
 
     pub async fn batch_get_chronik_tokens(
         &self,
         token_ids: HashSet<Sha256d>,
     ) -> Result<HashMap<String, Token>> {
-        let mut token_calls = Vec::new();
         let mut token_map = HashMap::new();
 
-        for token_id in token_ids.iter() {
-            token_calls.push(Box::pin(self.chronik.token(token_id)));
-        }
-
-        let tokens = future::try_join_all(token_calls).await?;
+        let tokens = self
+            .token_cache
+            .get_many(&self.chronik, &self.status, token_ids)
+            .await?;
         for token in tokens.into_iter() {
             if let Some(slp_tx_data) = &token.slp_tx_data {
                 if let Some(slp_meta) = &slp_tx_data.slp_meta {
@@ -444,17 +3206,46 @@ impl Server {
         Ok(token_map)
     }
 
-    pub async fn address_qr(&self, address: &str) -> Result<Vec<u8>> {
-        use qrcode_generator::QrCodeEcc;
-        if address.len() > 60 {
-            bail!("Invalid address length");
-        }
-        let png = qrcode_generator::to_png_to_vec(address, QrCodeEcc::Quartile, 160)?;
-        Ok(png)
+    /// Parses `?size=`, `?format=` and `?logo=` of an address QR code, returning them in that
+    /// order.
+    pub fn validate_qr_query(
+        &self,
+        address: &str,
+        query: &HashMap<String, String>,
+    ) -> Result<(usize, QrFormat, bool)> {
+        let size: usize = match query.get("size") {
+            Some(size) => size.parse()?,
+            None => qr_codes::DEFAULT_SIZE,
+        };
+        let format = QrFormat::parse(query.get("format").map(|s| s.as_str()).unwrap_or("png"))?;
+        let with_logo: bool = query
+            .get("logo")
+            .map(|s| s.as_str())
+            .unwrap_or("false")
+            .parse()?;
+        validate_qr_params(address, size, format, with_logo)?;
+        Ok((size, format, with_logo))
+    }
+
+    pub async fn address_qr(
+        &self,
+        address: &str,
+        size: usize,
+        format: QrFormat,
+        with_logo: bool,
+    ) -> Result<Vec<u8>> {
+        self.qr_codes
+            .get(&self.status, address, size, format, with_logo)
     }
 
     pub async fn block_height(&self, height: u32) -> Result<Redirect> {
-        let block = self.chronik.block_by_height(height as i32).await.ok();
+        let block = self
+            .timed(
+                "block_by_height",
+                self.chronik.block_by_height(height as i32),
+            )
+            .await
+            .ok();
 
         match block {
             Some(block) => {
@@ -466,16 +3257,28 @@ impl Server {
     }
 
     pub async fn search(&self, query: &str) -> Result<Redirect> {
-        if let Ok(address) = CashAddress::parse_cow(query.into()) {
+        if let Ok(address) = parse_address(query, self.network) {
             return Ok(self.redirect(format!("/address/{}", address.as_str())));
         }
+        if let Some((address, amount)) = parse_payment_uri(query) {
+            let address = parse_address(address, self.network)?;
+            let url = match amount.filter(|amount| parse_xec_amount(amount).is_some()) {
+                Some(amount) => format!("/address/{}?amount={}", address.as_str(), amount),
+                None => format!("/address/{}", address.as_str()),
+            };
+            return Ok(self.redirect(url));
+        }
         let bytes = from_be_hex(query)?;
         let unknown_hash = Sha256d::from_slice(&bytes)?;
 
-        if self.chronik.tx(&unknown_hash).await.is_ok() {
+        if self
+            .timed("tx", self.chronik.tx(&unknown_hash))
+            .await
+            .is_ok()
+        {
             return Ok(self.redirect(format!("/tx/{}", query)));
         }
-        if self.chronik.block_by_hash(&unknown_hash).await.is_ok() {
+        if self.block_by_hash(&unknown_hash).await.is_ok() {
             return Ok(self.redirect(format!("/block/{}", query)));
         }
 
@@ -486,3 +3289,60 @@ impl Server {
         Redirect::permanent(&url)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{page_of, parse_txs_paging, TOKEN_HISTORY_MAX_TXS};
+
+    fn query(params: &[(&str, &str)]) -> HashMap<String, String> {
+        params
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn txs_paging_defaults_and_bounds() {
+        assert_eq!(parse_txs_paging(&query(&[])).unwrap(), (0, 200));
+        assert_eq!(
+            parse_txs_paging(&query(&[("page", "3"), ("take", "25")])).unwrap(),
+            (3, 25)
+        );
+        for take in ["0", "201", "-1", "x"] {
+            assert!(parse_txs_paging(&query(&[("take", take)])).is_err());
+        }
+        // page * take would overflow
+        let huge_page = usize::MAX.to_string();
+        assert!(parse_txs_paging(&query(&[("page", &huge_page), ("take", "2")])).is_err());
+        assert!(parse_txs_paging(&query(&[("page", "99999999999999999999999")])).is_err());
+    }
+
+    #[test]
+    fn token_txs_paging_stays_within_scanned_txs() {
+        let token_id = "00".repeat(32);
+        let last_page = (TOKEN_HISTORY_MAX_TXS / 200 - 1).to_string();
+        let past_last_page = (TOKEN_HISTORY_MAX_TXS / 200).to_string();
+        assert!(parse_txs_paging(&query(&[("token_id", &token_id), ("page", &last_page)])).is_ok());
+        assert!(parse_txs_paging(&query(&[
+            ("token_id", &token_id),
+            ("page", &past_last_page)
+        ]))
+        .is_err());
+        // Without token_id, chronik pages through the whole history
+        assert!(parse_txs_paging(&query(&[("page", &past_last_page)])).is_ok());
+    }
+
+    #[test]
+    fn page_of_items() {
+        let items = (0..7).collect::<Vec<_>>();
+        assert_eq!(page_of(items.clone(), 0, 3), (vec![0, 1, 2], true));
+        assert_eq!(page_of(items.clone(), 1, 3), (vec![3, 4, 5], true));
+        assert_eq!(page_of(items.clone(), 2, 3), (vec![6], false));
+        assert_eq!(page_of(items.clone(), 3, 3), (vec![], false));
+        // Exactly full pages have no next page
+        assert_eq!(page_of(items.clone(), 0, 7), (items.clone(), false));
+        assert_eq!(page_of(items, usize::MAX, usize::MAX), (vec![], false));
+    }
+}
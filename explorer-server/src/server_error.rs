@@ -2,27 +2,138 @@ use askama::Template;
 use axum::{
     http::StatusCode,
     response::{Html, IntoResponse, Response},
+    Json,
 };
+use bitcoinsuite_chronik_client::ChronikClientError;
+use bitcoinsuite_error::Result;
+use eyre::Report;
+use serde::Serialize;
 
-use crate::templating::ErrorTemplate;
+use crate::{server_middleware::current_request_id, templating::ErrorTemplate};
 
 pub struct ServerError {
+    pub status: StatusCode,
     pub message: String,
 }
 
+/// Attached to error responses so the request middleware can report what went wrong.
+#[derive(Clone)]
+pub struct ErrorMessage(pub String);
+
 impl IntoResponse for ServerError {
     fn into_response(self) -> Response {
+        let error_message = ErrorMessage(self.message.clone());
         let error_template = ErrorTemplate {
             message: self.message,
+            request_id: current_request_id(),
         };
         let error_page = error_template.render().unwrap();
 
-        (StatusCode::INTERNAL_SERVER_ERROR, Html(error_page)).into_response()
+        let mut response = (self.status, Html(error_page)).into_response();
+        response.extensions_mut().insert(error_message);
+        response
     }
 }
 
 pub fn to_server_error<T: ToString>(err: T) -> ServerError {
     ServerError {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        message: err.to_string(),
+    }
+}
+
+/// For page requests with invalid parameters.
+pub fn to_server_bad_request<T: ToString>(err: T) -> ServerError {
+    ServerError {
+        status: StatusCode::BAD_REQUEST,
+        message: err.to_string(),
+    }
+}
+
+/// For page requests for things that don't exist.
+pub fn to_server_not_found<T: ToString>(err: T) -> ServerError {
+    ServerError {
+        status: StatusCode::NOT_FOUND,
+        message: err.to_string(),
+    }
+}
+
+/// For requests refused because the server is at capacity; clients may retry later.
+pub fn to_unavailable<T: ToString>(err: T) -> ServerError {
+    ServerError {
+        status: StatusCode::SERVICE_UNAVAILABLE,
         message: err.to_string(),
     }
 }
+
+/// Error returned by the JSON API, rendered as JSON instead of an error page.
+pub struct ApiError {
+    pub status: StatusCode,
+    pub message: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonError {
+    error: String,
+    request_id: Option<String>,
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let error_message = ErrorMessage(self.message.clone());
+        let json_error = JsonError {
+            error: self.message,
+            request_id: current_request_id(),
+        };
+
+        let mut response = (self.status, Json(json_error)).into_response();
+        response.extensions_mut().insert(error_message);
+        response
+    }
+}
+
+pub fn to_api_error<T: ToString>(err: T) -> ApiError {
+    ApiError {
+        status: StatusCode::INTERNAL_SERVER_ERROR,
+        message: err.to_string(),
+    }
+}
+
+/// For errors caused by the request itself, e.g. invalid parameters.
+pub fn to_bad_request<T: ToString>(err: T) -> ApiError {
+    ApiError {
+        status: StatusCode::BAD_REQUEST,
+        message: err.to_string(),
+    }
+}
+
+/// For well-formed requests for things that don't exist, e.g. an unknown block hash.
+pub fn to_not_found<T: ToString>(err: T) -> ApiError {
+    ApiError {
+        status: StatusCode::NOT_FOUND,
+        message: err.to_string(),
+    }
+}
+
+/// Status code chronik answered with, if the error is an error response from chronik.
+pub fn chronik_status_code(err: &Report) -> Option<StatusCode> {
+    match err.downcast_ref::<ChronikClientError>() {
+        Some(ChronikClientError::ChronikError { status_code, .. }) => Some(*status_code),
+        _ => None,
+    }
+}
+
+/// Whether chronik answered that what was looked up doesn't exist.
+pub fn is_chronik_not_found(err: &Report) -> bool {
+    chronik_status_code(err) == Some(StatusCode::NOT_FOUND)
+}
+
+/// Turns chronik's not found into `None`, so it can be told apart from failures.
+pub fn not_found_to_none<T>(result: Result<T>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(err) if is_chronik_not_found(&err) => Ok(None),
+        Err(err) => Err(err),
+    }
+}
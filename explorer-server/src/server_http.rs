@@ -1,54 +1,209 @@
 use crate::{
+    assets::IMMUTABLE_CACHE_CONTROL,
+    meta::PreviewKind,
+    openapi::ApiDoc,
+    preferences::Preferences,
     server::Server,
-    server_error::{to_server_error, ServerError},
-    server_primitives::{JsonBlocksResponse, JsonTxsResponse},
+    server_error::{
+        chronik_status_code, to_api_error, to_bad_request, to_not_found, to_server_bad_request,
+        to_server_error, to_server_not_found, to_unavailable, ApiError, ServerError,
+    },
+    server_middleware,
+    server_primitives::{
+        JsonAddressBalances, JsonAddressForms, JsonAddressValidation, JsonBlock, JsonBlockFullness,
+        JsonBlockIntervals, JsonBlocksResponse, JsonBroadcastTxRequest, JsonBroadcastTxResponse,
+        JsonBurned, JsonCountdowns, JsonCounterpartiesResponse, JsonHashrate, JsonHeaders,
+        JsonLargeTxsResponse, JsonMerkleProof, JsonOpReturnSearchResponse, JsonReadiness,
+        JsonRecentTokensResponse, JsonSignaling, JsonSupply, JsonTokenStats, JsonTokenTopTransfers,
+        JsonTx, JsonTxCountChart, JsonTxStatus, JsonTxsResponse, JsonUtxosResponse,
+        JsonVerifyMessageRequest, JsonVerifyMessageResponse,
+    },
 };
 use axum::{
-    extract::{Path, Query},
-    http::StatusCode,
-    response::{Html, IntoResponse, Redirect},
+    body::StreamBody,
+    extract::{
+        ws::{Message, WebSocketUpgrade},
+        Path, Query,
+    },
+    http::{HeaderMap, StatusCode},
+    middleware,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        Html, IntoResponse, Redirect, Response,
+    },
     routing::{get_service, MethodRouter},
     Extension, Json,
 };
-use futures::future::ready;
+use bitcoinsuite_core::Sha256d;
+use futures::{future::ready, Stream, StreamExt, TryStreamExt};
 use std::{collections::HashMap, sync::Arc};
 use tower_http::services::ServeDir;
+use utoipa::OpenApi;
 
-pub async fn homepage(server: Extension<Arc<Server>>) -> Result<Html<String>, ServerError> {
-    Ok(Html(server.homepage().await.map_err(to_server_error)?))
+pub async fn homepage(
+    preferences: Preferences,
+    server: Extension<Arc<Server>>,
+) -> Result<Html<String>, ServerError> {
+    Ok(Html(
+        server
+            .homepage(preferences.locale)
+            .await
+            .map_err(to_server_error)?,
+    ))
 }
 
 pub async fn blocks(server: Extension<Arc<Server>>) -> Result<Html<String>, ServerError> {
     Ok(Html(server.blocks().await.map_err(to_server_error)?))
 }
 
+pub async fn hashrate_chart(server: Extension<Arc<Server>>) -> Result<Html<String>, ServerError> {
+    Ok(Html(
+        server.hashrate_chart().await.map_err(to_server_error)?,
+    ))
+}
+
 pub async fn tx(
     Path(hash): Path<String>,
+    preferences: Preferences,
     server: Extension<Arc<Server>>,
 ) -> Result<Html<String>, ServerError> {
-    Ok(Html(server.tx(&hash).await.map_err(to_server_error)?))
+    Ok(Html(
+        server
+            .tx(&hash, preferences)
+            .await
+            .map_err(to_server_error)?,
+    ))
 }
 
 pub async fn block(
     Path(hash): Path<String>,
+    preferences: Preferences,
     server: Extension<Arc<Server>>,
 ) -> Result<Html<String>, ServerError> {
-    Ok(Html(server.block(&hash).await.map_err(to_server_error)?))
+    Ok(Html(
+        server
+            .block(&hash, preferences)
+            .await
+            .map_err(to_server_error)?,
+    ))
 }
 
 pub async fn address(
     Path(hash): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    preferences: Preferences,
+    server: Extension<Arc<Server>>,
+) -> Result<Html<String>, ServerError> {
+    Ok(Html(
+        server
+            .address(&hash, query, preferences)
+            .await
+            .map_err(to_server_error)?,
+    ))
+}
+
+pub async fn script(
+    Path(hex): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    preferences: Preferences,
     server: Extension<Arc<Server>>,
 ) -> Result<Html<String>, ServerError> {
-    Ok(Html(server.address(&hash).await.map_err(to_server_error)?))
+    Ok(Html(
+        server
+            .script(&hex, query, preferences)
+            .await
+            .map_err(to_server_error)?,
+    ))
 }
 
+/// `?size=` in pixels, `?format=png|svg` and `?logo=true` (SVG only).
 pub async fn address_qr(
     Path(hash): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
     server: Extension<Arc<Server>>,
 ) -> Result<impl IntoResponse, ServerError> {
-    let qr_code = server.address_qr(&hash).await.map_err(to_server_error)?;
-    Ok((StatusCode::OK, [("content-type", "image/png")], qr_code))
+    let (size, format, with_logo) = server
+        .validate_qr_query(&hash, &query)
+        .map_err(to_server_bad_request)?;
+    let qr_code = server
+        .address_qr(&hash, size, format, with_logo)
+        .await
+        .map_err(to_server_error)?;
+    Ok((
+        StatusCode::OK,
+        [("content-type", format.content_type())],
+        qr_code,
+    ))
+}
+
+pub async fn preview_image(
+    Path((kind, hash)): Path<(String, String)>,
+    server: Extension<Arc<Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let kind = PreviewKind::parse(&kind).map_err(to_server_bad_request)?;
+    let hash = server
+        .parse_preview_hash(kind, &hash)
+        .map_err(to_server_bad_request)?;
+    let png = server
+        .preview_image(kind, hash)
+        .await
+        .map_err(to_server_error)?
+        .ok_or_else(|| to_server_not_found("Not found"))?;
+    Ok((
+        StatusCode::OK,
+        [("content-type", "image/png")],
+        Vec::clone(&png),
+    ))
+}
+
+/// Never changes for a token ID, so browsers may keep it forever.
+pub async fn identicon(
+    Path(token_id): Path<String>,
+    server: Extension<Arc<Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let token_id = server
+        .parse_identicon_token_id(&token_id)
+        .map_err(to_server_bad_request)?;
+    let svg = server.identicon(&token_id);
+    Ok((
+        StatusCode::OK,
+        [
+            ("content-type", "image/svg+xml"),
+            ("cache-control", IMMUTABLE_CACHE_CONTROL),
+        ],
+        svg,
+    ))
+}
+
+pub async fn sitemap_index(
+    server: Extension<Arc<Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let sitemap = server.sitemap_index().await.map_err(to_server_error)?;
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        sitemap,
+    ))
+}
+
+pub async fn sitemap_blocks(
+    Path(chunk): Path<String>,
+    server: Extension<Arc<Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let chunk = chunk
+        .trim_end_matches(".xml")
+        .parse()
+        .map_err(|_| to_server_not_found("Sitemap not found"))?;
+    let sitemap = server
+        .sitemap_blocks(chunk)
+        .await
+        .map_err(to_server_error)?
+        .ok_or_else(|| to_server_not_found("Sitemap not found"))?;
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/xml")],
+        String::clone(&sitemap),
+    ))
 }
 
 pub async fn block_height(
@@ -65,43 +220,1004 @@ pub async fn search(
     server.search(&query).await.map_err(to_server_error)
 }
 
+/// `?format=ndjson` streams one tx per line instead of a page of JSON.
+fn wants_ndjson(query: &HashMap<String, String>) -> bool {
+    query.get("format").map(|s| s.as_str()) == Some("ndjson")
+}
+
+fn ndjson_response<S>(lines: S) -> Response
+where
+    S: Stream<Item = bitcoinsuite_error::Result<String>> + Send + 'static,
+{
+    // The status is already sent once streaming starts, so errors can only cut the body short
+    let body = StreamBody::new(lines.map_err(|err| err.to_string()));
+    ([("content-type", "application/x-ndjson")], body).into_response()
+}
+
+/// `?envelope=false` keeps the bare response shape, without pagination metadata.
+fn wants_envelope(query: &HashMap<String, String>) -> bool {
+    query.get("envelope").map(|s| s.as_str()) != Some("false")
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/blocks/{start_height}/{end_height}",
+    params(
+        ("start_height" = i32, Path, description = "Lowest block height, inclusive"),
+        ("end_height" = i32, Path, description = "Highest block height, inclusive"),
+        ("envelope" = Option<bool>, Query, description = "false leaves out the pagination metadata"),
+    ),
+    responses(
+        (status = 200, description = "Blocks in the range, highest first, at most 1000", body = JsonBlocksResponse),
+        (status = 400, description = "Negative or inverted range"),
+    ),
+)]
 pub async fn data_blocks(
     Path((start_height, end_height)): Path<(i32, i32)>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonBlocksResponse>, ApiError> {
+    server
+        .validate_block_range(start_height, end_height)
+        .map_err(to_bad_request)?;
+    let mut blocks = server
+        .data_blocks(start_height, end_height)
+        .await
+        .map_err(to_api_error)?;
+    if !wants_envelope(&query) {
+        blocks.pagination = None;
+    }
+    Ok(Json(blocks))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/headers/{start_height}/{count}",
+    params(
+        ("start_height" = i32, Path, description = "Height of the first header"),
+        ("count" = i32, Path, description = "Number of headers, at most 10"),
+    ),
+    responses(
+        (status = 200, description = "Raw block headers from start_height upwards", body = JsonHeaders),
+        (status = 400, description = "Negative height or count out of range"),
+    ),
+)]
+pub async fn data_headers(
+    Path((start_height, count)): Path<(i32, i32)>,
     server: Extension<Arc<Server>>,
-) -> Result<Json<JsonBlocksResponse>, ServerError> {
+) -> Result<Json<JsonHeaders>, ApiError> {
+    server
+        .validate_headers_range(start_height, count)
+        .map_err(to_bad_request)?;
     Ok(Json(
         server
-            .data_blocks(start_height, end_height)
+            .data_headers(start_height, count)
             .await
-            .map_err(to_server_error)?,
+            .map_err(to_api_error)?,
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/block/{hash}/transactions",
+    params(
+        ("hash" = String, Path, description = "Block hash, big-endian hex"),
+        ("page" = Option<usize>, Query, description = "Page number, in block order"),
+        ("take" = Option<usize>, Query, description = "Page size, defaults to 200, at most 1000"),
+        ("unit" = Option<String>, Query, description = "Preferred display unit: xec, sats or bcha"),
+        ("envelope" = Option<bool>, Query, description = "false leaves out the pagination metadata"),
+        ("format" = Option<String>, Query, description = "ndjson streams every tx, one per line, ignoring paging"),
+    ),
+    responses(
+        (status = 200, description = "A page of the transactions of the block", body = JsonTxsResponse),
+        (status = 400, description = "Malformed page or take out of range"),
+    ),
+)]
 pub async fn data_block_txs(
     Path(hash): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    preferences: Preferences,
     server: Extension<Arc<Server>>,
-) -> Result<Json<JsonTxsResponse>, ServerError> {
+) -> Result<Response, ApiError> {
+    if wants_ndjson(&query) {
+        let lines = Arc::clone(&server)
+            .block_txs_ndjson(&hash)
+            .await
+            .map_err(to_api_error)?;
+        return Ok(ndjson_response(lines));
+    }
+    server
+        .validate_block_txs_query(&query)
+        .map_err(to_bad_request)?;
+    let envelope = wants_envelope(&query);
+    let mut txs = server
+        .data_block_txs(&hash, query)
+        .await
+        .map_err(to_api_error)?;
+    txs.display_unit = preferences.unit.name();
+    if !envelope {
+        txs.pagination = None;
+    }
+    Ok(Json(txs).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/block-height/{height}",
+    params(
+        ("height" = i32, Path, description = "Height of the block in the main chain"),
+    ),
+    responses(
+        (status = 200, description = "The block at the given height", body = JsonBlock),
+        (status = 404, description = "No block at that height"),
+    ),
+)]
+pub async fn data_block_by_height(
+    Path(height): Path<i32>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonBlock>, ApiError> {
+    let block = server
+        .data_block_by_height(height)
+        .await
+        .map_err(to_api_error)?;
+    block
+        .map(Json)
+        .ok_or_else(|| to_not_found(format!("No block at height {}", height)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/block/{hash}/tx/{index}",
+    params(
+        ("hash" = String, Path, description = "Block hash, big-endian hex"),
+        ("index" = usize, Path, description = "Position of the tx in the block, 0 is the coinbase"),
+    ),
+    responses(
+        (status = 200, description = "The transaction at the given position", body = JsonTx),
+        (status = 400, description = "Malformed block hash"),
+        (status = 404, description = "Unknown block, or it has no tx at that position"),
+    ),
+)]
+pub async fn data_block_tx(
+    Path((hash, index)): Path<(String, usize)>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonTx>, ApiError> {
+    let block_hash = Sha256d::from_hex_be(&hash).map_err(to_bad_request)?;
+    let tx = server
+        .data_block_tx(&block_hash, index)
+        .await
+        .map_err(to_api_error)?;
+    tx.map(Json)
+        .ok_or_else(|| to_not_found(format!("No tx at position {} of block {}", index, hash)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tx/{hash}/status",
+    params(
+        ("hash" = String, Path, description = "Transaction ID, big-endian hex"),
+    ),
+    responses(
+        (status = 200, description = "Whether the tx is mined, and where", body = JsonTxStatus),
+        (status = 400, description = "Invalid txid"),
+        (status = 404, description = "Unknown tx"),
+    ),
+)]
+pub async fn data_tx_status(
+    Path(hash): Path<String>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonTxStatus>, ApiError> {
+    let tx_hash = Sha256d::from_hex_be(&hash).map_err(to_bad_request)?;
+    let status = server
+        .data_tx_status(&tx_hash)
+        .await
+        .map_err(to_api_error)?;
+    status
+        .map(Json)
+        .ok_or_else(|| to_not_found(format!("No tx {}", hash)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tx/{hash}/merkle-proof",
+    params(
+        ("hash" = String, Path, description = "Transaction ID, big-endian hex"),
+    ),
+    responses(
+        (status = 200, description = "Block header and merkle branch of a mined transaction", body = JsonMerkleProof),
+    ),
+)]
+pub async fn data_merkle_proof(
+    Path(hash): Path<String>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonMerkleProof>, ApiError> {
     Ok(Json(
         server
-            .data_block_txs(&hash)
+            .data_merkle_proof(&hash)
             .await
-            .map_err(to_server_error)?,
+            .map_err(to_api_error)?,
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{hash}/transactions",
+    params(
+        ("hash" = String, Path, description = "Cash address"),
+        ("page" = Option<usize>, Query, description = "Page number, newest first"),
+        ("take" = Option<usize>, Query, description = "Page size, defaults to and at most 200"),
+        ("cursor" = Option<String>, Query, description = "Resume after a previous nextCursor"),
+        ("unit" = Option<String>, Query, description = "Preferred display unit: xec, sats or bcha"),
+        ("envelope" = Option<bool>, Query, description = "false leaves out the pagination metadata"),
+        ("format" = Option<String>, Query, description = "ndjson streams the newest txs, one per line, ignoring paging"),
+        ("limit" = Option<usize>, Query, description = "With format=ndjson, the number of txs to stream, defaults to and at most 10,000"),
+        ("token_id" = Option<String>, Query, description = "Only txs of this token, among the 2000 most recent txs; offset pages only"),
+    ),
+    responses(
+        (status = 200, description = "A page of the address history", body = JsonTxsResponse),
+        (status = 400, description = "Invalid cursor, token_id, page, take or NDJSON limit"),
+    ),
+)]
 pub async fn data_address_txs(
     Path(hash): Path<String>,
     Query(query): Query<HashMap<String, String>>,
+    preferences: Preferences,
     server: Extension<Arc<Server>>,
-) -> Result<Json<JsonTxsResponse>, ServerError> {
+) -> Result<Response, ApiError> {
+    server.validate_txs_query(&query).map_err(to_bad_request)?;
+    if wants_ndjson(&query) {
+        server.parse_address(&hash).map_err(to_bad_request)?;
+        let limit = server.parse_ndjson_limit(&query).map_err(to_bad_request)?;
+        let lines = Arc::clone(&server).address_txs_ndjson(hash, None, limit, false);
+        return Ok(ndjson_response(lines));
+    }
+    let envelope = wants_envelope(&query);
+    let mut txs = server
+        .data_address_txs(&hash, query)
+        .await
+        .map_err(to_api_error)?;
+    txs.display_unit = preferences.unit.name();
+    if !envelope {
+        txs.pagination = None;
+    }
+    Ok(Json(txs).into_response())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{hash}/balances",
+    params(
+        ("hash" = String, Path, description = "Cash address"),
+        ("show_spam" = Option<bool>, Query, description = "Include tokens filtered as spam"),
+    ),
+    responses(
+        (status = 200, description = "XEC and token balances with their UTXOs", body = JsonAddressBalances),
+    ),
+)]
+pub async fn data_address_balances(
+    Path(hash): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonAddressBalances>, ApiError> {
     Ok(Json(
         server
-            .data_address_txs(&hash, query)
+            .data_address_balances(&hash, query)
             .await
-            .map_err(to_server_error)?,
+            .map_err(to_api_error)?,
     ))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{hash}/utxos",
+    params(
+        ("hash" = String, Path, description = "Cash address"),
+        ("token_id" = Option<String>, Query, description = "Token ID in hex; plain XEC UTXOs if left out"),
+        ("page" = Option<usize>, Query, description = "Page number"),
+        ("take" = Option<usize>, Query, description = "Page size, defaults to and at most 1000"),
+        ("envelope" = Option<bool>, Query, description = "false leaves out the pagination metadata"),
+    ),
+    responses(
+        (status = 200, description = "A page of the UTXOs of one balance of the address", body = JsonUtxosResponse),
+        (status = 400, description = "Invalid address, token ID, page or take"),
+    ),
+)]
+pub async fn data_address_utxos(
+    Path(hash): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonUtxosResponse>, ApiError> {
+    let address = server.parse_address(&hash).map_err(to_bad_request)?;
+    let utxos_query = server
+        .validate_utxos_query(&query)
+        .map_err(to_bad_request)?;
+    let mut utxos = server
+        .data_address_utxos(&address, &utxos_query)
+        .await
+        .map_err(to_api_error)?;
+    if !wants_envelope(&query) {
+        utxos.pagination = None;
+    }
+    Ok(Json(utxos))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/{hash}/counterparties",
+    params(
+        ("hash" = String, Path, description = "Cash address"),
+        ("limit" = Option<usize>, Query, description = "Number of counterparties, defaults to 50, at most 1000"),
+    ),
+    responses(
+        (status = 200, description = "Addresses most frequently transacted with, based on the most recent 2000 txs", body = JsonCounterpartiesResponse),
+        (status = 400, description = "Invalid address or limit"),
+    ),
+)]
+pub async fn data_address_counterparties(
+    Path(hash): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonCounterpartiesResponse>, ApiError> {
+    let address = server.parse_address(&hash).map_err(to_bad_request)?;
+    let limit = server
+        .parse_counterparties_limit(&query)
+        .map_err(to_bad_request)?;
+    Ok(Json(
+        server
+            .data_address_counterparties(&address, limit)
+            .await
+            .map_err(to_api_error)?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/address/convert/{hash}",
+    params(
+        ("hash" = String, Path, description = "Cash address, with either prefix"),
+    ),
+    responses(
+        (status = 200, description = "The address in all of its encodings", body = JsonAddressForms),
+    ),
+)]
+pub async fn data_address_forms(
+    Path(hash): Path<String>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonAddressForms>, ApiError> {
+    Ok(Json(
+        server
+            .data_address_forms(&hash)
+            .await
+            .map_err(to_api_error)?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/validate/{hash}",
+    params(
+        ("hash" = String, Path, description = "Address to validate, including its prefix"),
+    ),
+    responses(
+        (status = 200, description = "Whether the address parses, with its network and normalized forms", body = JsonAddressValidation),
+    ),
+)]
+pub async fn data_validate_address(
+    Path(hash): Path<String>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonAddressValidation>, ApiError> {
+    Ok(Json(
+        server
+            .data_validate_address(&hash)
+            .await
+            .map_err(to_api_error)?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/script/{hex}/transactions",
+    params(
+        ("hex" = String, Path, description = "Output script in hex, e.g. a P2PK or nonstandard script"),
+        ("page" = Option<usize>, Query, description = "Page number, newest first"),
+        ("take" = Option<usize>, Query, description = "Page size, defaults to and at most 200"),
+        ("cursor" = Option<String>, Query, description = "Resume after a previous nextCursor"),
+        ("unit" = Option<String>, Query, description = "Preferred display unit: xec, sats or bcha"),
+        ("envelope" = Option<bool>, Query, description = "false leaves out the pagination metadata"),
+        ("token_id" = Option<String>, Query, description = "Only txs of this token, among the 2000 most recent txs; offset pages only"),
+    ),
+    responses(
+        (status = 200, description = "A page of the script history", body = JsonTxsResponse),
+        (status = 400, description = "Invalid cursor, token_id, page or take"),
+    ),
+)]
+pub async fn data_script_txs(
+    Path(hex): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    preferences: Preferences,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonTxsResponse>, ApiError> {
+    server.validate_txs_query(&query).map_err(to_bad_request)?;
+    let envelope = wants_envelope(&query);
+    let mut txs = server
+        .data_script_txs(&hex, query)
+        .await
+        .map_err(to_api_error)?;
+    txs.display_unit = preferences.unit.name();
+    if !envelope {
+        txs.pagination = None;
+    }
+    Ok(Json(txs))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/script/{hex}/balances",
+    params(
+        ("hex" = String, Path, description = "Output script in hex, e.g. a P2PK or nonstandard script"),
+        ("show_spam" = Option<bool>, Query, description = "Include tokens filtered as spam"),
+    ),
+    responses(
+        (status = 200, description = "XEC and token balances with their UTXOs", body = JsonAddressBalances),
+    ),
+)]
+pub async fn data_script_balances(
+    Path(hex): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonAddressBalances>, ApiError> {
+    Ok(Json(
+        server
+            .data_script_balances(&hex, query)
+            .await
+            .map_err(to_api_error)?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/script/{hex}/utxos",
+    params(
+        ("hex" = String, Path, description = "Output script in hex, e.g. a P2PK or nonstandard script"),
+        ("token_id" = Option<String>, Query, description = "Token ID in hex; plain XEC UTXOs if left out"),
+        ("page" = Option<usize>, Query, description = "Page number"),
+        ("take" = Option<usize>, Query, description = "Page size, defaults to and at most 1000"),
+        ("envelope" = Option<bool>, Query, description = "false leaves out the pagination metadata"),
+    ),
+    responses(
+        (status = 200, description = "A page of the UTXOs of one balance of the script", body = JsonUtxosResponse),
+        (status = 400, description = "Invalid script hex, token ID, page or take"),
+    ),
+)]
+pub async fn data_script_utxos(
+    Path(hex): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonUtxosResponse>, ApiError> {
+    let output_script = hex::decode(&hex).map_err(to_bad_request)?;
+    let utxos_query = server
+        .validate_utxos_query(&query)
+        .map_err(to_bad_request)?;
+    let mut utxos = server
+        .data_script_utxos(&output_script, &utxos_query)
+        .await
+        .map_err(to_api_error)?;
+    if !wants_envelope(&query) {
+        utxos.pagination = None;
+    }
+    Ok(Json(utxos))
+}
+
+#[cfg(feature = "graphql")]
+pub async fn graphql(
+    server: Extension<Arc<Server>>,
+    request: async_graphql_axum::GraphQLRequest,
+) -> async_graphql_axum::GraphQLResponse {
+    let request = request.into_inner().data(Arc::clone(&server));
+    server.graphql_schema.execute(request).await.into()
+}
+
+#[cfg(feature = "graphql")]
+pub async fn graphql_playground() -> Html<String> {
+    use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
+    Html(playground_source(GraphQLPlaygroundConfig::new("/graphql")))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/supply",
+    responses(
+        (status = 200, description = "Coins issued so far minus provably burned coins", body = JsonSupply),
+        (status = 503, description = "Not served by instances running without the indexer"),
+    ),
+)]
+pub async fn data_supply(server: Extension<Arc<Server>>) -> Result<Json<JsonSupply>, ApiError> {
+    Ok(Json(server.data_supply().await.map_err(to_api_error)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/burned",
+    responses(
+        (status = 200, description = "Sats sent to OP_RETURN outputs and known burn addresses", body = JsonBurned),
+        (status = 503, description = "Not served by instances running without the indexer"),
+    ),
+)]
+pub async fn data_burned(server: Extension<Arc<Server>>) -> Result<Json<JsonBurned>, ApiError> {
+    Ok(Json(server.data_burned().await.map_err(to_api_error)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/hashrate",
+    params(
+        ("window" = Option<i32>, Query, description = "Blocks per estimate, defaults to 144, at most 2016"),
+        ("points" = Option<i32>, Query, description = "Number of consecutive windows to chart, defaults to 30, at most 100 and 2016 blocks in total"),
+    ),
+    responses(
+        (status = 200, description = "Estimated network hashrate", body = JsonHashrate),
+        (status = 400, description = "Window or number of points out of range"),
+    ),
+)]
+pub async fn data_hashrate(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonHashrate>, ApiError> {
+    let (window, num_points) = server
+        .validate_hashrate_query(&query)
+        .map_err(to_bad_request)?;
+    let hashrate = server
+        .data_hashrate(window, num_points)
+        .await
+        .map_err(to_api_error)?;
+    Ok(Json(JsonHashrate::clone(&hashrate)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/countdowns",
+    responses(
+        (status = 200, description = "Blocks and ETA until the next halving", body = JsonCountdowns),
+    ),
+)]
+pub async fn data_countdowns(
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonCountdowns>, ApiError> {
+    Ok(Json(server.data_countdowns().await.map_err(to_api_error)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/block-intervals",
+    params(
+        ("window" = Option<i32>, Query, description = "Number of most recent block intervals, defaults to 144, at most 2016"),
+    ),
+    responses(
+        (status = 200, description = "Average and median time between recent blocks, and the time since the last one", body = JsonBlockIntervals),
+        (status = 400, description = "Window out of range"),
+    ),
+)]
+pub async fn data_block_intervals(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonBlockIntervals>, ApiError> {
+    let window = server
+        .parse_block_intervals_window(&query)
+        .map_err(to_bad_request)?;
+    Ok(Json(
+        server
+            .data_block_intervals(window)
+            .await
+            .map_err(to_api_error)?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/charts/block-fullness",
+    params(
+        ("window" = Option<i32>, Query, description = "Blocks per point, defaults to 144, at most 2016"),
+        ("points" = Option<i32>, Query, description = "Number of points, defaults to 30, at most 100 and 2016 blocks in total"),
+    ),
+    responses(
+        (status = 200, description = "Block sizes as a percentage of the max block size, oldest first", body = JsonBlockFullness),
+        (status = 400, description = "Window or number of points out of range"),
+    ),
+)]
+pub async fn data_block_fullness(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonBlockFullness>, ApiError> {
+    let (window, num_points) = server
+        .validate_block_fullness_query(&query)
+        .map_err(to_bad_request)?;
+    let fullness = server
+        .data_block_fullness(window, num_points)
+        .await
+        .map_err(to_api_error)?;
+    Ok(Json(JsonBlockFullness::clone(&fullness)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/charts/tx-count",
+    params(
+        ("from" = Option<i64>, Query, description = "Unix timestamp, defaults to 30 days before to"),
+        ("to" = Option<i64>, Query, description = "Unix timestamp, exclusive, defaults to the end of the current UTC day; at most 90 days after from"),
+    ),
+    responses(
+        (status = 200, description = "Number of confirmed txs and blocks per UTC day", body = JsonTxCountChart),
+        (status = 400, description = "Invalid or too long time range"),
+    ),
+)]
+pub async fn data_tx_count_chart(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonTxCountChart>, ApiError> {
+    let (from, to) = server
+        .validate_tx_count_query(&query)
+        .map_err(to_bad_request)?;
+    let chart = server
+        .data_tx_count_chart(from, to)
+        .await
+        .map_err(to_api_error)?;
+    Ok(Json(JsonTxCountChart::clone(&chart)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/stats/signaling",
+    params(
+        ("window" = Option<usize>, Query, description = "Number of most recent blocks to count over, defaults to 1008, at most 4032"),
+    ),
+    responses(
+        (status = 200, description = "How many blocks of the window signal each version bit", body = JsonSignaling),
+        (status = 400, description = "Invalid window"),
+        (status = 503, description = "Not served by instances running without the indexer"),
+    ),
+)]
+pub async fn data_signaling(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonSignaling>, ApiError> {
+    Ok(Json(
+        server.data_signaling(query).await.map_err(to_bad_request)?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/feed/large-txs",
+    params(
+        ("min_value" = Option<i64>, Query, description = "Minimum sats output, defaults to 1M XEC, at least 100k XEC"),
+        ("limit" = Option<usize>, Query, description = "Max number of txs, defaults to 50, at most 200"),
+    ),
+    responses(
+        (status = 200, description = "Recently confirmed large transactions, newest first", body = JsonLargeTxsResponse),
+        (status = 503, description = "Not served by instances running without the indexer"),
+    ),
+)]
+pub async fn data_large_txs(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonLargeTxsResponse>, ApiError> {
+    Ok(Json(
+        server.data_large_txs(query).await.map_err(to_api_error)?,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/token/{hex}/stats",
+    params(
+        ("hex" = String, Path, description = "Token ID, big-endian hex"),
+        ("window" = Option<i64>, Query, description = "Number of days, defaults to 30, at most 30"),
+    ),
+    responses(
+        (status = 200, description = "Daily transfer volume (excluding change) and tx count of the token, oldest first", body = JsonTokenStats),
+        (status = 400, description = "Invalid token ID or window"),
+        (status = 503, description = "Not served by instances running without the indexer"),
+    ),
+)]
+pub async fn data_token_stats(
+    Path(hex): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonTokenStats>, ApiError> {
+    let token_id = Sha256d::from_hex_be(&hex).map_err(to_bad_request)?;
+    let window = server
+        .parse_token_stats_window(&query)
+        .map_err(to_bad_request)?;
+    Ok(Json(server.data_token_stats(&token_id, window)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/token/{hex}/top-transfers",
+    params(
+        ("hex" = String, Path, description = "Token ID, big-endian hex"),
+        ("window" = Option<i64>, Query, description = "Number of days, defaults to 30, at most 30"),
+        ("limit" = Option<usize>, Query, description = "Number of transfers, defaults to 20, at most 50"),
+    ),
+    responses(
+        (status = 200, description = "Largest confirmed transfers of the token, largest first", body = JsonTokenTopTransfers),
+        (status = 400, description = "Invalid token ID, window or limit"),
+        (status = 503, description = "Not served by instances running without the indexer"),
+    ),
+)]
+pub async fn data_token_top_transfers(
+    Path(hex): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonTokenTopTransfers>, ApiError> {
+    let token_id = Sha256d::from_hex_be(&hex).map_err(to_bad_request)?;
+    let window = server
+        .parse_token_stats_window(&query)
+        .map_err(to_bad_request)?;
+    let limit = server
+        .parse_top_transfers_limit(&query)
+        .map_err(to_bad_request)?;
+    Ok(Json(
+        server.data_token_top_transfers(&token_id, window, limit),
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tokens/recent",
+    params(
+        ("limit" = Option<usize>, Query, description = "Max number of tokens, defaults to 50, at most 200"),
+        ("show_spam" = Option<bool>, Query, description = "Include tokens the token filter considers spam"),
+    ),
+    responses(
+        (status = 200, description = "Tokens created by recently confirmed GENESIS txs, newest first", body = JsonRecentTokensResponse),
+        (status = 400, description = "Invalid limit"),
+        (status = 503, description = "Not served by instances running without the indexer"),
+    ),
+)]
+pub async fn data_recent_tokens(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonRecentTokensResponse>, ApiError> {
+    let limit = server
+        .parse_recent_tokens_limit(&query)
+        .map_err(to_bad_request)?;
+    let show_spam = query.get("show_spam").map(|s| s.as_str()) == Some("true");
+    Ok(Json(server.data_recent_tokens(limit, show_spam)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/search/opreturn",
+    params(
+        ("q" = String, Query, description = "Text to look for in OP_RETURN messages, case-insensitive, at least 2 characters"),
+        ("protocol" = Option<String>, Query, description = "Only messages of this protocol: cashtab, memo or other"),
+        ("page" = Option<usize>, Query, description = "Page number, newest first"),
+        ("take" = Option<usize>, Query, description = "Page size, defaults to 50, at most 200"),
+        ("envelope" = Option<bool>, Query, description = "false leaves out the pagination metadata"),
+    ),
+    responses(
+        (status = 200, description = "Matching OP_RETURN messages of the last week of blocks, newest first", body = JsonOpReturnSearchResponse),
+        (status = 400, description = "Query too short, unknown protocol or invalid paging"),
+        (status = 503, description = "Not served by instances running without the indexer"),
+    ),
+)]
+pub async fn data_search_op_return(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonOpReturnSearchResponse>, ApiError> {
+    let envelope = wants_envelope(&query);
+    let mut response = server
+        .data_search_op_return(query)
+        .await
+        .map_err(to_bad_request)?;
+    if !envelope {
+        response.pagination = None;
+    }
+    Ok(Json(response))
+}
+
+pub async fn large_txs_rss(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<impl IntoResponse, ServerError> {
+    let rss = server.large_txs_rss(query).await.map_err(to_server_error)?;
+    Ok((
+        StatusCode::OK,
+        [("content-type", "application/rss+xml; charset=utf-8")],
+        rss,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/export/address/{hash}",
+    params(
+        ("hash" = String, Path, description = "Cash address"),
+        ("cursor" = Option<String>, Query, description = "nextCursor from the trailer of the previous page"),
+        ("x-api-key" = String, Header, description = "API key from the server config"),
+    ),
+    responses(
+        (status = 200, description = "A page of up to 10000 txs of the address history as NDJSON, newest first, gzip-compressed if accepted; the last line is a JsonNdjsonTrailer, {\"trailer\":{\"nextCursor\":...}}, with a null cursor once the history is complete"),
+        (status = 400, description = "Invalid address or cursor"),
+        (status = 401, description = "Missing or unknown API key"),
+        (status = 429, description = "API key rate limit exceeded"),
+    ),
+)]
+pub async fn export_address_txs(
+    Path(hash): Path<String>,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Response, ApiError> {
+    let cursor = query.get("cursor").cloned();
+    server
+        .validate_history_export(&hash, cursor.as_deref())
+        .map_err(to_bad_request)?;
+    let lines = Arc::clone(&server).address_txs_export(hash, cursor);
+    Ok(ndjson_response(lines))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/broadcast-tx",
+    request_body = JsonBroadcastTxRequest,
+    params(
+        ("x-api-key" = String, Header, description = "API key from the server config"),
+    ),
+    responses(
+        (status = 200, description = "Tx accepted by the node", body = JsonBroadcastTxResponse),
+        (status = 400, description = "Malformed or rejected tx"),
+        (status = 401, description = "Missing or unknown API key"),
+        (status = 429, description = "API key rate limit exceeded"),
+        (status = 502, description = "Chronik failed to broadcast the tx"),
+    ),
+)]
+pub async fn broadcast_tx(
+    Json(request): Json<JsonBroadcastTxRequest>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonBroadcastTxResponse>, ApiError> {
+    let raw_tx = hex::decode(&request.raw_tx).map_err(to_bad_request)?;
+    let response = server.broadcast_tx(raw_tx).await.map_err(|err| {
+        // Chronik answers 400 when the node rejects the tx; anything else is on our side
+        let status = match chronik_status_code(&err) {
+            Some(StatusCode::BAD_REQUEST) => StatusCode::BAD_REQUEST,
+            Some(_) => StatusCode::BAD_GATEWAY,
+            None => StatusCode::INTERNAL_SERVER_ERROR,
+        };
+        ApiError {
+            status,
+            message: err.to_string(),
+        }
+    })?;
+    Ok(Json(response))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/verify-message",
+    request_body = JsonVerifyMessageRequest,
+    responses(
+        (status = 200, description = "Whether the signature was made by the address", body = JsonVerifyMessageResponse),
+        (status = 400, description = "Malformed address or signature"),
+    ),
+)]
+pub async fn verify_message(
+    Json(request): Json<JsonVerifyMessageRequest>,
+    server: Extension<Arc<Server>>,
+) -> Result<Json<JsonVerifyMessageResponse>, ApiError> {
+    Ok(Json(server.verify_message(&request).await.map_err(
+        |err| ApiError {
+            status: StatusCode::BAD_REQUEST,
+            message: err.to_string(),
+        },
+    )?))
+}
+
+pub async fn admin(
+    headers: HeaderMap,
+    server: Extension<Arc<Server>>,
+) -> Result<Response, ServerError> {
+    if !server.is_admin_enabled() {
+        return Ok(StatusCode::NOT_FOUND.into_response());
+    }
+    if !server.is_admin_authorized(&headers) {
+        return Ok((
+            StatusCode::UNAUTHORIZED,
+            [("www-authenticate", "Basic realm=\"explorer admin\"")],
+        )
+            .into_response());
+    }
+    Ok(Html(server.admin().await.map_err(to_server_error)?).into_response())
+}
+
+/// Request, cache and chronik stats for Prometheus to scrape.
+pub async fn metrics(server: Extension<Arc<Server>>) -> impl IntoResponse {
+    (
+        StatusCode::OK,
+        [("content-type", "text/plain; version=0.0.4")],
+        server.status().prometheus_metrics(),
+    )
+}
+
+/// 200 while chronik's tip advances and the block feeds keep up with it, 503 otherwise.
+pub async fn ready(server: Extension<Arc<Server>>) -> (StatusCode, Json<JsonReadiness>) {
+    let problems = server.readiness_problems();
+    let status = match problems.is_empty() {
+        true => StatusCode::OK,
+        false => StatusCode::SERVICE_UNAVAILABLE,
+    };
+    (
+        status,
+        Json(JsonReadiness {
+            is_ready: problems.is_empty(),
+            problems,
+        }),
+    )
+}
+
+/// Server-sent events of new blocks, plus the mempool txs of `?address=` or the confirmation
+/// of `?tx=` if given.
+/// Invalid params are refused with 400, `?address=` subscribers with 503 once chronik
+/// connections for them run out.
+pub async fn events(
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, ServerError> {
+    let address = query.get("address").map(|s| s.as_str());
+    let txid = query.get("tx").map(|s| s.as_str());
+    server
+        .validate_chain_events_query(address, txid)
+        .map_err(to_server_bad_request)?;
+    let subscription = server
+        .reserve_address_subscription(address)
+        .map_err(to_unavailable)?;
+    let events = server
+        .chain_events(address, txid, subscription)
+        .await
+        .map_err(to_server_error)?;
+    let events = events.map(|event| Event::default().json_data(event));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// Same events as [`events`], as JSON text messages over a WebSocket.
+pub async fn ws(
+    upgrade: WebSocketUpgrade,
+    Query(query): Query<HashMap<String, String>>,
+    server: Extension<Arc<Server>>,
+) -> Result<Response, ServerError> {
+    let address = query.get("address").map(|s| s.as_str());
+    let txid = query.get("tx").map(|s| s.as_str());
+    server
+        .validate_chain_events_query(address, txid)
+        .map_err(to_server_bad_request)?;
+    let subscription = server
+        .reserve_address_subscription(address)
+        .map_err(to_unavailable)?;
+    let mut events = server
+        .chain_events(address, txid, subscription)
+        .await
+        .map_err(to_server_error)?;
+    Ok(upgrade.on_upgrade(|mut socket| async move {
+        while let Some(event) = events.next().await {
+            let json = serde_json::to_string(&event).expect("Events always serialize");
+            if socket.send(Message::Text(json)).await.is_err() {
+                return;
+            }
+        }
+        let _ = socket.send(Message::Close(None)).await;
+    }))
+}
+
+pub async fn openapi_spec() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+pub async fn api_docs(server: Extension<Arc<Server>>) -> Result<Html<String>, ServerError> {
+    Ok(Html(server.api_docs().await.map_err(to_server_error)?))
+}
+
 pub fn serve_files(path: &std::path::Path) -> MethodRouter {
-    get_service(ServeDir::new(path)).handle_error(|_| ready(StatusCode::INTERNAL_SERVER_ERROR))
+    get_service(ServeDir::new(path))
+        .handle_error(|_| ready(StatusCode::INTERNAL_SERVER_ERROR))
+        .layer(middleware::from_fn(server_middleware::cache_control))
 }
@@ -0,0 +1,327 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use axum::{
+    extract::{MatchedPath, OriginalUri},
+    http::{header, HeaderMap, HeaderValue, Method, Request, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use bitcoinsuite_error::Result;
+use chrono::Utc;
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::{info, Instrument};
+
+use crate::{
+    assets::{is_current_hash, DEFAULT_CACHE_CONTROL, IMMUTABLE_CACHE_CONTROL},
+    auth::ApiKeyCheck,
+    config::CorsConfig,
+    preferences::Preferences,
+    server::Server,
+    server_error::{ApiError, ErrorMessage},
+    status::RecentError,
+    translations,
+};
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+static NEXT_REQUEST_ID: AtomicU64 = AtomicU64::new(0);
+
+/// ID of the request currently being handled, if called from within a request.
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|request_id| request_id.clone()).ok()
+}
+
+fn generate_request_id() -> String {
+    let millis = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis())
+        .unwrap_or_default();
+    let counter = NEXT_REQUEST_ID.fetch_add(1, Ordering::Relaxed);
+    format!("{:x}-{:x}", millis, counter)
+}
+
+fn incoming_request_id(headers: &HeaderMap) -> Option<String> {
+    let request_id = headers.get("x-request-id")?.to_str().ok()?;
+    let is_sane = !request_id.is_empty()
+        && request_id.len() <= 64
+        && request_id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    is_sane.then(|| request_id.to_string())
+}
+
+/// Assigns every request an ID (reusing a sane incoming `X-Request-Id`), makes it available to
+/// error rendering via [`current_request_id`], echoes it back and writes an access log line.
+/// Server errors are also recorded in the status registry for the admin dashboard.
+pub async fn request_id<B>(req: Request<B>, next: Next<B>) -> Response {
+    let request_id = incoming_request_id(req.headers()).unwrap_or_else(generate_request_id);
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    // Only set once routed to an endpoint; anything else (404s, static files) shares one entry
+    let route = req
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|matched_path| matched_path.as_str().to_string())
+        .unwrap_or_else(|| "other".to_string());
+    let server = req.extensions().get::<Arc<Server>>().cloned();
+    let started = Instant::now();
+
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), next.run(req).instrument(span))
+        .await;
+
+    if let Some(server) = &server {
+        let status = server.status();
+        status.record_request();
+        status.record_route_latency(
+            &route,
+            started.elapsed(),
+            response.status().is_server_error(),
+        );
+        if response.status().is_server_error() {
+            let message = response
+                .extensions()
+                .get::<ErrorMessage>()
+                .map(|ErrorMessage(message)| message.clone())
+                .unwrap_or_default();
+            status.record_error(RecentError {
+                timestamp: Utc::now(),
+                request_id: request_id.clone(),
+                method: method.to_string(),
+                path: path.clone(),
+                status: response.status().as_u16(),
+                message,
+            });
+        }
+    }
+
+    let status = response.status().as_u16();
+    info!(
+        target: "access",
+        request_id = %request_id,
+        method = %method,
+        path = %path,
+        status,
+        duration_ms = started.elapsed().as_secs_f64() * 1000.0,
+        "{} {} {}",
+        method,
+        path,
+        status,
+    );
+    if let Ok(request_id) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert("x-request-id", request_id);
+    }
+    response
+}
+
+/// Negotiates the visitor's language and makes its message catalog available to templates.
+pub async fn language<B>(req: Request<B>, next: Next<B>) -> Response {
+    let preferences = Preferences::from_request_parts(req.uri().query(), req.headers());
+    let catalog = req
+        .extensions()
+        .get::<Arc<Server>>()
+        .and_then(|server| server.translations().catalog(preferences.locale))
+        .unwrap_or_default();
+
+    translations::with_catalog(catalog, next.run(req)).await
+}
+
+/// Current version of the JSON API, served under `/api/v{API_VERSION}`.
+///
+/// Unversioned `/api/...` paths stay pinned to v1 so existing consumers keep working; any
+/// breaking change to the JSON shapes must bump this and be mounted alongside the old version.
+pub const API_VERSION: u32 = 1;
+
+const VENDOR_MEDIA_TYPE_PREFIX: &str = "application/vnd.becash.explorer.v";
+
+fn requested_api_version(headers: &HeaderMap) -> Option<u32> {
+    let accept = headers.get(header::ACCEPT)?.to_str().ok()?;
+    accept.split(',').find_map(|media_type| {
+        let media_type = media_type.split(';').next()?.trim();
+        media_type
+            .strip_prefix(VENDOR_MEDIA_TYPE_PREFIX)?
+            .strip_suffix("+json")?
+            .parse()
+            .ok()
+    })
+}
+
+/// Rejects requests asking for an API version via `Accept` that this server doesn't serve, and
+/// tags every response with the version that produced it.
+pub async fn api_version<B>(req: Request<B>, next: Next<B>) -> Response {
+    if let Some(requested_version) = requested_api_version(req.headers()) {
+        if requested_version != API_VERSION {
+            let message = format!(
+                "API version {} is not supported, this server provides version {}",
+                requested_version, API_VERSION,
+            );
+            return (StatusCode::NOT_ACCEPTABLE, message).into_response();
+        }
+    }
+
+    let mut response = next.run(req).await;
+    response
+        .headers_mut()
+        .insert("x-api-version", HeaderValue::from(API_VERSION));
+    response
+}
+
+/// Static files requested through a fingerprinted URL (see [`asset_url`]) can be cached
+/// forever, since a changed file gets a new URL; anything else only briefly. That includes
+/// URLs with a stale or made-up hash, which would otherwise pin whatever is served now.
+///
+/// [`asset_url`]: crate::assets::asset_url
+pub async fn cache_control<B>(req: Request<B>, next: Next<B>) -> Response {
+    // Files are served from nested routers, which only see the path below their prefix
+    let path = match req.extensions().get::<OriginalUri>() {
+        Some(original_uri) => original_uri.path(),
+        None => req.uri().path(),
+    };
+    let is_fingerprinted = req
+        .uri()
+        .query()
+        .map(|query| {
+            query
+                .split('&')
+                .filter_map(|pair| pair.strip_prefix("hash="))
+                .any(|hash| is_current_hash(path, hash))
+        })
+        .unwrap_or(false);
+
+    let mut response = next.run(req).await;
+    if response.status().is_success() {
+        let cache_control = match is_fingerprinted {
+            true => IMMUTABLE_CACHE_CONTROL,
+            false => DEFAULT_CACHE_CONTROL,
+        };
+        response.headers_mut().insert(
+            header::CACHE_CONTROL,
+            HeaderValue::from_static(cache_control),
+        );
+    }
+    response
+}
+
+/// Marks responses from the unversioned compatibility paths as deprecated, pointing to the
+/// versioned successor.
+pub async fn legacy_api<B>(req: Request<B>, next: Next<B>) -> Response {
+    let successor = format!(
+        "</api/v{}{}>; rel=\"successor-version\"",
+        API_VERSION,
+        req.uri().path(),
+    );
+
+    let mut response = next.run(req).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Ok(successor) = HeaderValue::from_str(&successor) {
+        headers.insert(header::LINK, successor);
+    }
+    response
+}
+
+pub fn cors_layer(config: &CorsConfig) -> Result<CorsLayer> {
+    let mut cors = CorsLayer::new();
+
+    if config.allowed_origins.iter().any(|origin| origin == "*") {
+        cors = cors.allow_origin(Any);
+    } else {
+        let origins = config
+            .allowed_origins
+            .iter()
+            .map(|origin| HeaderValue::from_str(origin))
+            .collect::<Result<Vec<_>, _>>()?;
+        cors = cors.allow_origin(AllowOrigin::list(origins));
+    }
+
+    let methods = config
+        .allowed_methods
+        .iter()
+        .map(|method| Method::from_bytes(method.as_bytes()))
+        .collect::<Result<Vec<_>, _>>()?;
+    cors = cors.allow_methods(methods);
+
+    if let Some(max_age) = config.max_age {
+        cors = cors.max_age(Duration::from_secs(max_age));
+    }
+
+    Ok(cors)
+}
+
+fn request_api_key(headers: &HeaderMap) -> Option<&str> {
+    if let Some(api_key) = headers.get("x-api-key") {
+        return api_key.to_str().ok();
+    }
+    let authorization = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    authorization.strip_prefix("Bearer ")
+}
+
+/// Guards mutating or expensive routes: requires a configured API key via `X-Api-Key` or
+/// `Authorization: Bearer`, and enforces that key's rate limit.
+pub async fn require_api_key<B>(req: Request<B>, next: Next<B>) -> Response {
+    let server = match req.extensions().get::<Arc<Server>>() {
+        Some(server) => Arc::clone(server),
+        None => return StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+    };
+    let check = match request_api_key(req.headers()) {
+        Some(api_key) => server.api_keys().check(api_key),
+        None => ApiKeyCheck::Unknown,
+    };
+
+    let (status, message) = match check {
+        ApiKeyCheck::Allowed => return next.run(req).await,
+        ApiKeyCheck::Unknown => (StatusCode::UNAUTHORIZED, "Missing or unknown API key"),
+        ApiKeyCheck::RateLimited => (StatusCode::TOO_MANY_REQUESTS, "API key rate limit exceeded"),
+    };
+    ApiError {
+        status,
+        message: message.to_string(),
+    }
+    .into_response()
+}
+
+/// Guards routes served from the block feeds: replicas started with `--no-indexer` don't
+/// run them, so they answer 503 instead of empty feeds or wrong totals.
+pub async fn require_indexer<B>(req: Request<B>, next: Next<B>) -> Response {
+    let runs_indexer = req
+        .extensions()
+        .get::<Arc<Server>>()
+        .map(|server| server.runs_indexer())
+        .unwrap_or(false);
+    if runs_indexer {
+        return next.run(req).await;
+    }
+    ApiError {
+        status: StatusCode::SERVICE_UNAVAILABLE,
+        message: "Not served by this instance, it runs without the indexer".to_string(),
+    }
+    .into_response()
+}
+
+/// Password from an HTTP basic `Authorization` header; the user name is ignored.
+pub fn basic_auth_password(headers: &HeaderMap) -> Option<String> {
+    let authorization = headers.get(header::AUTHORIZATION)?.to_str().ok()?;
+    let credentials = authorization.strip_prefix("Basic ")?;
+    let credentials = String::from_utf8(base64::decode(credentials.trim()).ok()?).ok()?;
+    let (_, password) = credentials.split_once(':')?;
+    Some(password.to_string())
+}
+
+/// Compares secrets without bailing out at the first mismatching byte.
+pub fn secrets_match(given: &str, expected: &str) -> bool {
+    given.len() == expected.len()
+        && given
+            .bytes()
+            .zip(expected.bytes())
+            .fold(0, |acc, (a, b)| acc | (a ^ b))
+            == 0
+}
@@ -1,7 +1,8 @@
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use utoipa::ToSchema;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonUtxo {
     pub tx_hash: String,
@@ -10,18 +11,32 @@ pub struct JsonUtxo {
     pub token_amount: u64,
     pub is_coinbase: bool,
     pub block_height: i32,
+    /// Unconfirmed tx already spending this UTXO; such UTXOs don't count towards balances
+    pub spent_in: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonUtxosResponse {
+    pub data: Vec<JsonUtxo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<JsonPagination>,
+}
+
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonBalance {
     pub token_id: Option<String>,
     pub sats_amount: i64,
     pub token_amount: i128,
+    /// Net change by unconfirmed txs, already included in `sats_amount`
+    pub unconfirmed_sats: i64,
+    /// Net change by unconfirmed txs, already included in `token_amount`
+    pub unconfirmed_token_amount: i128,
     pub utxos: Vec<JsonUtxo>,
 }
 
-#[derive(Serialize, Clone)]
+#[derive(Serialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonToken {
     pub token_id: String,
@@ -32,7 +47,7 @@ pub struct JsonToken {
     pub group_id: Option<String>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonBlock {
     pub hash: String,
@@ -40,14 +55,50 @@ pub struct JsonBlock {
     pub timestamp: i64,
     pub difficulty: f64,
     pub size: u64,
+    /// Size as a percentage of the consensus max block size
+    pub fullness_percent: f64,
     pub num_txs: u64,
+    /// 1 for the tip, as of the time of the request
+    pub confirmations: i32,
+    /// Buried deep enough that nodes won't reorg it anymore
+    pub is_finalized: bool,
+    /// Only set where a single block is requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coinbase_split: Option<JsonCoinbaseSplit>,
 }
 
-#[derive(Serialize, Clone)]
+/// How the coinbase outputs of a block divide the block reward.
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonCoinbaseSplit {
+    pub miner_reward_sats: i64,
+    pub miner_fund_sats: i64,
+    pub staking_reward_sats: i64,
+    pub outputs: Vec<JsonCoinbaseOutput>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonCoinbaseOutput {
+    pub out_idx: u32,
+    /// "minerReward", "minerFund" or "stakingReward"
+    pub kind: &'static str,
+    /// Cash address of the output, if it pays to one
+    pub address: Option<String>,
+    /// Output script, in hex
+    pub output_script: String,
+    pub sats_amount: i64,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonTx {
     pub tx_hash: String,
     pub block_height: Option<i32>,
+    /// Index of the tx within its block, 0 for the coinbase; only set where the tx was
+    /// listed as part of its block
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub position_in_block: Option<u32>,
     pub timestamp: i64,
     pub is_coinbase: bool,
     pub size: i32,
@@ -56,9 +107,47 @@ pub struct JsonTx {
     pub stats: JsonTxStats,
     pub token_id: Option<String>,
     pub token: Option<JsonToken>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub slp_errors: Vec<JsonSlpError>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub op_returns: Vec<JsonOpReturnData>,
+    /// Only checked where a single unconfirmed tx is looked up
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub double_spent_inputs: Vec<JsonDoubleSpentInput>,
+}
+
+/// Input whose previous output is also spent by a conflicting tx.
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonDoubleSpentInput {
+    pub input_idx: u32,
+    /// Conflicting tx, in the mempool or already mined
+    pub spent_by: String,
 }
 
-#[derive(Serialize, Clone)]
+/// Data pushed by an OP_RETURN output, decoded.
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonOpReturnData {
+    pub out_idx: u32,
+    /// "slp", "cashtab", "memo" or "other"
+    pub protocol: &'static str,
+    /// Every push in hex, including the ones identifying the protocol
+    pub pushes: Vec<String>,
+    /// Pushes after the protocol prefix as UTF-8, invalid bytes replaced, joined by spaces
+    pub text: String,
+}
+
+/// Part of a tx that failed eToken validation or burns tokens, and why.
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonSlpError {
+    /// "Transaction" for the tx as a whole, "Input #n" for a single input
+    pub section: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonTxStats {
     pub sats_input: i64,
@@ -78,14 +167,432 @@ pub struct JsonTxs {
     pub token_indices: HashMap<Vec<u8>, usize>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, Clone, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonBlocksResponse {
     pub data: Vec<JsonBlock>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<JsonPagination>,
 }
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 #[serde(rename_all = "camelCase")]
 pub struct JsonTxsResponse {
     pub data: Vec<JsonTx>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_cursor: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<JsonPagination>,
+    /// Unit the client prefers amounts displayed in (`?unit=` or the `unit` cookie); the
+    /// amounts themselves are always in sats
+    pub display_unit: &'static str,
+}
+
+/// Last line of an address history export; every line before it is a tx.
+#[derive(Serialize, ToSchema)]
+pub struct JsonNdjsonTrailer {
+    pub trailer: JsonNdjsonTrailerInfo,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonNdjsonTrailerInfo {
+    /// `?cursor=` of the next export, null once the history is complete
+    pub next_cursor: Option<String>,
+}
+
+/// Where a page sits in its listing; left out with `?envelope=false` for clients expecting
+/// the bare response.
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonPagination {
+    pub page: usize,
+    pub page_size: usize,
+    /// Total number of items across all pages, if known upfront
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<usize>,
+    pub num_pages: usize,
+    /// Path of the following page, if any
+    pub next: Option<String>,
+    /// Path of the preceding page, if any
+    pub prev: Option<String>,
+}
+
+/// Consecutive block headers for SPV clients, oldest first.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonHeaders {
+    pub start_height: i32,
+    /// Raw 80-byte block headers, in hex; fewer than requested if the range passes the tip
+    pub headers: Vec<String>,
+}
+
+/// Whether a tx is mined yet, for polling where `/ws?tx=` can't be used.
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTxStatus {
+    pub txid: String,
+    pub is_confirmed: bool,
+    pub block_hash: Option<String>,
+    pub block_height: Option<i32>,
+    /// 0 while unconfirmed
+    pub confirmations: i32,
+}
+
+/// Everything an SPV client needs to check a tx got mined, hashes in big-endian hex.
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonMerkleProof {
+    pub tx_hash: String,
+    pub block_hash: String,
+    pub block_height: i32,
+    /// Raw 80-byte block header, in hex
+    pub block_header: String,
+    pub merkle_root: String,
+    pub position_in_block: u32,
+    /// Sibling hashes from the tx up to the root
+    pub branch: Vec<String>,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonBroadcastTxRequest {
+    pub raw_tx: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonBroadcastTxResponse {
+    pub txid: String,
+}
+
+#[derive(Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonVerifyMessageRequest {
+    pub address: String,
+    pub message: String,
+    /// Base64 signature as produced by `signmessage`
+    pub signature: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonVerifyMessageResponse {
+    pub is_valid: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonSupply {
+    pub block_height: i32,
+    pub issued_sats: i64,
+    pub burned_sats: i64,
+    pub circulating_sats: i64,
+    /// False while OP_RETURN burns are still being summed from genesis; until then
+    /// `burned_sats` understates and `circulating_sats` overstates the true figures
+    pub is_complete: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonBurned {
+    /// Highest block whose OP_RETURN burns are included
+    pub scanned_height: i32,
+    /// False while the scan from genesis hasn't caught up yet, e.g. shortly after startup
+    pub is_complete: bool,
+    pub op_return_burned_sats: i64,
+    pub burn_address_sats: i64,
+    pub total_burned_sats: i64,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonHashrate {
+    /// Number of blocks each estimate is based on
+    pub window: i32,
+    pub hashrate: f64,
+    pub difficulty: f64,
+    /// One estimate per consecutive window, oldest first, for charting
+    pub points: Vec<JsonHashratePoint>,
+}
+
+#[derive(Clone, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonHashratePoint {
+    pub height: i32,
+    pub timestamp: i64,
+    pub hashrate: f64,
+    pub difficulty: f64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTokenStats {
+    pub token_id: String,
+    /// Number of days covered
+    pub window: i64,
+    /// False while the scan of recent blocks hasn't reached back to the start of the window
+    pub is_complete: bool,
+    /// One point per UTC day, oldest first, for charting
+    pub points: Vec<JsonTokenStatsPoint>,
+}
+
+#[derive(Debug, PartialEq, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTokenStatsPoint {
+    /// Start of the day
+    pub timestamp: i64,
+    /// Token base units sent by confirmed txs, not counting outputs back to the senders
+    pub volume: i128,
+    pub num_txs: u32,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTokenTopTransfers {
+    pub token_id: String,
+    /// Number of days covered
+    pub window: i64,
+    /// False while the scan of recent blocks hasn't reached back to the start of the window
+    pub is_complete: bool,
+    /// Largest first
+    pub data: Vec<JsonTokenTransfer>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTokenTransfer {
+    pub tx_hash: String,
+    pub block_height: i32,
+    pub timestamp: i64,
+    /// Token base units sent by the tx, leaving out change back to the scripts it spends from
+    pub amount: i128,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonReadiness {
+    pub is_ready: bool,
+    /// Why the explorer isn't ready, empty if it is
+    pub problems: Vec<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonSignaling {
+    /// Number of most recent blocks asked to count over
+    pub window: usize,
+    /// First and last block of the window counted over
+    pub start_height: i32,
+    pub end_height: i32,
+    /// Less than `window` while the tracker is still catching up after startup
+    pub num_blocks: usize,
+    /// Every bit signaled by at least one block of the window, lowest first
+    pub bits: Vec<JsonSignalingBit>,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonSignalingBit {
+    pub bit: u8,
+    /// Blocks of the window signaling the bit
+    pub num_blocks: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonBlockIntervals {
+    /// Number of intervals between the blocks looked at
+    pub window: usize,
+    pub average_secs: f64,
+    pub median_secs: f64,
+    pub target_secs: i64,
+    pub last_block_timestamp: i64,
+    /// As of the time of the request
+    pub secs_since_last_block: i64,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonBlockFullness {
+    /// Number of blocks each point is based on
+    pub window: i32,
+    pub max_block_size: u64,
+    /// One point per consecutive window, oldest first, for charting
+    pub points: Vec<JsonBlockFullnessPoint>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonBlockFullnessPoint {
+    pub height: i32,
+    pub timestamp: i64,
+    pub average_percent: f64,
+    /// Fullest block of the window
+    pub max_percent: f64,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTxCountChart {
+    pub from: i64,
+    pub to: i64,
+    /// One point per UTC day, oldest first
+    pub points: Vec<JsonTxCountPoint>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonTxCountPoint {
+    /// Start of the day
+    pub timestamp: i64,
+    /// Coinbase txs included
+    pub num_txs: u64,
+    pub num_blocks: u32,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonCountdowns {
+    pub tip_height: i32,
+    pub next_halving: JsonCountdown,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonCountdown {
+    pub height: i32,
+    pub blocks_remaining: i32,
+    pub eta_timestamp: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLargeTxsResponse {
+    pub data: Vec<JsonLargeTx>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonLargeTx {
+    pub tx_hash: String,
+    pub block_height: i32,
+    pub timestamp: i64,
+    pub sats_output: i64,
+    pub num_inputs: u32,
+    pub num_outputs: u32,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonRecentTokensResponse {
+    pub data: Vec<JsonNewToken>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonNewToken {
+    pub token: JsonToken,
+    /// GENESIS tx that created the token
+    pub tx_hash: String,
+    pub block_height: i32,
+    pub timestamp: i64,
+    /// Base units minted by the GENESIS, before applying `decimals`
+    pub initial_supply: i128,
+    /// Whether more can be minted later
+    pub has_mint_baton: bool,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonOpReturnSearchResponse {
+    pub data: Vec<JsonOpReturn>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pagination: Option<JsonPagination>,
+}
+
+#[derive(Serialize, Clone, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonOpReturn {
+    pub tx_hash: String,
+    pub out_idx: u32,
+    pub block_height: i32,
+    pub timestamp: i64,
+    /// "cashtab", "memo" or "other"
+    pub protocol: &'static str,
+    /// Pushes decoded as UTF-8, joined by spaces
+    pub message: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAddressBalances {
+    /// XEC balance under "main", token balances under their token ID
+    pub balances: HashMap<String, JsonBalance>,
+    /// Sats of all UTXOs, hidden tokens included, split by what they can be spent on
+    pub breakdown: JsonBalanceBreakdown,
+    /// Whether the address has more unconfirmed txs than were scanned, so the unconfirmed
+    /// amounts of the balances are incomplete
+    pub unconfirmed_truncated: bool,
+    pub tokens: HashMap<String, JsonToken>,
+    /// Number of spam tokens left out, see `show_spam`
+    pub num_hidden_tokens: usize,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonBalanceBreakdown {
+    /// Plain XEC that can be spent now
+    pub spendable_sats: i64,
+    /// Coinbase outputs with fewer than 100 confirmations
+    pub immature_coinbase_sats: i64,
+    /// Sats in token UTXOs, mint batons included
+    pub token_dust_sats: i64,
+    pub total_sats: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonCounterpartiesResponse {
+    pub data: Vec<JsonCounterparty>,
+    /// Number of most recent txs of the address the counts are based on
+    pub num_txs_scanned: usize,
+    /// False if the history was longer than what gets scanned
+    pub is_complete: bool,
+}
+
+#[derive(Serialize, Clone, Debug, PartialEq, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonCounterparty {
+    pub address: String,
+    /// Number of txs the two addresses were both part of
+    pub num_txs: u32,
+    /// Sats the address sent to the counterparty
+    pub sent_sats: i64,
+    /// Sats the address received in txs funded by the counterparty, split by input value
+    pub received_sats: i64,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAddressForms {
+    /// "P2PKH" or "P2SH"
+    pub address_type: &'static str,
+    pub cash_address: String,
+    pub token_address: String,
+    pub legacy_address: String,
+    /// Output script paying to the address, in hex
+    pub output_script: String,
+}
+
+#[derive(Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct JsonAddressValidation {
+    pub is_valid: bool,
+    /// "mainnet", "testnet" or "regtest", if the prefix is known
+    pub network: Option<&'static str>,
+    /// Normalized forms of the address, if it is valid
+    pub forms: Option<JsonAddressForms>,
+    /// Why the address did not parse
+    pub error: Option<String>,
 }
@@ -0,0 +1,132 @@
+use bitcoin::hashes::{hash160, sha256d, Hash};
+use bitcoinsuite_core::{AddressType, CashAddress};
+use bitcoinsuite_error::Result;
+use eyre::bail;
+use secp256k1::{
+    recovery::{RecoverableSignature, RecoveryId},
+    Message, Secp256k1,
+};
+
+/// Cashtab signs under the eCash magic, Electrum ABC and the node under the Bitcoin one.
+const MESSAGE_MAGICS: [&str; 2] = ["eCash Signed Message:\n", "Bitcoin Signed Message:\n"];
+
+pub fn signed_message_hash(magic: &str, message: &str) -> sha256d::Hash {
+    let mut data = Vec::with_capacity(magic.len() + message.len() + 4);
+    write_var_bytes(&mut data, magic.as_bytes());
+    write_var_bytes(&mut data, message.as_bytes());
+    sha256d::Hash::hash(&data)
+}
+
+/// Checks a base64 recoverable ECDSA signature, as made by `signmessage`, against a P2PKH
+/// address by recovering the public key and comparing its hash.
+pub fn verify_message(address: &CashAddress, message: &str, signature: &str) -> Result<bool> {
+    if address.addr_type() != AddressType::P2PKH {
+        bail!("Only P2PKH addresses can sign messages");
+    }
+    let signature = base64::decode(signature.trim())?;
+    if signature.len() != 65 {
+        bail!("Signature must be 65 bytes, got {}", signature.len());
+    }
+    let header = signature[0];
+    if !(27..=34).contains(&header) {
+        bail!("Invalid signature header byte {}", header);
+    }
+    let is_compressed = header >= 31;
+    let recovery_id = RecoveryId::from_i32(i32::from((header - 27) & 3))?;
+    let signature = RecoverableSignature::from_compact(&signature[1..], recovery_id)?;
+
+    let secp = Secp256k1::verification_only();
+    for magic in MESSAGE_MAGICS {
+        let message_hash = signed_message_hash(magic, message);
+        let message = Message::from_slice(&message_hash[..])?;
+        let pubkey = match secp.recover(&message, &signature) {
+            Ok(pubkey) => pubkey,
+            Err(_) => continue,
+        };
+        let pubkey_hash = if is_compressed {
+            hash160::Hash::hash(&pubkey.serialize())
+        } else {
+            hash160::Hash::hash(&pubkey.serialize_uncompressed())
+        };
+        if pubkey_hash[..] == *address.hash().as_slice() {
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+fn write_var_bytes(data: &mut Vec<u8>, bytes: &[u8]) {
+    match bytes.len() {
+        len @ 0..=0xfc => data.push(len as u8),
+        len @ 0xfd..=0xffff => {
+            data.push(0xfd);
+            data.extend_from_slice(&(len as u16).to_le_bytes());
+        }
+        len => {
+            data.push(0xfe);
+            data.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+    }
+    data.extend_from_slice(bytes);
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_core::{AddressType, CashAddress, Hashed, ShaRmd160};
+
+    use super::verify_message;
+
+    /// From Bitcoin Core's `rpc_signmessage.py`, signed under the Bitcoin magic by
+    /// 19pTScE8LZfwRNasdjXrgFWkVqMRcU99GK.
+    const CORE_HASH: &str = "60baa0f494b38ce3c940dea67f3804dc52d1fb94";
+    const CORE_MESSAGE: &str = "This is just a test message";
+    const CORE_SIGNATURE: &str =
+        "INbVnW4e6PeRmsv2Qgu8NuopvrVjkcxob+sX8OcZG0SALhWybUjzMLPdAsXI46YZGb0KQTRii+wWIQzRpG/U+S0=";
+
+    /// Signed under the eCash magic, like Cashtab does, by 1CtR62cMqKAzm96dno2tz2hc54BQ5R1qwk.
+    const ECASH_HASH: &str = "8262b4481a6fd16e1794470b499a53f7f50c17d5";
+    const ECASH_MESSAGE: &str = "Proof of ownership for be.cash";
+    const ECASH_SIGNATURE: &str =
+        "H7E7P5rHXzZqrX80zhYGTPvtv0q9OEt03r26X+paiqLmRt5d2VvF6ckQu3wU3HV9hd5GJHDEwhZlgVSir25POTk=";
+
+    fn p2pkh_address(hash_hex: &str) -> CashAddress<'static> {
+        let hash = ShaRmd160::from_slice(&hex::decode(hash_hex).unwrap()).unwrap();
+        CashAddress::from_hash("ecash", AddressType::P2PKH, hash)
+    }
+
+    #[test]
+    fn verifies_bitcoin_magic() {
+        let address = p2pkh_address(CORE_HASH);
+        assert!(verify_message(&address, CORE_MESSAGE, CORE_SIGNATURE).unwrap());
+    }
+
+    #[test]
+    fn verifies_ecash_magic() {
+        let address = p2pkh_address(ECASH_HASH);
+        assert!(verify_message(&address, ECASH_MESSAGE, ECASH_SIGNATURE).unwrap());
+    }
+
+    #[test]
+    fn rejects_other_message_or_address() {
+        let address = p2pkh_address(CORE_HASH);
+        assert!(!verify_message(&address, "This is just a test message.", CORE_SIGNATURE).unwrap());
+        let other_address = p2pkh_address(ECASH_HASH);
+        assert!(!verify_message(&other_address, CORE_MESSAGE, CORE_SIGNATURE).unwrap());
+    }
+
+    #[test]
+    fn rejects_p2sh_address() {
+        let hash = ShaRmd160::from_slice(&hex::decode(CORE_HASH).unwrap()).unwrap();
+        let address = CashAddress::from_hash("ecash", AddressType::P2SH, hash);
+        assert!(verify_message(&address, CORE_MESSAGE, CORE_SIGNATURE).is_err());
+    }
+
+    #[test]
+    fn rejects_malformed_signature() {
+        let address = p2pkh_address(CORE_HASH);
+        assert!(verify_message(&address, CORE_MESSAGE, "not base64!").is_err());
+        assert!(verify_message(&address, CORE_MESSAGE, &base64::encode([31; 64])).is_err());
+        assert!(verify_message(&address, CORE_MESSAGE, &base64::encode([26; 65])).is_err());
+    }
+}
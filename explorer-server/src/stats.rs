@@ -0,0 +1,304 @@
+use std::collections::{HashMap, HashSet};
+
+use bitcoinsuite_chronik_client::proto::{BlockInfo, Tx};
+
+use crate::{
+    blockchain::{
+        block_fullness_percent, calculate_block_difficulty, destination_from_script, Destination,
+        HALVING_INTERVAL, MAX_BLOCK_SIZE, TARGET_BLOCK_SPACING_SECS,
+    },
+    server_primitives::{
+        JsonBlockIntervals, JsonCountdown, JsonCountdowns, JsonCounterparty, JsonTxCountPoint,
+    },
+};
+
+pub const SECONDS_PER_DAY: i64 = 86_400;
+/// Expected hashes to find a block at difficulty 1.
+const HASHES_PER_DIFFICULTY: f64 = 4_294_967_296.0;
+
+/// Estimated network hashrate in H/s over `blocks`, sorted by height ascending.
+///
+/// Uses the work of all blocks after the first over the time it took to mine them, so that
+/// a single block with a skewed timestamp doesn't dominate the estimate.
+pub fn estimate_hashrate(blocks: &[BlockInfo]) -> Option<f64> {
+    let (first, rest) = blocks.split_first()?;
+    let last = rest.last()?;
+    let timespan = last.timestamp - first.timestamp;
+    if timespan <= 0 {
+        return None;
+    }
+    let work = rest
+        .iter()
+        .map(|block| calculate_block_difficulty(block.n_bits) * HASHES_PER_DIFFICULTY)
+        .sum::<f64>();
+    Some(work / timespan as f64)
+}
+
+pub fn average_difficulty(blocks: &[BlockInfo]) -> f64 {
+    if blocks.is_empty() {
+        return 0.0;
+    }
+    let total = blocks
+        .iter()
+        .map(|block| calculate_block_difficulty(block.n_bits))
+        .sum::<f64>();
+    total / blocks.len() as f64
+}
+
+/// Average and largest fullness of `blocks`, or `None` if there are none.
+pub fn block_fullness(blocks: &[BlockInfo]) -> Option<(f64, f64)> {
+    let sizes = blocks.iter().map(|block| block.block_size);
+    let max_size = sizes.clone().max()?;
+    let average_size = sizes.sum::<u64>() as f64 / blocks.len() as f64;
+    Some((
+        average_size * 100.0 / MAX_BLOCK_SIZE as f64,
+        block_fullness_percent(max_size),
+    ))
+}
+
+/// Time between consecutive `blocks`, sorted by height ascending, and since the last one.
+pub fn block_intervals(blocks: &[BlockInfo], now: i64) -> JsonBlockIntervals {
+    let mut intervals = blocks
+        .windows(2)
+        .map(|pair| pair[1].timestamp - pair[0].timestamp)
+        .collect::<Vec<_>>();
+    intervals.sort_unstable();
+
+    let average_secs = match intervals.len() {
+        0 => 0.0,
+        len => intervals.iter().sum::<i64>() as f64 / len as f64,
+    };
+    // Timestamps may go backwards a little, so the median is the more robust of the two
+    let median_secs = match intervals.len() {
+        0 => 0.0,
+        len if len % 2 == 0 => (intervals[len / 2 - 1] + intervals[len / 2]) as f64 / 2.0,
+        len => intervals[len / 2] as f64,
+    };
+    let last_block_timestamp = blocks.last().map_or(0, |block| block.timestamp);
+
+    JsonBlockIntervals {
+        window: intervals.len(),
+        average_secs,
+        median_secs,
+        target_secs: TARGET_BLOCK_SPACING_SECS,
+        last_block_timestamp,
+        secs_since_last_block: (now - last_block_timestamp).max(0),
+    }
+}
+
+/// Number of txs (coinbases included) and blocks per UTC day, for every day overlapping
+/// `from..to`, oldest first. Blocks outside the range are ignored.
+pub fn daily_tx_counts(blocks: &[BlockInfo], from: i64, to: i64) -> Vec<JsonTxCountPoint> {
+    let first_day = from.div_euclid(SECONDS_PER_DAY);
+    let last_day = (to - 1).div_euclid(SECONDS_PER_DAY);
+    let mut points = (first_day..=last_day)
+        .map(|day| JsonTxCountPoint {
+            timestamp: day * SECONDS_PER_DAY,
+            num_txs: 0,
+            num_blocks: 0,
+        })
+        .collect::<Vec<_>>();
+    for block in blocks {
+        if block.timestamp < from || block.timestamp >= to {
+            continue;
+        }
+        let day = block.timestamp.div_euclid(SECONDS_PER_DAY);
+        let point = &mut points[(day - first_day) as usize];
+        point.num_txs += block.num_txs;
+        point.num_blocks += 1;
+    }
+    points
+}
+
+/// Countdowns from the tip, with ETAs assuming target block spacing.
+pub fn countdowns(tip_height: i32, tip_timestamp: i64) -> JsonCountdowns {
+    let countdown_to = |height: i32| {
+        let blocks_remaining = height - tip_height;
+        JsonCountdown {
+            height,
+            blocks_remaining,
+            eta_timestamp: tip_timestamp + i64::from(blocks_remaining) * TARGET_BLOCK_SPACING_SECS,
+        }
+    };
+    let next_halving_height = (tip_height / HALVING_INTERVAL + 1) * HALVING_INTERVAL;
+
+    JsonCountdowns {
+        tip_height,
+        next_halving: countdown_to(next_halving_height),
+    }
+}
+
+/// Addresses `output_script` transacted with in `txs`, most frequent first.
+///
+/// In txs spending from the script, every other output address counts as sent to. In txs
+/// only paying to it, what it received is attributed to the input addresses in proportion
+/// to the value they put in.
+pub fn counterparties(txs: &[Tx], output_script: &[u8], prefix: &str) -> Vec<JsonCounterparty> {
+    let mut counterparties: HashMap<String, JsonCounterparty> = HashMap::new();
+    let address_of = |script: &[u8]| match destination_from_script(prefix, script) {
+        Destination::Address(address) if script != output_script => {
+            Some(address.as_str().to_string())
+        }
+        _ => None,
+    };
+
+    for tx in txs {
+        let mut seen = HashSet::new();
+        let is_sender = tx
+            .inputs
+            .iter()
+            .any(|input| input.output_script == output_script);
+        if is_sender {
+            for output in &tx.outputs {
+                if let Some(address) = address_of(&output.output_script) {
+                    let counterparty = counterparty_entry(&mut counterparties, &address);
+                    counterparty.sent_sats += output.value;
+                    if seen.insert(address) {
+                        counterparty.num_txs += 1;
+                    }
+                }
+            }
+        } else {
+            let received_sats: i64 = tx
+                .outputs
+                .iter()
+                .filter(|output| output.output_script == output_script)
+                .map(|output| output.value)
+                .sum();
+            let input_sats: i64 = tx.inputs.iter().map(|input| input.value).sum();
+            if input_sats == 0 {
+                continue;
+            }
+            for input in &tx.inputs {
+                if let Some(address) = address_of(&input.output_script) {
+                    let counterparty = counterparty_entry(&mut counterparties, &address);
+                    counterparty.received_sats +=
+                        (i128::from(received_sats) * i128::from(input.value)
+                            / i128::from(input_sats)) as i64;
+                    if seen.insert(address) {
+                        counterparty.num_txs += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    let mut counterparties = counterparties.into_values().collect::<Vec<_>>();
+    counterparties.sort_by(|a, b| {
+        b.num_txs
+            .cmp(&a.num_txs)
+            .then_with(|| a.address.cmp(&b.address))
+    });
+    counterparties
+}
+
+fn counterparty_entry<'a>(
+    counterparties: &'a mut HashMap<String, JsonCounterparty>,
+    address: &str,
+) -> &'a mut JsonCounterparty {
+    counterparties
+        .entry(address.to_string())
+        .or_insert_with(|| JsonCounterparty {
+            address: address.to_string(),
+            num_txs: 0,
+            sent_sats: 0,
+            received_sats: 0,
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_chronik_client::proto::{Tx, TxInput, TxOutput};
+
+    use super::counterparties;
+    use crate::{
+        blockchain::{destination_from_script, Destination},
+        server_primitives::JsonCounterparty,
+    };
+
+    fn p2pkh_script(byte: u8) -> Vec<u8> {
+        let mut script = vec![0x76, 0xa9, 20];
+        script.extend([byte; 20]);
+        script.extend([0x88, 0xac]);
+        script
+    }
+
+    fn address(byte: u8) -> String {
+        match destination_from_script("ecash", &p2pkh_script(byte)) {
+            Destination::Address(address) => address.as_str().to_string(),
+            _ => unreachable!(),
+        }
+    }
+
+    fn tx(inputs: &[(u8, i64)], outputs: &[(u8, i64)]) -> Tx {
+        Tx {
+            inputs: inputs
+                .iter()
+                .map(|&(byte, value)| TxInput {
+                    output_script: p2pkh_script(byte),
+                    value,
+                    ..Default::default()
+                })
+                .collect(),
+            outputs: outputs
+                .iter()
+                .map(|&(byte, value)| TxOutput {
+                    output_script: p2pkh_script(byte),
+                    value,
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    fn counterparty(
+        byte: u8,
+        num_txs: u32,
+        sent_sats: i64,
+        received_sats: i64,
+    ) -> JsonCounterparty {
+        JsonCounterparty {
+            address: address(byte),
+            num_txs,
+            sent_sats,
+            received_sats,
+        }
+    }
+
+    #[test]
+    fn sending_counts_outputs_except_change() {
+        let txs = [tx(&[(1, 1000)], &[(2, 600), (2, 100), (1, 290)])];
+        assert_eq!(
+            counterparties(&txs, &p2pkh_script(1), "ecash"),
+            vec![counterparty(2, 1, 700, 0)],
+        );
+    }
+
+    #[test]
+    fn receiving_splits_by_input_value() {
+        let txs = [tx(&[(3, 300), (4, 100)], &[(1, 200), (3, 190)])];
+        let mut expected = vec![counterparty(3, 1, 0, 150), counterparty(4, 1, 0, 50)];
+        expected.sort_by(|a, b| a.address.cmp(&b.address));
+        assert_eq!(counterparties(&txs, &p2pkh_script(1), "ecash"), expected);
+    }
+
+    #[test]
+    fn most_frequent_first() {
+        let txs = [
+            tx(&[(1, 1000)], &[(2, 500), (3, 400)]),
+            tx(&[(3, 1000)], &[(1, 900)]),
+            tx(&[(1, 500)], &[(3, 400)]),
+        ];
+        assert_eq!(
+            counterparties(&txs, &p2pkh_script(1), "ecash"),
+            vec![counterparty(3, 3, 800, 900), counterparty(2, 1, 500, 0)],
+        );
+    }
+
+    #[test]
+    fn unrelated_txs_are_ignored() {
+        let txs = [tx(&[], &[(2, 100)])];
+        assert!(counterparties(&txs, &p2pkh_script(1), "ecash").is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+use std::{
+    collections::{BTreeMap, VecDeque},
+    fmt::Write,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+
+const MAX_RECENT_ERRORS: usize = 50;
+/// Upper bounds of the latency histogram buckets, in seconds, like Prometheus' defaults.
+const LATENCY_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// In-process counters and recent failures, rendered on the admin dashboard.
+pub struct StatusRegistry {
+    started_at: Instant,
+    num_requests: AtomicU64,
+    num_errors: AtomicU64,
+    recent_errors: Mutex<VecDeque<RecentError>>,
+    caches: Mutex<BTreeMap<&'static str, CacheStats>>,
+    /// Latency of every request, by route pattern
+    routes: Mutex<BTreeMap<String, LatencyHistogram>>,
+    /// Latency of calls to chronik, by call
+    upstream: Mutex<BTreeMap<&'static str, LatencyHistogram>>,
+}
+
+#[derive(Clone)]
+pub struct RecentError {
+    pub timestamp: DateTime<Utc>,
+    pub request_id: String,
+    pub method: String,
+    pub path: String,
+    pub status: u16,
+    pub message: String,
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        let total = self.hits + self.misses;
+        if total == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / total as f64
+    }
+}
+
+/// Durations sorted into [`LATENCY_BUCKETS`], plus how many of them failed.
+#[derive(Clone, Default)]
+pub struct LatencyHistogram {
+    /// Count per bucket, not cumulative; durations above the last bucket are only counted in
+    /// `count`
+    bucket_counts: [u64; LATENCY_BUCKETS.len()],
+    pub count: u64,
+    pub sum_secs: f64,
+    pub num_errors: u64,
+}
+
+impl LatencyHistogram {
+    fn record(&mut self, duration: Duration, is_error: bool) {
+        let secs = duration.as_secs_f64();
+        if let Some(bucket) = LATENCY_BUCKETS.iter().position(|bound| secs <= *bound) {
+            self.bucket_counts[bucket] += 1;
+        }
+        self.count += 1;
+        self.sum_secs += secs;
+        self.num_errors += is_error as u64;
+    }
+
+    /// Upper bound of the bucket the `quantile` falls into, e.g. "≤ 25 ms"; only as precise as
+    /// the buckets.
+    pub fn quantile(&self, quantile: f64) -> String {
+        let rank = (quantile * self.count as f64).ceil() as u64;
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&self.bucket_counts) {
+            cumulative += count;
+            if cumulative >= rank {
+                return format!("≤ {:.0} ms", bound * 1000.0);
+            }
+        }
+        format!(
+            "> {:.0} ms",
+            LATENCY_BUCKETS[LATENCY_BUCKETS.len() - 1] * 1000.0
+        )
+    }
+
+    pub fn error_rate(&self) -> f64 {
+        if self.count == 0 {
+            return 0.0;
+        }
+        self.num_errors as f64 / self.count as f64
+    }
+
+    fn write_prometheus(&self, output: &mut String, name: &str, labels: &str) {
+        let mut cumulative = 0;
+        for (bound, count) in LATENCY_BUCKETS.iter().zip(&self.bucket_counts) {
+            cumulative += count;
+            let _ = writeln!(
+                output,
+                "{}_bucket{{{},le=\"{}\"}} {}",
+                name, labels, bound, cumulative
+            );
+        }
+        let _ = writeln!(
+            output,
+            "{}_bucket{{{},le=\"+Inf\"}} {}",
+            name, labels, self.count
+        );
+        let _ = writeln!(output, "{}_sum{{{}}} {}", name, labels, self.sum_secs);
+        let _ = writeln!(output, "{}_count{{{}}} {}", name, labels, self.count);
+    }
+}
+
+impl StatusRegistry {
+    pub fn new() -> Self {
+        StatusRegistry {
+            started_at: Instant::now(),
+            num_requests: AtomicU64::new(0),
+            num_errors: AtomicU64::new(0),
+            recent_errors: Mutex::new(VecDeque::with_capacity(MAX_RECENT_ERRORS)),
+            caches: Mutex::new(BTreeMap::new()),
+            routes: Mutex::new(BTreeMap::new()),
+            upstream: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    pub fn uptime(&self) -> String {
+        let secs = self.started_at.elapsed().as_secs();
+        format!(
+            "{}d {}h {}m",
+            secs / 86_400,
+            secs % 86_400 / 3_600,
+            secs % 3_600 / 60,
+        )
+    }
+
+    pub fn num_requests(&self) -> u64 {
+        self.num_requests.load(Ordering::Relaxed)
+    }
+
+    pub fn num_errors(&self) -> u64 {
+        self.num_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn record_request(&self) {
+        self.num_requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_error(&self, error: RecentError) {
+        self.num_errors.fetch_add(1, Ordering::Relaxed);
+        let mut recent_errors = self.recent_errors.lock().unwrap();
+        if recent_errors.len() == MAX_RECENT_ERRORS {
+            recent_errors.pop_back();
+        }
+        recent_errors.push_front(error);
+    }
+
+    /// Most recent first.
+    pub fn recent_errors(&self) -> Vec<RecentError> {
+        self.recent_errors.lock().unwrap().iter().cloned().collect()
+    }
+
+    pub fn record_cache_lookup(&self, cache: &'static str, is_hit: bool) {
+        let mut caches = self.caches.lock().unwrap();
+        let stats = caches.entry(cache).or_default();
+        if is_hit {
+            stats.hits += 1;
+        } else {
+            stats.misses += 1;
+        }
+    }
+
+    pub fn cache_stats(&self) -> Vec<(&'static str, CacheStats)> {
+        let caches = self.caches.lock().unwrap();
+        caches.iter().map(|(name, stats)| (*name, *stats)).collect()
+    }
+
+    /// `route` is the pattern the request matched, e.g. `/tx/:hash`, so histograms don't
+    /// multiply with every hash requested.
+    pub fn record_route_latency(&self, route: &str, duration: Duration, is_error: bool) {
+        let mut routes = self.routes.lock().unwrap();
+        match routes.get_mut(route) {
+            Some(histogram) => histogram.record(duration, is_error),
+            None => {
+                let mut histogram = LatencyHistogram::default();
+                histogram.record(duration, is_error);
+                routes.insert(route.to_string(), histogram);
+            }
+        }
+    }
+
+    pub fn record_upstream_latency(&self, call: &'static str, duration: Duration, is_error: bool) {
+        let mut upstream = self.upstream.lock().unwrap();
+        upstream.entry(call).or_default().record(duration, is_error);
+    }
+
+    pub fn route_latencies(&self) -> Vec<(String, LatencyHistogram)> {
+        let routes = self.routes.lock().unwrap();
+        routes
+            .iter()
+            .map(|(route, histogram)| (route.clone(), histogram.clone()))
+            .collect()
+    }
+
+    pub fn upstream_latencies(&self) -> Vec<(&'static str, LatencyHistogram)> {
+        let upstream = self.upstream.lock().unwrap();
+        upstream
+            .iter()
+            .map(|(call, histogram)| (*call, histogram.clone()))
+            .collect()
+    }
+
+    /// Everything above in the Prometheus text format.
+    pub fn prometheus_metrics(&self) -> String {
+        let mut output = String::new();
+        let _ = writeln!(output, "# TYPE explorer_uptime_seconds gauge");
+        let _ = writeln!(
+            output,
+            "explorer_uptime_seconds {}",
+            self.started_at.elapsed().as_secs()
+        );
+        let _ = writeln!(output, "# TYPE explorer_requests_total counter");
+        let _ = writeln!(output, "explorer_requests_total {}", self.num_requests());
+        let _ = writeln!(output, "# TYPE explorer_errors_total counter");
+        let _ = writeln!(output, "explorer_errors_total {}", self.num_errors());
+
+        let _ = writeln!(output, "# TYPE explorer_cache_lookups_total counter");
+        for (name, stats) in self.cache_stats() {
+            let _ = writeln!(
+                output,
+                "explorer_cache_lookups_total{{cache=\"{}\",result=\"hit\"}} {}",
+                name, stats.hits
+            );
+            let _ = writeln!(
+                output,
+                "explorer_cache_lookups_total{{cache=\"{}\",result=\"miss\"}} {}",
+                name, stats.misses
+            );
+        }
+
+        let route_latencies = self.route_latencies();
+        let _ = writeln!(output, "# TYPE explorer_request_duration_seconds histogram");
+        for (route, histogram) in &route_latencies {
+            let labels = format!("route=\"{}\"", route);
+            histogram.write_prometheus(&mut output, "explorer_request_duration_seconds", &labels);
+        }
+        let _ = writeln!(output, "# TYPE explorer_request_errors_total counter");
+        for (route, histogram) in &route_latencies {
+            let _ = writeln!(
+                output,
+                "explorer_request_errors_total{{route=\"{}\"}} {}",
+                route, histogram.num_errors
+            );
+        }
+
+        let upstream_latencies = self.upstream_latencies();
+        let _ = writeln!(output, "# TYPE explorer_chronik_duration_seconds histogram");
+        for (call, histogram) in &upstream_latencies {
+            let labels = format!("call=\"{}\"", call);
+            histogram.write_prometheus(&mut output, "explorer_chronik_duration_seconds", &labels);
+        }
+        let _ = writeln!(output, "# TYPE explorer_chronik_errors_total counter");
+        for (call, histogram) in &upstream_latencies {
+            let _ = writeln!(
+                output,
+                "explorer_chronik_errors_total{{call=\"{}\"}} {}",
+                call, histogram.num_errors
+            );
+        }
+
+        output
+    }
+}
+
+impl Default for StatusRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -3,22 +3,42 @@ use bitcoinsuite_chronik_client::proto::{
     BlockDetails, BlockInfo, SlpGenesisInfo, SlpMeta, SlpTokenType, SlpTxType, Token, Tx, Utxo,
 };
 use chrono::{DateTime, Utc};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use crate::{blockchain::Destination, server_primitives::JsonBalance};
+use crate::{
+    api::RawTokenSection,
+    balances::BalanceBreakdown,
+    meta::PageMeta,
+    preferences::Preferences,
+    server_primitives::{
+        JsonBlock, JsonCoinbaseSplit, JsonCountdowns, JsonDoubleSpentInput, JsonLargeTx,
+        JsonNewToken, JsonSlpError,
+    },
+    status::{CacheStats, LatencyHistogram, RecentError},
+};
 
 mod filters;
 
 #[derive(Template)]
 #[template(path = "pages/homepage.html")]
-pub struct HomepageTemplate {}
+pub struct HomepageTemplate {
+    /// Missing if the tip block couldn't be fetched
+    pub countdowns: Option<JsonCountdowns>,
+    pub last_block_timestamp: Option<i64>,
+    pub new_tokens: Vec<JsonNewToken>,
+}
 
 #[derive(Template)]
 #[template(path = "pages/blocks.html")]
 pub struct BlocksTemplate {
     pub last_block_height: u32,
+    pub last_block_timestamp: i64,
 }
 
+#[derive(Template)]
+#[template(path = "pages/hashrate.html")]
+pub struct HashrateTemplate {}
+
 #[derive(Template)]
 #[template(path = "pages/block.html")]
 pub struct BlockTemplate<'a> {
@@ -30,6 +50,11 @@ pub struct BlockTemplate<'a> {
     pub timestamp: DateTime<chrono::Utc>,
     pub difficulty: f64,
     pub coinbase_data: Vec<u8>,
+    pub coinbase_split: JsonCoinbaseSplit,
+    /// Version bits the block signals, as "bit N"
+    pub signaled_deployments: Vec<String>,
+    pub meta: PageMeta,
+    pub preferences: Preferences,
 }
 
 #[derive(Template)]
@@ -45,32 +70,117 @@ pub struct TransactionTemplate<'a> {
     pub slp_meta: Option<SlpMeta>,
     pub raw_tx: String,
     pub confirmations: i32,
+    pub position_in_block: Option<u32>,
     pub timestamp: DateTime<Utc>,
     pub sats_input: i64,
     pub sats_output: i64,
     pub token_input: i128,
     pub token_output: i128,
+    pub burn_scripts: &'a HashSet<Vec<u8>>,
+    pub slp_errors: Vec<JsonSlpError>,
+    pub double_spent_inputs: Vec<JsonDoubleSpentInput>,
+    /// Only for token types chronik doesn't parse
+    pub raw_token_section: Option<RawTokenSection>,
+    pub meta: PageMeta,
+    pub preferences: Preferences,
 }
 
 #[derive(Template)]
 #[template(path = "pages/address.html")]
 pub struct AddressTemplate<'a> {
     pub tokens: HashMap<String, Token>,
-    pub token_dust: i64,
-    pub total_xec: i64,
+    pub breakdown: BalanceBreakdown,
+    /// Net change of the XEC balance by unconfirmed txs
+    pub unconfirmed_xec: i64,
+    /// Whether `unconfirmed_xec` leaves out some of the unconfirmed txs
+    pub unconfirmed_truncated: bool,
+    pub num_hidden_tokens: usize,
+    /// Amount of the payment URI the page was searched by
+    pub requested_sats: Option<i64>,
+    /// Token whose history tab is open, empty for all txs
+    pub selected_token_id: &'a str,
     pub token_utxos: Vec<Utxo>,
     pub address_num_txs: u32,
     pub address: &'a str,
     pub sats_address: &'a str,
     pub token_address: &'a str,
     pub legacy_address: String,
-    pub json_balances: HashMap<String, JsonBalance>,
-    pub encoded_tokens: String,
-    pub encoded_balances: String,
+    pub token_balances: Vec<TokenBalance>,
+    pub meta: PageMeta,
+    pub preferences: Preferences,
+}
+
+#[derive(Template)]
+#[template(path = "pages/script.html")]
+pub struct ScriptTemplate {
+    pub script_hex: String,
+    pub script_kind: &'static str,
+    /// Cash address of the script, if it has one
+    pub address: Option<String>,
+    pub tokens: HashMap<String, Token>,
+    pub breakdown: BalanceBreakdown,
+    /// Net change of the XEC balance by unconfirmed txs
+    pub unconfirmed_xec: i64,
+    /// Whether `unconfirmed_xec` leaves out some of the unconfirmed txs
+    pub unconfirmed_truncated: bool,
+    pub num_hidden_tokens: usize,
+    pub script_num_txs: u32,
+    pub token_balances: Vec<TokenBalance>,
+    pub preferences: Preferences,
+}
+
+/// Totals of one token held by an address or script. The page only shows these; the UTXOs
+/// themselves are fetched from the `utxos` API once expanded.
+pub struct TokenBalance {
+    pub token_id: String,
+    pub sats_amount: i64,
+    pub token_amount: i128,
+    pub num_utxos: usize,
+}
+
+#[derive(Template)]
+#[template(path = "pages/admin.html")]
+pub struct AdminTemplate {
+    pub uptime: String,
+    pub num_requests: u64,
+    pub num_errors: u64,
+    pub chronik_tip_height: Option<i32>,
+    pub chronik_error: Option<String>,
+    pub chronik_latency_ms: u128,
+    pub cache_stats: Vec<(&'static str, CacheStats)>,
+    pub route_latencies: Vec<(String, LatencyHistogram)>,
+    pub upstream_latencies: Vec<(&'static str, LatencyHistogram)>,
+    pub recent_errors: Vec<RecentError>,
+}
+
+#[derive(Template)]
+#[template(path = "pages/api_docs.html")]
+pub struct ApiDocsTemplate {}
+
+#[derive(Template)]
+#[template(path = "feeds/large_txs.xml")]
+pub struct LargeTxsRssTemplate<'a> {
+    pub public_url: &'a str,
+    pub txs: Vec<JsonLargeTx>,
+}
+
+#[derive(Template)]
+#[template(path = "feeds/sitemap_index.xml")]
+pub struct SitemapIndexTemplate<'a> {
+    pub public_url: &'a str,
+    pub num_block_chunks: i32,
+}
+
+#[derive(Template)]
+#[template(path = "feeds/sitemap_blocks.xml")]
+pub struct SitemapBlocksTemplate<'a> {
+    pub public_url: &'a str,
+    pub blocks: Vec<JsonBlock>,
 }
 
 #[derive(Template)]
 #[template(path = "pages/error.html")]
 pub struct ErrorTemplate {
     pub message: String,
+    pub request_id: Option<String>,
 }
@@ -1,15 +1,21 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-use chrono::DateTime;
+use chrono::{DateTime, TimeZone, Utc};
 use chrono_humanize::HumanTime;
 use maud::{html, PreEscaped};
 
-use bitcoinsuite_chronik_client::proto::{OutPoint, SlpToken, Token};
+use bitcoinsuite_chronik_client::proto::{OutPoint, SlpToken, Token, TxOutput};
 use bitcoinsuite_core::Script;
 use humansize::{file_size_opts as options, FileSize};
 use num_format::{Locale, ToFormattedString};
 
-use crate::blockchain;
+use crate::{
+    api, assets,
+    blockchain::{self, Destination},
+    preferences::{AmountUnit, Preferences, UserLocale},
+    server_primitives::JsonOpReturnData,
+    translations::translate,
+};
 
 fn render_integer_with_small_flag(int: i128, smallify: bool) -> askama::Result<String> {
     let string = int.to_formatted_string(&Locale::en);
@@ -27,6 +33,16 @@ fn render_integer_with_small_flag(int: i128, smallify: bool) -> askama::Result<S
     Ok(output.into_string())
 }
 
+/// Translates a message into the visitor's language, e.g. `{{ "Blocks"|t }}`.
+pub fn t(message: &str) -> askama::Result<String> {
+    Ok(translate(message))
+}
+
+/// Fingerprinted URL of a static file, e.g. `{{ "/code/common.js"|asset|safe }}`.
+pub fn asset(path: &str) -> askama::Result<String> {
+    Ok(assets::asset_url(path))
+}
+
 pub fn max(value: &i64, maximum: &i64) -> askama::Result<i64> {
     Ok(*value.max(maximum))
 }
@@ -35,12 +51,34 @@ pub fn check_is_coinbase(outpoint: &OutPoint) -> askama::Result<bool> {
     Ok(outpoint.txid == [0; 32] && outpoint.out_idx == 0xffff_ffff)
 }
 
-pub fn destination_from_script<'a>(
-    script: &'a [u8],
-    is_token: &bool,
-) -> askama::Result<blockchain::Destination<'a>> {
+pub fn is_burned(output: &TxOutput, burn_scripts: &HashSet<Vec<u8>>) -> askama::Result<bool> {
+    const OP_RETURN: u8 = 106;
+    let is_op_return = output.output_script.first() == Some(&OP_RETURN);
+    Ok(output.value > 0 && (is_op_return || burn_scripts.contains(&output.output_script)))
+}
+
+/// Where an output script sends coins, as shown everywhere a script is listed: a link to its
+/// address, its pubkey for P2PK, "OP_RETURN data", or "Nonstandard".
+pub fn render_destination(script: &[u8], is_token: &bool) -> askama::Result<String> {
     let prefix = if *is_token { "etoken" } else { "ecash" };
-    Ok(blockchain::destination_from_script(prefix, script))
+    let script_url = format!("/script/{}", hex::encode(script));
+    let output = match blockchain::destination_from_script(prefix, script) {
+        Destination::Address(address) => html! {
+            a href=(format!("/address/{}", address.as_str())) { (address.as_str()) }
+        },
+        Destination::P2PK(pubkey) => html! {
+            a href=(script_url) { "Pubkey: " (hex::encode(&pubkey)) }
+        },
+        Destination::Nulldata(_) => html! { (translate("OP_RETURN data")) },
+        Destination::Unknown(_) => html! {
+            a href=(script_url) { (translate("Nonstandard")) }
+        },
+    };
+    Ok(output.into_string())
+}
+
+pub fn op_return_data(script: &[u8], out_idx: &usize) -> askama::Result<Option<JsonOpReturnData>> {
+    Ok(api::op_return_to_json(*out_idx as u32, script))
 }
 
 pub fn get_script(signature_script: &[u8]) -> askama::Result<String> {
@@ -59,6 +97,38 @@ pub fn human_time(timestamp: &DateTime<chrono::Utc>) -> askama::Result<HumanTime
     Ok(HumanTime::from(*timestamp))
 }
 
+/// E.g. "5 minutes ago", for raw unix timestamps, with the UTC date and time as tooltip.
+pub fn relative_time(timestamp: &i64) -> askama::Result<String> {
+    let datetime = Utc.timestamp(*timestamp, 0);
+    let output = html! {
+        span title=(datetime.format("%Y-%m-%d %H:%M:%S UTC")) {
+            (HumanTime::from(datetime))
+        }
+    };
+    Ok(output.into_string())
+}
+
+/// Date and time in the visitor's timezone and locale, with the UTC time as tooltip.
+pub fn render_datetime(
+    timestamp: &DateTime<Utc>,
+    preferences: &Preferences,
+) -> askama::Result<String> {
+    let datetime_format = preferences.locale.datetime_format();
+    let local = timestamp.with_timezone(&preferences.timezone);
+    let output = html! {
+        span title=(format!("{} UTC", timestamp.format(datetime_format))) {
+            (local.format(datetime_format)) " " (local.format("%Z"))
+        }
+    };
+    Ok(output.into_string())
+}
+
+/// Plain number with the thousands separators of `locale`.
+pub fn render_number<T: Into<i128> + Copy>(int: &T, locale: &UserLocale) -> askama::Result<String> {
+    let int: i128 = (*int).into();
+    Ok(int.to_formatted_string(&locale.number_locale()))
+}
+
 pub fn render_integer(int: &i128) -> askama::Result<String> {
     render_integer_with_small_flag(*int, false)
 }
@@ -143,6 +213,36 @@ pub fn render_sats(sats: &i64) -> askama::Result<String> {
     Ok(output)
 }
 
+/// Amount in the visitor's preferred unit, including the unit name.
+pub fn render_amount(sats: &i64, unit: &AmountUnit) -> askama::Result<String> {
+    let amount = match unit {
+        AmountUnit::Xec => render_sats(sats)?,
+        AmountUnit::Sats => render_integer(&i128::from(*sats))?,
+        AmountUnit::Bcha => render_token_amount(&i128::from(*sats), &unit.decimals())?,
+    };
+    Ok(format!("{} {}", amount, unit.name()))
+}
+
+/// Like [`render_sats`], but without markup, e.g. for feeds.
+pub fn render_sats_plain(sats: &i64) -> askama::Result<String> {
+    let coins = *sats / 100;
+    let cents = (*sats % 100).abs();
+    let integer_part = coins.to_formatted_string(&Locale::en);
+    if cents == 0 {
+        Ok(integer_part)
+    } else {
+        Ok(format!("{}.{:02}", integer_part, cents))
+    }
+}
+
+pub fn render_rfc2822(timestamp: &i64) -> askama::Result<String> {
+    Ok(Utc.timestamp(*timestamp, 0).to_rfc2822())
+}
+
+pub fn render_rfc3339(timestamp: &i64) -> askama::Result<String> {
+    Ok(Utc.timestamp(*timestamp, 0).to_rfc3339())
+}
+
 pub fn hexify_u8_vector(value: &[u8]) -> askama::Result<String> {
     Ok(hex::encode(value))
 }
@@ -151,6 +251,10 @@ pub fn string_from_lossy_utf8(value: &[u8]) -> askama::Result<String> {
     Ok(String::from_utf8_lossy(value).to_string())
 }
 
+pub fn from_hex(value: &str) -> askama::Result<Vec<u8>> {
+    hex::decode(value).map_err(|err| askama::Error::Custom(Box::new(err)))
+}
+
 pub fn to_le_hex(slice: &[u8]) -> askama::Result<String> {
     Ok(blockchain::to_be_hex(slice))
 }
@@ -0,0 +1,170 @@
+use std::{
+    collections::HashMap,
+    hash::Hash,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use bitcoinsuite_chronik_client::{proto::BlockchainInfo, ChronikClient};
+use bitcoinsuite_error::Result;
+use futures::{Stream, StreamExt};
+use tracing::warn;
+
+use crate::chain_events::ChainEvent;
+
+/// Refreshed this often even without block events, in case the WebSocket is down.
+const REFRESH_INTERVAL: Duration = Duration::from_secs(30);
+/// Older entries are refetched on access rather than trusted.
+const MAX_AGE: Duration = Duration::from_secs(90);
+
+/// Chain tip shared by all handlers, so confirmation math doesn't cost a chronik round trip
+/// per request.
+///
+/// Refreshed whenever chronik reports a block being connected or disconnected, and every
+/// [`REFRESH_INTERVAL`] as a fallback.
+pub struct TipCache {
+    tip: Mutex<Option<(BlockchainInfo, Instant)>>,
+}
+
+impl TipCache {
+    pub fn new() -> Self {
+        TipCache {
+            tip: Mutex::new(None),
+        }
+    }
+
+    pub async fn get(&self, chronik: &ChronikClient) -> Result<BlockchainInfo> {
+        if let Some((blockchain_info, fetched_at)) = self.tip.lock().unwrap().as_ref() {
+            if fetched_at.elapsed() < MAX_AGE {
+                return Ok(blockchain_info.clone());
+            }
+        }
+        self.refresh(chronik).await
+    }
+
+    pub async fn run(&self, chronik: &ChronikClient, events: impl Stream<Item = ChainEvent>) {
+        let mut events = Box::pin(events);
+        loop {
+            if let Err(err) = self.refresh(chronik).await {
+                warn!("Tip cache failed to refresh: {}", err);
+            }
+            let block_event = async {
+                while let Some(event) = events.next().await {
+                    if let ChainEvent::BlockConnected { .. }
+                    | ChainEvent::BlockDisconnected { .. } = event
+                    {
+                        return;
+                    }
+                }
+                // No more events, fall back to polling only
+                futures::future::pending::<()>().await
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(REFRESH_INTERVAL) => {}
+                _ = block_event => {}
+            }
+        }
+    }
+
+    pub async fn refresh(&self, chronik: &ChronikClient) -> Result<BlockchainInfo> {
+        let blockchain_info = chronik.blockchain_info().await?;
+        *self.tip.lock().unwrap() = Some((blockchain_info.clone(), Instant::now()));
+        Ok(blockchain_info)
+    }
+}
+
+impl Default for TipCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Results derived from the chain, kept until the tip moves.
+///
+/// Entries also expire after `max_age`, for results that include mempool txs, and once
+/// `max_entries` are held for the current tip further results aren't cached.
+pub struct PerTipCache<K, V> {
+    max_entries: usize,
+    max_age: Duration,
+    state: Mutex<PerTipState<K, V>>,
+}
+
+struct PerTipState<K, V> {
+    tip_hash: Vec<u8>,
+    entries: HashMap<K, (Arc<V>, Instant)>,
+}
+
+impl<K: Hash + Eq, V> PerTipCache<K, V> {
+    pub fn new(max_entries: usize, max_age: Duration) -> Self {
+        PerTipCache {
+            max_entries,
+            max_age,
+            state: Mutex::new(PerTipState {
+                tip_hash: Vec::new(),
+                entries: HashMap::new(),
+            }),
+        }
+    }
+
+    pub fn get(&self, tip_hash: &[u8], key: &K) -> Option<Arc<V>> {
+        let mut state = self.state.lock().unwrap();
+        let (value, computed_at) = state.at_tip(tip_hash).entries.get(key)?;
+        (computed_at.elapsed() < self.max_age).then(|| Arc::clone(value))
+    }
+
+    pub fn insert(&self, tip_hash: &[u8], key: K, value: V) -> Arc<V> {
+        let value = Arc::new(value);
+        let mut state = self.state.lock().unwrap();
+        let entries = &mut state.at_tip(tip_hash).entries;
+        if entries.len() < self.max_entries || entries.contains_key(&key) {
+            entries.insert(key, (Arc::clone(&value), Instant::now()));
+        }
+        value
+    }
+}
+
+impl<K, V> PerTipState<K, V> {
+    /// Drops all entries if the tip moved since they were computed.
+    fn at_tip(&mut self, tip_hash: &[u8]) -> &mut Self {
+        if self.tip_hash != tip_hash {
+            self.tip_hash = tip_hash.to_vec();
+            self.entries.clear();
+        }
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::PerTipCache;
+
+    #[test]
+    fn per_tip_cache_clears_on_new_tip() {
+        let cache = PerTipCache::new(10, Duration::from_secs(60));
+        cache.insert(b"tip1", "key", 1);
+        assert_eq!(cache.get(b"tip1", &"key").as_deref(), Some(&1));
+        assert_eq!(cache.get(b"tip2", &"key"), None);
+        // Going back to the old tip doesn't bring the entries back
+        assert_eq!(cache.get(b"tip1", &"key"), None);
+    }
+
+    #[test]
+    fn per_tip_cache_is_bounded() {
+        let cache = PerTipCache::new(2, Duration::from_secs(60));
+        cache.insert(b"tip", 1, "one");
+        cache.insert(b"tip", 2, "two");
+        assert_eq!(*cache.insert(b"tip", 3, "three"), "three");
+        assert_eq!(cache.get(b"tip", &3), None);
+        cache.insert(b"tip", 2, "two again");
+        assert_eq!(cache.get(b"tip", &2).as_deref(), Some(&"two again"));
+    }
+
+    #[test]
+    fn per_tip_cache_expires_entries() {
+        let cache = PerTipCache::new(10, Duration::ZERO);
+        cache.insert(b"tip", 1, 1);
+        assert_eq!(cache.get(b"tip", &1), None);
+    }
+}
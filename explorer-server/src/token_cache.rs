@@ -0,0 +1,125 @@
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bitcoinsuite_chronik_client::{proto::Token, ChronikClient};
+use bitcoinsuite_core::Sha256d;
+use bitcoinsuite_error::Result;
+use futures::future;
+use tracing::warn;
+
+use crate::status::StatusRegistry;
+
+/// Entries older than this are refetched by the background refresh.
+const REFRESH_AFTER: Duration = Duration::from_secs(10 * 60);
+const REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+/// Tokens refetched per round, so a refresh never floods chronik.
+const REFRESH_BATCH_SIZE: usize = 100;
+const MAX_ENTRIES: usize = 10_000;
+
+/// Tokens looked up from chronik, keyed by token ID.
+///
+/// The genesis info of a token never changes, but its mint and burn stats do, and a token
+/// seen in the mempool only gets its block later. So entries are refetched in the background
+/// once they're [`REFRESH_AFTER`] old rather than kept forever.
+pub struct TokenCache {
+    tokens: Mutex<HashMap<Sha256d, (Token, Instant)>>,
+}
+
+impl TokenCache {
+    pub fn new() -> Self {
+        TokenCache {
+            tokens: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Every token of `token_ids`, fetching the ones not cached yet concurrently.
+    pub async fn get_many(
+        &self,
+        chronik: &ChronikClient,
+        status: &StatusRegistry,
+        token_ids: impl IntoIterator<Item = Sha256d>,
+    ) -> Result<Vec<Token>> {
+        let mut tokens = Vec::new();
+        let mut missing = Vec::new();
+        {
+            let cached = self.tokens.lock().unwrap();
+            for token_id in token_ids {
+                match cached.get(&token_id) {
+                    Some((token, _)) => tokens.push(token.clone()),
+                    None => missing.push(token_id),
+                }
+            }
+        }
+        for _ in &tokens {
+            status.record_cache_lookup("tokens", true);
+        }
+        for _ in &missing {
+            status.record_cache_lookup("tokens", false);
+        }
+
+        let fetched = self.fetch(chronik, missing).await?;
+        tokens.extend(fetched);
+        Ok(tokens)
+    }
+
+    pub async fn run(&self, chronik: &ChronikClient) {
+        loop {
+            tokio::time::sleep(REFRESH_INTERVAL).await;
+            if let Err(err) = self.refresh_stale(chronik).await {
+                warn!("Token cache failed to refresh: {}", err);
+            }
+        }
+    }
+
+    async fn refresh_stale(&self, chronik: &ChronikClient) -> Result<()> {
+        let mut stale = self
+            .tokens
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|(_, (_, fetched_at))| fetched_at.elapsed() >= REFRESH_AFTER)
+            .map(|(token_id, (_, fetched_at))| (token_id.clone(), *fetched_at))
+            .collect::<Vec<_>>();
+        // Oldest first, so every entry gets its turn even if there are many
+        stale.sort_by_key(|(_, fetched_at)| *fetched_at);
+        let token_ids = stale
+            .into_iter()
+            .take(REFRESH_BATCH_SIZE)
+            .map(|(token_id, _)| token_id)
+            .collect();
+        self.fetch(chronik, token_ids).await?;
+        Ok(())
+    }
+
+    async fn fetch(&self, chronik: &ChronikClient, token_ids: Vec<Sha256d>) -> Result<Vec<Token>> {
+        let tokens =
+            future::try_join_all(token_ids.iter().map(|token_id| chronik.token(token_id))).await?;
+
+        let mut cached = self.tokens.lock().unwrap();
+        let now = Instant::now();
+        for (token_id, token) in token_ids.into_iter().zip(&tokens) {
+            cached.insert(token_id, (token.clone(), now));
+        }
+        if cached.len() > MAX_ENTRIES {
+            // Evict the least recently fetched half rather than one entry per insert
+            let mut fetched_ats = cached
+                .values()
+                .map(|(_, fetched_at)| *fetched_at)
+                .collect::<Vec<_>>();
+            fetched_ats.sort_unstable();
+            let cutoff = fetched_ats[fetched_ats.len() / 2];
+            cached.retain(|_, (_, fetched_at)| *fetched_at >= cutoff);
+        }
+
+        Ok(tokens)
+    }
+}
+
+impl Default for TokenCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,38 @@
+use std::collections::HashSet;
+
+use crate::config::TokenFilterConfig;
+
+/// Decides which tokens count as spam, e.g. unsolicited airdrops that would otherwise bury the
+/// real balances of an address.
+pub struct TokenFilter {
+    blocklist: HashSet<String>,
+    allowlist: HashSet<String>,
+    hide_unlisted: bool,
+}
+
+impl TokenFilter {
+    pub fn new(config: &TokenFilterConfig) -> Self {
+        TokenFilter {
+            blocklist: config
+                .blocklist
+                .iter()
+                .map(|id| id.to_lowercase())
+                .collect(),
+            allowlist: config
+                .allowlist
+                .iter()
+                .map(|id| id.to_lowercase())
+                .collect(),
+            hide_unlisted: config.hide_unlisted,
+        }
+    }
+
+    /// Allowlisted tokens are never spam; blocklisted ones always are, and with
+    /// `hide_unlisted` so is everything not on the allowlist.
+    pub fn is_spam(&self, token_id_hex: &str) -> bool {
+        if self.allowlist.contains(token_id_hex) {
+            return false;
+        }
+        self.hide_unlisted || self.blocklist.contains(token_id_hex)
+    }
+}
@@ -0,0 +1,111 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use bitcoinsuite_chronik_client::proto::{BlockDetails, BlockInfo, SlpTokenType, SlpTxType, Tx};
+
+use crate::{
+    block_follower::BlockConsumer,
+    blockchain::to_be_hex,
+    server_primitives::{JsonNewToken, JsonToken},
+};
+
+/// How far back the feed is backfilled on startup, about a week.
+const INITIAL_BLOCKS: i32 = 1008;
+const MAX_TOKENS: usize = 1000;
+
+/// Tokens created by recently confirmed GENESIS txs, newest first.
+///
+/// Kept in memory only and backfilled from the last [`INITIAL_BLOCKS`] blocks on startup.
+/// Tokens of reorged blocks are dropped and the replacement blocks are scanned again.
+pub struct TokenGenesisFeed {
+    tokens: Mutex<VecDeque<JsonNewToken>>,
+}
+
+impl TokenGenesisFeed {
+    pub fn new() -> Self {
+        TokenGenesisFeed {
+            tokens: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// The newest `limit` tokens for which `keep` returns true.
+    pub fn tokens(&self, limit: usize, keep: impl Fn(&JsonNewToken) -> bool) -> Vec<JsonNewToken> {
+        let tokens = self.tokens.lock().unwrap();
+        tokens
+            .iter()
+            .filter(|token| keep(token))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+}
+
+impl BlockConsumer for TokenGenesisFeed {
+    fn initial_blocks(&self) -> i32 {
+        INITIAL_BLOCKS
+    }
+
+    fn add_block(&self, block_info: &BlockInfo, _block_details: &BlockDetails, txs: &[Tx]) {
+        let new_tokens = txs
+            .iter()
+            .filter_map(|tx| genesis_token(tx, block_info.height, block_info.timestamp));
+
+        let mut tokens = self.tokens.lock().unwrap();
+        for new_token in new_tokens {
+            tokens.push_front(new_token);
+        }
+        tokens.truncate(MAX_TOKENS);
+    }
+
+    fn remove_blocks_from(&self, height: i32) {
+        let mut tokens = self.tokens.lock().unwrap();
+        tokens.retain(|token| token.block_height < height);
+    }
+}
+
+impl Default for TokenGenesisFeed {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The token created by `tx`, if it's a valid GENESIS.
+fn genesis_token(tx: &Tx, block_height: i32, timestamp: i64) -> Option<JsonNewToken> {
+    let slp_tx_data = tx.slp_tx_data.as_ref()?;
+    let slp_meta = slp_tx_data.slp_meta.as_ref()?;
+    let genesis_info = slp_tx_data.genesis_info.as_ref()?;
+    if slp_meta.tx_type() != SlpTxType::Genesis
+        || slp_meta.token_type() == SlpTokenType::UnknownTokenType
+    {
+        return None;
+    }
+
+    let initial_supply = tx
+        .outputs
+        .iter()
+        .filter_map(|output| output.slp_token.as_ref())
+        .map(|slp_token| slp_token.amount as i128)
+        .sum();
+    let has_mint_baton = tx
+        .outputs
+        .iter()
+        .filter_map(|output| output.slp_token.as_ref())
+        .any(|slp_token| slp_token.is_mint_baton);
+
+    Some(JsonNewToken {
+        token: JsonToken {
+            token_id: hex::encode(&slp_meta.token_id),
+            token_type: slp_meta.token_type as u32,
+            token_ticker: String::from_utf8_lossy(&genesis_info.token_ticker).to_string(),
+            token_name: String::from_utf8_lossy(&genesis_info.token_name).to_string(),
+            decimals: genesis_info.decimals,
+            // Only NFT children belong to a group
+            group_id: (!slp_meta.group_token_id.is_empty())
+                .then(|| hex::encode(&slp_meta.group_token_id)),
+        },
+        tx_hash: to_be_hex(&tx.txid),
+        block_height,
+        timestamp,
+        initial_supply,
+        has_mint_baton,
+    })
+}
@@ -0,0 +1,402 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use bitcoinsuite_chronik_client::proto::{BlockDetails, BlockInfo, SlpTokenType, Tx};
+
+use crate::{
+    block_follower::BlockConsumer,
+    blockchain::to_be_hex,
+    server_primitives::{JsonTokenStatsPoint, JsonTokenTransfer},
+};
+
+/// How many days of token activity are kept, which is also the largest `window`.
+pub const MAX_WINDOW_DAYS: i64 = 30;
+/// Largest transfers of each token kept per block, which is also the most that can be listed.
+pub const MAX_TOP_TRANSFERS: usize = 50;
+/// How far back the stats are backfilled on startup, a bit over [`MAX_WINDOW_DAYS`] days.
+const INITIAL_BLOCKS: i32 = 4464;
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Daily transfer volume, tx counts and largest transfers of every token, over the last
+/// [`MAX_WINDOW_DAYS`] days.
+///
+/// Kept in memory only and backfilled from the last [`INITIAL_BLOCKS`] blocks on startup.
+/// Activity is stored per block, so reorged blocks can simply be dropped and scanned again.
+pub struct TokenStats {
+    /// Token activity of each scanned block, oldest first
+    blocks: Mutex<VecDeque<BlockActivity>>,
+}
+
+struct BlockActivity {
+    height: i32,
+    timestamp: i64,
+    /// Activity of every token in the block, by token ID hex
+    tokens: HashMap<String, TokenActivity>,
+}
+
+#[derive(Default)]
+struct TokenActivity {
+    /// Token amount sent by all txs, change excluded
+    volume: i128,
+    num_txs: u32,
+    /// Sent token amount and txid of the largest txs, at most [`MAX_TOP_TRANSFERS`]
+    top_transfers: Vec<(i128, Vec<u8>)>,
+}
+
+impl TokenStats {
+    pub fn new() -> Self {
+        TokenStats {
+            blocks: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// One point per UTC day of the last `window_days` days up to `now`, oldest first, and
+    /// whether the scanned blocks cover the whole window yet.
+    pub fn daily_stats(
+        &self,
+        token_id_hex: &str,
+        window_days: i64,
+        now: i64,
+    ) -> (Vec<JsonTokenStatsPoint>, bool) {
+        let last_day = now.div_euclid(SECONDS_PER_DAY);
+        let first_day = last_day - window_days + 1;
+        let mut points = (first_day..=last_day)
+            .map(|day| JsonTokenStatsPoint {
+                timestamp: day * SECONDS_PER_DAY,
+                volume: 0,
+                num_txs: 0,
+            })
+            .collect::<Vec<_>>();
+
+        let blocks = self.blocks.lock().unwrap();
+        for block in blocks.iter() {
+            let day = block.timestamp.div_euclid(SECONDS_PER_DAY);
+            if day < first_day || day > last_day {
+                continue;
+            }
+            if let Some(activity) = block.tokens.get(token_id_hex) {
+                let point = &mut points[(day - first_day) as usize];
+                point.volume += activity.volume;
+                point.num_txs += activity.num_txs;
+            }
+        }
+        let is_complete = covers_window(&blocks, first_day);
+
+        (points, is_complete)
+    }
+
+    /// The `limit` largest transfers of a token in the last `window_days` days up to `now`,
+    /// largest first, and whether the scanned blocks cover the whole window yet.
+    pub fn top_transfers(
+        &self,
+        token_id_hex: &str,
+        window_days: i64,
+        now: i64,
+        limit: usize,
+    ) -> (Vec<JsonTokenTransfer>, bool) {
+        let first_day = now.div_euclid(SECONDS_PER_DAY) - window_days + 1;
+
+        let blocks = self.blocks.lock().unwrap();
+        let mut transfers = blocks
+            .iter()
+            .filter(|block| block.timestamp.div_euclid(SECONDS_PER_DAY) >= first_day)
+            .filter_map(|block| Some((block, block.tokens.get(token_id_hex)?)))
+            .flat_map(|(block, activity)| {
+                activity
+                    .top_transfers
+                    .iter()
+                    .map(move |(amount, txid)| JsonTokenTransfer {
+                        tx_hash: to_be_hex(txid),
+                        block_height: block.height,
+                        timestamp: block.timestamp,
+                        amount: *amount,
+                    })
+            })
+            .collect::<Vec<_>>();
+        transfers.sort_by(|a, b| b.amount.cmp(&a.amount));
+        transfers.truncate(limit);
+
+        (transfers, covers_window(&blocks, first_day))
+    }
+}
+
+impl BlockConsumer for TokenStats {
+    fn initial_blocks(&self) -> i32 {
+        INITIAL_BLOCKS
+    }
+
+    fn add_block(&self, block_info: &BlockInfo, _block_details: &BlockDetails, txs: &[Tx]) {
+        let mut tokens = HashMap::<String, TokenActivity>::new();
+        for tx in txs {
+            let (token_id_hex, amount) = match token_output(tx) {
+                Some(token_output) => token_output,
+                None => continue,
+            };
+            let activity = tokens.entry(token_id_hex).or_default();
+            activity.volume += amount;
+            activity.num_txs += 1;
+            activity.top_transfers.push((amount, tx.txid.clone()));
+        }
+        for activity in tokens.values_mut() {
+            activity.top_transfers.sort_by(|a, b| b.0.cmp(&a.0));
+            activity.top_transfers.truncate(MAX_TOP_TRANSFERS);
+        }
+
+        let mut blocks = self.blocks.lock().unwrap();
+        blocks.push_back(BlockActivity {
+            height: block_info.height,
+            timestamp: block_info.timestamp,
+            tokens,
+        });
+        while blocks.len() > INITIAL_BLOCKS as usize {
+            blocks.pop_front();
+        }
+    }
+
+    fn remove_blocks_from(&self, height: i32) {
+        let mut blocks = self.blocks.lock().unwrap();
+        blocks.retain(|block| block.height < height);
+    }
+}
+
+/// Whether the scanned blocks reach back to the start of the day `first_day`.
+fn covers_window(blocks: &VecDeque<BlockActivity>, first_day: i64) -> bool {
+    blocks.front().map_or(false, |block| {
+        block.timestamp <= first_day * SECONDS_PER_DAY
+    })
+}
+
+impl Default for TokenStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Token ID hex and the token amount `tx` sends, if it's a valid token tx. Outputs back to
+/// one of the scripts spent from are change and don't count.
+fn token_output(tx: &Tx) -> Option<(String, i128)> {
+    let slp_tx_data = tx.slp_tx_data.as_ref()?;
+    let slp_meta = slp_tx_data.slp_meta.as_ref()?;
+    if slp_meta.token_type() == SlpTokenType::UnknownTokenType {
+        return None;
+    }
+    let amount = tx
+        .outputs
+        .iter()
+        .filter(|output| {
+            !tx.inputs
+                .iter()
+                .any(|input| input.output_script == output.output_script)
+        })
+        .filter_map(|output| output.slp_token.as_ref())
+        .map(|slp_token| slp_token.amount as i128)
+        .sum();
+    Some((hex::encode(&slp_meta.token_id), amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_chronik_client::proto::{
+        BlockDetails, BlockInfo, SlpMeta, SlpToken, SlpTokenType, SlpTxData, Tx, TxInput, TxOutput,
+    };
+
+    use super::{TokenStats, SECONDS_PER_DAY};
+    use crate::{block_follower::BlockConsumer, server_primitives::JsonTokenStatsPoint};
+
+    const TOKEN_ID: [u8; 32] = [1; 32];
+    const OTHER_TOKEN_ID: [u8; 32] = [2; 32];
+    /// Some day, as days since the epoch
+    const DAY: i64 = 19_000;
+
+    /// Token tx spending from script `[sender]` to scripts `[recipient]`.
+    fn token_tx(txid: u8, token_id: [u8; 32], sender: u8, outputs: &[(u8, u64)]) -> Tx {
+        Tx {
+            txid: vec![txid; 32],
+            inputs: vec![TxInput {
+                output_script: vec![sender],
+                ..Default::default()
+            }],
+            outputs: outputs
+                .iter()
+                .map(|&(recipient, amount)| TxOutput {
+                    output_script: vec![recipient],
+                    slp_token: Some(SlpToken {
+                        amount,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                })
+                .collect(),
+            slp_tx_data: Some(SlpTxData {
+                slp_meta: Some(SlpMeta {
+                    token_type: SlpTokenType::Fungible as i32,
+                    token_id: token_id.to_vec(),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn add_block(stats: &TokenStats, height: i32, timestamp: i64, txs: &[Tx]) {
+        let block_info = BlockInfo {
+            height,
+            timestamp,
+            ..Default::default()
+        };
+        stats.add_block(&block_info, &BlockDetails::default(), txs);
+    }
+
+    fn point(day: i64, volume: i128, num_txs: u32) -> JsonTokenStatsPoint {
+        JsonTokenStatsPoint {
+            timestamp: day * SECONDS_PER_DAY,
+            volume,
+            num_txs,
+        }
+    }
+
+    #[test]
+    fn volume_excludes_change() {
+        let stats = TokenStats::new();
+        let tx = token_tx(1, TOKEN_ID, 0xaa, &[(0xbb, 30), (0xaa, 70)]);
+        add_block(&stats, 100, DAY * SECONDS_PER_DAY, &[tx]);
+
+        let (points, _) = stats.daily_stats(&hex::encode(TOKEN_ID), 1, DAY * SECONDS_PER_DAY);
+        assert_eq!(points, vec![point(DAY, 30, 1)]);
+    }
+
+    #[test]
+    fn daily_stats_group_blocks_by_day() {
+        let stats = TokenStats::new();
+        let yesterday = (DAY - 1) * SECONDS_PER_DAY;
+        let today = DAY * SECONDS_PER_DAY;
+        add_block(
+            &stats,
+            100,
+            yesterday + 60,
+            &[token_tx(1, TOKEN_ID, 0xaa, &[(0xbb, 10)])],
+        );
+        add_block(
+            &stats,
+            101,
+            today + 60,
+            &[
+                token_tx(2, TOKEN_ID, 0xaa, &[(0xbb, 20)]),
+                token_tx(3, OTHER_TOKEN_ID, 0xaa, &[(0xbb, 5)]),
+                Tx::default(),
+            ],
+        );
+        add_block(
+            &stats,
+            102,
+            today + 120,
+            &[token_tx(4, TOKEN_ID, 0xbb, &[(0xcc, 1)])],
+        );
+
+        let (points, is_complete) = stats.daily_stats(&hex::encode(TOKEN_ID), 3, today + 600);
+        assert_eq!(
+            points,
+            vec![
+                point(DAY - 2, 0, 0),
+                point(DAY - 1, 10, 1),
+                point(DAY, 21, 2)
+            ],
+        );
+        assert!(!is_complete);
+
+        let (points, is_complete) = stats.daily_stats(&hex::encode(TOKEN_ID), 1, today + 600);
+        assert_eq!(points, vec![point(DAY, 21, 2)]);
+        assert!(is_complete);
+    }
+
+    #[test]
+    fn removed_blocks_no_longer_count() {
+        let stats = TokenStats::new();
+        let today = DAY * SECONDS_PER_DAY;
+        add_block(
+            &stats,
+            100,
+            today,
+            &[token_tx(1, TOKEN_ID, 0xaa, &[(0xbb, 10)])],
+        );
+        add_block(
+            &stats,
+            101,
+            today,
+            &[token_tx(2, TOKEN_ID, 0xaa, &[(0xbb, 20)])],
+        );
+        stats.remove_blocks_from(101);
+
+        let (points, _) = stats.daily_stats(&hex::encode(TOKEN_ID), 1, today);
+        assert_eq!(points, vec![point(DAY, 10, 1)]);
+    }
+
+    #[test]
+    fn top_transfers_largest_first_without_change() {
+        let stats = TokenStats::new();
+        let today = DAY * SECONDS_PER_DAY;
+        add_block(
+            &stats,
+            100,
+            today,
+            &[
+                // Sends 5, the 95 back to the sender are change
+                token_tx(1, TOKEN_ID, 0xaa, &[(0xbb, 5), (0xaa, 95)]),
+                token_tx(2, TOKEN_ID, 0xaa, &[(0xbb, 40)]),
+                token_tx(3, OTHER_TOKEN_ID, 0xaa, &[(0xbb, 1000)]),
+            ],
+        );
+        add_block(
+            &stats,
+            101,
+            today + 60,
+            &[token_tx(4, TOKEN_ID, 0xcc, &[(0xdd, 20), (0xee, 3)])],
+        );
+
+        let (transfers, is_complete) =
+            stats.top_transfers(&hex::encode(TOKEN_ID), 1, today + 600, 10);
+        let summary = transfers
+            .iter()
+            .map(|transfer| (transfer.amount, transfer.block_height))
+            .collect::<Vec<_>>();
+        assert_eq!(summary, vec![(40, 100), (23, 101), (5, 100)]);
+        assert_eq!(transfers[0].tx_hash, hex::encode([2; 32]));
+        assert_eq!(transfers[1].timestamp, today + 60);
+        assert!(is_complete);
+
+        let (transfers, _) = stats.top_transfers(&hex::encode(TOKEN_ID), 1, today + 600, 2);
+        assert_eq!(transfers.len(), 2);
+        assert_eq!(transfers[1].amount, 23);
+    }
+
+    #[test]
+    fn top_transfers_within_window() {
+        let stats = TokenStats::new();
+        let today = DAY * SECONDS_PER_DAY;
+        add_block(
+            &stats,
+            100,
+            today - 2 * SECONDS_PER_DAY,
+            &[token_tx(1, TOKEN_ID, 0xaa, &[(0xbb, 500)])],
+        );
+        add_block(
+            &stats,
+            101,
+            today,
+            &[token_tx(2, TOKEN_ID, 0xaa, &[(0xbb, 7)])],
+        );
+
+        let (transfers, _) = stats.top_transfers(&hex::encode(TOKEN_ID), 1, today, 10);
+        assert_eq!(transfers.len(), 1);
+        assert_eq!(transfers[0].amount, 7);
+
+        let (transfers, _) = stats.top_transfers(&hex::encode(TOKEN_ID), 3, today, 10);
+        assert_eq!(transfers[0].amount, 500);
+
+        let (transfers, _) = stats.top_transfers(&hex::encode(OTHER_TOKEN_ID), 3, today, 10);
+        assert!(transfers.is_empty());
+    }
+}
@@ -0,0 +1,51 @@
+use std::{collections::HashMap, future::Future, path::Path, sync::Arc};
+
+use bitcoinsuite_error::Result;
+
+use crate::preferences::UserLocale;
+
+/// Messages of one language, keyed by their English source text (gettext-style), so anything
+/// missing from a catalog simply falls back to English.
+pub type Catalog = HashMap<String, String>;
+
+pub struct Translations {
+    catalogs: HashMap<UserLocale, Arc<Catalog>>,
+}
+
+tokio::task_local! {
+    static CATALOG: Arc<Catalog>;
+}
+
+impl Translations {
+    /// Loads `<locales_dir>/<language>.toml` for every supported locale that has one.
+    pub fn load(locales_dir: &Path) -> Result<Self> {
+        let mut catalogs = HashMap::new();
+        for locale in UserLocale::ALL {
+            let path = locales_dir.join(format!("{}.toml", locale.tag()));
+            if !path.exists() {
+                continue;
+            }
+            let catalog: Catalog = toml::from_str(&std::fs::read_to_string(&path)?)?;
+            catalogs.insert(locale, Arc::new(catalog));
+        }
+        Ok(Translations { catalogs })
+    }
+
+    pub fn catalog(&self, locale: UserLocale) -> Option<Arc<Catalog>> {
+        self.catalogs.get(&locale).cloned()
+    }
+}
+
+/// Runs `future` (usually the rest of the request) with `catalog` used by [`translate`].
+pub async fn with_catalog<F: Future>(catalog: Arc<Catalog>, future: F) -> F::Output {
+    CATALOG.scope(catalog, future).await
+}
+
+/// `message` in the current request's language, or unchanged if there's no translation.
+pub fn translate(message: &str) -> String {
+    CATALOG
+        .try_with(|catalog| catalog.get(message).cloned())
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| message.to_string())
+}
@@ -0,0 +1,124 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use bitcoinsuite_chronik_client::proto::Tx;
+
+/// Upper bound on the positions held, about 40 MB; the largest blocks have a few 10k txs.
+const MAX_POSITIONS: usize = 500_000;
+
+/// Position of each tx within its block, by txid.
+pub type TxPositions = HashMap<Vec<u8>, u32>;
+
+/// Tx positions of recently looked up blocks, by block hash.
+///
+/// Chronik doesn't store where in its block a tx is, so finding out means fetching the
+/// whole block. A block's txs never change, so entries never go stale; the oldest blocks
+/// are dropped once more than [`MAX_POSITIONS`] positions are held.
+pub struct TxPositionCache {
+    state: Mutex<CacheState>,
+}
+
+#[derive(Default)]
+struct CacheState {
+    blocks: HashMap<Vec<u8>, Arc<TxPositions>>,
+    /// Block hashes, oldest first
+    insertion_order: VecDeque<Vec<u8>>,
+    num_positions: usize,
+}
+
+impl TxPositionCache {
+    pub fn new() -> Self {
+        TxPositionCache {
+            state: Mutex::new(CacheState::default()),
+        }
+    }
+
+    pub fn get(&self, block_hash: &[u8]) -> Option<Arc<TxPositions>> {
+        let state = self.state.lock().unwrap();
+        state.blocks.get(block_hash).cloned()
+    }
+
+    /// Remembers the positions of the txs of a block, `txs` being all of them in order.
+    pub fn insert(&self, block_hash: &[u8], txs: &[Tx]) -> Arc<TxPositions> {
+        let positions = Arc::new(
+            txs.iter()
+                .enumerate()
+                .map(|(position, tx)| (tx.txid.clone(), position as u32))
+                .collect::<TxPositions>(),
+        );
+
+        let mut state = self.state.lock().unwrap();
+        if state.blocks.contains_key(block_hash) {
+            return positions;
+        }
+        state.num_positions += positions.len();
+        state
+            .blocks
+            .insert(block_hash.to_vec(), Arc::clone(&positions));
+        state.insertion_order.push_back(block_hash.to_vec());
+        while state.num_positions > MAX_POSITIONS && state.insertion_order.len() > 1 {
+            let oldest_hash = state.insertion_order.pop_front().unwrap();
+            if let Some(oldest) = state.blocks.remove(&oldest_hash) {
+                state.num_positions -= oldest.len();
+            }
+        }
+        positions
+    }
+}
+
+impl Default for TxPositionCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_chronik_client::proto::Tx;
+
+    use super::{TxPositionCache, MAX_POSITIONS};
+
+    fn txs(first_txid: u32, num_txs: usize) -> Vec<Tx> {
+        (first_txid..)
+            .take(num_txs)
+            .map(|txid| Tx {
+                txid: txid.to_le_bytes().to_vec(),
+                ..Default::default()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn positions_follow_block_order() {
+        let cache = TxPositionCache::new();
+        cache.insert(b"block", &txs(7, 3));
+        let positions = cache.get(b"block").unwrap();
+        assert_eq!(positions.get(&7u32.to_le_bytes().to_vec()), Some(&0));
+        assert_eq!(positions.get(&9u32.to_le_bytes().to_vec()), Some(&2));
+        assert_eq!(positions.get(&10u32.to_le_bytes().to_vec()), None);
+        assert!(cache.get(b"other block").is_none());
+    }
+
+    #[test]
+    fn evicts_oldest_blocks_beyond_capacity() {
+        let cache = TxPositionCache::new();
+        let half = MAX_POSITIONS / 2;
+        cache.insert(b"first", &txs(0, half));
+        cache.insert(b"second", &txs(0, half));
+        assert!(cache.get(b"first").is_some());
+
+        cache.insert(b"third", &txs(0, 1));
+        assert!(cache.get(b"first").is_none());
+        assert!(cache.get(b"second").is_some());
+        assert!(cache.get(b"third").is_some());
+    }
+
+    #[test]
+    fn keeps_a_single_oversized_block() {
+        let cache = TxPositionCache::new();
+        cache.insert(b"huge", &txs(0, MAX_POSITIONS + 1));
+        assert_eq!(cache.get(b"huge").unwrap().len(), MAX_POSITIONS + 1);
+    }
+}
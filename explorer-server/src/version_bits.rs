@@ -0,0 +1,166 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use bitcoinsuite_chronik_client::proto::{BlockDetails, BlockInfo, Tx};
+
+use crate::{
+    block_follower::BlockConsumer,
+    server_primitives::{JsonSignaling, JsonSignalingBit},
+};
+
+/// Most blocks signaling can be counted over, about four weeks; also how many are kept.
+pub const MAX_SIGNALING_WINDOW: usize = 4032;
+
+const VERSION_BITS_TOP_MASK: u32 = 0xe000_0000;
+const VERSION_BITS_TOP_BITS: u32 = 0x2000_0000;
+const NUM_VERSION_BITS: u8 = 29;
+
+/// Bits signaled by a block version, lowest first; empty unless the version uses the BIP9
+/// top bits.
+///
+/// eCash schedules its upgrades by median time past, not version bits, so the bits aren't
+/// mapped to deployment names.
+pub fn signaled_bits(version: i32) -> Vec<u8> {
+    let version = version as u32;
+    if version & VERSION_BITS_TOP_MASK != VERSION_BITS_TOP_BITS {
+        return Vec::new();
+    }
+    (0..NUM_VERSION_BITS)
+        .filter(|bit| version & (1 << bit) != 0)
+        .collect()
+}
+
+/// Versions of the last [`MAX_SIGNALING_WINDOW`] blocks, to count how many signal each bit.
+///
+/// Chronik only returns the version along with the whole block, so this is kept in memory and
+/// backfilled on startup rather than fetched per request.
+pub struct VersionBitsTracker {
+    /// Height and version of the scanned blocks, oldest first
+    versions: Mutex<VecDeque<(i32, i32)>>,
+}
+
+impl VersionBitsTracker {
+    pub fn new() -> Self {
+        VersionBitsTracker {
+            versions: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// How many of the last `window` scanned blocks signal each bit.
+    pub fn signaling(&self, window: usize) -> JsonSignaling {
+        let versions = self.versions.lock().unwrap();
+        let window_versions = versions.range(versions.len().saturating_sub(window)..);
+        let mut bits: Vec<JsonSignalingBit> = Vec::new();
+        for (_, version) in window_versions.clone() {
+            for bit in signaled_bits(*version) {
+                match bits.iter_mut().find(|entry| entry.bit == bit) {
+                    Some(entry) => entry.num_blocks += 1,
+                    None => bits.push(JsonSignalingBit { bit, num_blocks: 1 }),
+                }
+            }
+        }
+        bits.sort_by_key(|entry| entry.bit);
+
+        JsonSignaling {
+            window,
+            start_height: window_versions
+                .clone()
+                .next()
+                .map_or(0, |(height, _)| *height),
+            end_height: window_versions
+                .clone()
+                .next_back()
+                .map_or(0, |(height, _)| *height),
+            num_blocks: window_versions.len(),
+            bits,
+        }
+    }
+}
+
+impl BlockConsumer for VersionBitsTracker {
+    fn initial_blocks(&self) -> i32 {
+        MAX_SIGNALING_WINDOW as i32
+    }
+
+    fn add_block(&self, block_info: &BlockInfo, block_details: &BlockDetails, _txs: &[Tx]) {
+        let mut versions = self.versions.lock().unwrap();
+        versions.push_back((block_info.height, block_details.version));
+        while versions.len() > MAX_SIGNALING_WINDOW {
+            versions.pop_front();
+        }
+    }
+
+    fn remove_blocks_from(&self, height: i32) {
+        let mut versions = self.versions.lock().unwrap();
+        versions.retain(|(block_height, _)| *block_height < height);
+    }
+}
+
+impl Default for VersionBitsTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bitcoinsuite_chronik_client::proto::{BlockDetails, BlockInfo};
+
+    use super::{signaled_bits, VersionBitsTracker};
+    use crate::block_follower::BlockConsumer;
+
+    fn add_block(tracker: &VersionBitsTracker, height: i32, version: i32) {
+        let block_info = BlockInfo {
+            height,
+            ..Default::default()
+        };
+        let block_details = BlockDetails {
+            version,
+            ..Default::default()
+        };
+        tracker.add_block(&block_info, &block_details, &[]);
+    }
+
+    #[test]
+    fn decodes_top_bits_versions_only() {
+        assert_eq!(signaled_bits(0x2000_0000), Vec::<u8>::new());
+        assert_eq!(signaled_bits(0x2000_0005), vec![0, 2]);
+        assert_eq!(signaled_bits(0x3000_0001), Vec::<u8>::new());
+        assert_eq!(signaled_bits(4), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn counts_bits_over_window() {
+        let tracker = VersionBitsTracker::new();
+        add_block(&tracker, 100, 0x2000_0001);
+        add_block(&tracker, 101, 0x2000_0003);
+        add_block(&tracker, 102, 0x2000_0002);
+
+        let signaling = tracker.signaling(2);
+        assert_eq!(signaling.start_height, 101);
+        assert_eq!(signaling.end_height, 102);
+        assert_eq!(signaling.num_blocks, 2);
+        let bits = signaling
+            .bits
+            .iter()
+            .map(|entry| (entry.bit, entry.num_blocks))
+            .collect::<Vec<_>>();
+        assert_eq!(bits, vec![(0, 1), (1, 2)]);
+
+        let signaling = tracker.signaling(10);
+        assert_eq!(signaling.start_height, 100);
+        assert_eq!(signaling.num_blocks, 3);
+    }
+
+    #[test]
+    fn drops_reorged_blocks() {
+        let tracker = VersionBitsTracker::new();
+        add_block(&tracker, 100, 0x2000_0001);
+        add_block(&tracker, 101, 0x2000_0002);
+        tracker.remove_blocks_from(101);
+
+        let signaling = tracker.signaling(10);
+        assert_eq!(signaling.end_height, 100);
+        assert_eq!(signaling.bits.len(), 1);
+        assert_eq!(signaling.bits[0].bit, 0);
+    }
+}
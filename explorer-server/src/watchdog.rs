@@ -0,0 +1,216 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use bitcoinsuite_error::Result;
+use serde::Serialize;
+
+use crate::config::WatchdogConfig;
+
+/// Whether the explorer keeps up with the chain: chronik's tip has to keep advancing, and the
+/// in-memory block feeds have to stay close to it.
+///
+/// Starts out not ready, so `/ready` only passes once the feeds have caught up after startup.
+/// Alerts only fire when a ready explorer becomes unhealthy and when it recovers.
+pub struct Watchdog {
+    config: WatchdogConfig,
+    state: Mutex<WatchdogState>,
+    /// Gives up on the alert webhook after [`ALERT_TIMEOUT`], so a hanging webhook can't hold
+    /// up the checks
+    http_client: reqwest::Client,
+}
+
+const ALERT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct WatchdogState {
+    /// Last tip height seen and when it last changed
+    last_tip: Option<(i32, Instant)>,
+    problems: Vec<String>,
+    has_been_ready: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct AlertPayload<'a> {
+    is_healthy: bool,
+    problems: &'a [String],
+}
+
+impl Watchdog {
+    pub fn new(config: WatchdogConfig) -> Result<Self> {
+        Ok(Watchdog {
+            config,
+            state: Mutex::new(WatchdogState {
+                last_tip: None,
+                problems: vec!["Starting up".to_string()],
+                has_been_ready: false,
+            }),
+            http_client: reqwest::Client::builder().timeout(ALERT_TIMEOUT).build()?,
+        })
+    }
+
+    pub fn check_interval(&self) -> Duration {
+        Duration::from_secs(self.config.check_interval_secs)
+    }
+
+    /// Empty if healthy, otherwise what's wrong.
+    pub fn problems(&self) -> Vec<String> {
+        self.state.lock().unwrap().problems.clone()
+    }
+
+    /// Records a check of chronik's tip and the height each feed has scanned up to; returns the
+    /// new health if it changed in a way worth alerting about.
+    pub fn record_check(
+        &self,
+        tip_height: i32,
+        scanned_heights: &[(&'static str, Option<i32>)],
+    ) -> Option<bool> {
+        let mut state = self.state.lock().unwrap();
+        let mut problems = Vec::new();
+
+        let tip_changed_at = match state.last_tip {
+            Some((last_height, changed_at)) if last_height == tip_height => changed_at,
+            _ => Instant::now(),
+        };
+        state.last_tip = Some((tip_height, tip_changed_at));
+        let stalled_for = tip_changed_at.elapsed();
+        if stalled_for > Duration::from_secs(self.config.max_stall_secs) {
+            problems.push(format!(
+                "Chain tip stuck at height {} for {} min",
+                tip_height,
+                stalled_for.as_secs() / 60
+            ));
+        }
+
+        for (name, scanned_height) in scanned_heights {
+            match scanned_height {
+                Some(scanned_height)
+                    if tip_height - scanned_height > self.config.max_lag_blocks =>
+                {
+                    problems.push(format!(
+                        "{} is {} blocks behind the tip",
+                        name,
+                        tip_height - scanned_height
+                    ))
+                }
+                Some(_) => {}
+                None => problems.push(format!("{} hasn't scanned any blocks yet", name)),
+            }
+        }
+
+        update_problems(&mut state, problems)
+    }
+
+    /// Records that chronik couldn't be asked for its tip.
+    pub fn record_failed_check(&self, error: &str) -> Option<bool> {
+        let mut state = self.state.lock().unwrap();
+        update_problems(&mut state, vec![format!("Chronik unreachable: {}", error)])
+    }
+
+    /// Posts the current health to the configured webhook, if any.
+    pub async fn alert(&self) -> Result<()> {
+        let url = match &self.config.alert_webhook_url {
+            Some(url) => url,
+            None => return Ok(()),
+        };
+        let problems = self.problems();
+        let payload = AlertPayload {
+            is_healthy: problems.is_empty(),
+            problems: &problems,
+        };
+        self.http_client
+            .post(url)
+            .json(&payload)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+fn update_problems(state: &mut WatchdogState, problems: Vec<String>) -> Option<bool> {
+    let was_healthy = state.problems.is_empty();
+    let is_healthy = problems.is_empty();
+    state.problems = problems;
+    let should_alert = state.has_been_ready && was_healthy != is_healthy;
+    state.has_been_ready |= is_healthy;
+    should_alert.then(|| is_healthy)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Watchdog;
+    use crate::config::WatchdogConfig;
+
+    fn watchdog() -> Watchdog {
+        Watchdog::new(WatchdogConfig {
+            max_lag_blocks: 6,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn starts_not_ready_without_alerting() {
+        let watchdog = watchdog();
+        assert_eq!(watchdog.problems(), ["Starting up"]);
+
+        let alert = watchdog.record_check(100, &[("Large txs", None)]);
+        assert_eq!(alert, None);
+        assert_eq!(
+            watchdog.problems(),
+            ["Large txs hasn't scanned any blocks yet"]
+        );
+
+        // Becoming ready the first time isn't a recovery
+        assert_eq!(
+            watchdog.record_check(100, &[("Large txs", Some(100))]),
+            None
+        );
+        assert!(watchdog.problems().is_empty());
+    }
+
+    #[test]
+    fn alerts_on_lag_and_recovery() {
+        let watchdog = watchdog();
+        assert_eq!(watchdog.record_check(100, &[("Large txs", Some(99))]), None);
+
+        assert_eq!(
+            watchdog.record_check(107, &[("Large txs", Some(100))]),
+            Some(false)
+        );
+        assert_eq!(
+            watchdog.problems(),
+            ["Large txs is 7 blocks behind the tip"]
+        );
+        // Still unhealthy, nothing new to alert about
+        assert_eq!(
+            watchdog.record_check(108, &[("Large txs", Some(100))]),
+            None
+        );
+
+        assert_eq!(
+            watchdog.record_check(108, &[("Large txs", Some(102))]),
+            Some(true)
+        );
+        assert!(watchdog.problems().is_empty());
+    }
+
+    #[test]
+    fn alerts_when_chronik_is_unreachable() {
+        let watchdog = watchdog();
+        assert_eq!(watchdog.record_failed_check("connection refused"), None);
+        assert_eq!(watchdog.record_check(100, &[]), None);
+
+        assert_eq!(
+            watchdog.record_failed_check("connection refused"),
+            Some(false)
+        );
+        assert_eq!(
+            watchdog.problems(),
+            ["Chronik unreachable: connection refused"]
+        );
+        assert_eq!(watchdog.record_check(100, &[]), Some(true));
+    }
+}